@@ -0,0 +1,411 @@
+//! Capture box dimensions: aspect-ratio lock applied while dragging and the
+//! numeric X / Y / W / H entry opened from the size label.
+
+/// A rectangle as (left, top, right, bottom).
+pub type Rect = (f32, f32, f32, f32);
+
+/// Aspect ratio the capture box is locked to.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AspectRatio {
+    #[default]
+    Free,
+    Square,
+    Standard,
+    Wide,
+    /// Width / height of the selection at the time the lock was chosen.
+    Custom(f32),
+}
+
+impl AspectRatio {
+    /// Width divided by height, `None` when unlocked.
+    pub fn ratio(self) -> Option<f32> {
+        match self {
+            AspectRatio::Free => None,
+            AspectRatio::Square => Some(1.0),
+            AspectRatio::Standard => Some(4.0 / 3.0),
+            AspectRatio::Wide => Some(16.0 / 9.0),
+            AspectRatio::Custom(ratio) => Some(ratio),
+        }
+    }
+
+    /// The next lock in the cycle Free → 1:1 → 4:3 → 16:9 → custom → Free;
+    /// the custom lock keeps the proportions of `current` (width, height).
+    pub fn next(self, (width, height): (f32, f32)) -> Self {
+        match self {
+            AspectRatio::Free => AspectRatio::Square,
+            AspectRatio::Square => AspectRatio::Standard,
+            AspectRatio::Standard => AspectRatio::Wide,
+            AspectRatio::Wide if width > 0.0 && height > 0.0 => AspectRatio::Custom(width / height),
+            AspectRatio::Wide | AspectRatio::Custom(_) => AspectRatio::Free,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            AspectRatio::Free => "Free".to_string(),
+            AspectRatio::Square => "1:1".to_string(),
+            AspectRatio::Standard => "4:3".to_string(),
+            AspectRatio::Wide => "16:9".to_string(),
+            AspectRatio::Custom(ratio) => format!("{:.2}:1", ratio),
+        }
+    }
+}
+
+/// Sides of a rectangle that follow the pointer while it is resized.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sides {
+    pub left: bool,
+    pub top: bool,
+    pub right: bool,
+    pub bottom: bool,
+}
+
+/// Adjust `rect` to width / height = `ratio` by moving only the sides being
+/// dragged. Dragging one edge sizes the other axis symmetrically around the
+/// center; dragging a corner grows whichever axis is too short.
+pub fn fit_ratio(rect: Rect, sides: Sides, ratio: f32) -> Rect {
+    let (mut left, mut top, mut right, mut bottom) = rect;
+    let (width, height) = (right - left, bottom - top);
+    let horizontal = sides.left || sides.right;
+    let vertical = sides.top || sides.bottom;
+    if ratio <= 0.0 || width <= 0.0 || height <= 0.0 || !(horizontal || vertical) {
+        return rect;
+    }
+
+    if horizontal && !vertical {
+        let center = (top + bottom) / 2.0;
+        top = center - width / ratio / 2.0;
+        bottom = center + width / ratio / 2.0;
+    } else if vertical && !horizontal {
+        let center = (left + right) / 2.0;
+        left = center - height * ratio / 2.0;
+        right = center + height * ratio / 2.0;
+    } else if width / height > ratio {
+        if sides.top {
+            top = bottom - width / ratio;
+        } else {
+            bottom = top + width / ratio;
+        }
+    } else if sides.left {
+        left = right - height * ratio;
+    } else {
+        right = left + height * ratio;
+    }
+    (left, top, right, bottom)
+}
+
+/// Clamp the sides of `rect` to a screen of `size`, then fit it to `ratio`
+/// (when locked) without leaving the screen: if the fitted box would cross
+/// an edge it is scaled down around the sides that stay put, so the locked
+/// ratio still holds.
+pub fn fit_on_screen(
+    rect: Rect,
+    sides: Sides,
+    ratio: Option<f32>,
+    (screen_width, screen_height): (f32, f32),
+) -> Rect {
+    let (left, top, right, bottom) = rect;
+    let clamped = (
+        left.max(0.0),
+        top.max(0.0),
+        right.min(screen_width),
+        bottom.min(screen_height),
+    );
+    let Some(ratio) = ratio else {
+        return clamped;
+    };
+    let (left, top, right, bottom) = fit_ratio(clamped, sides, ratio);
+
+    // 缩放中心：不动的边，两边都不动时为中心
+    let anchor =
+        |low: f32, high: f32, low_moves: bool, high_moves: bool| match (low_moves, high_moves) {
+            (true, false) => high,
+            (false, true) => low,
+            _ => (low + high) / 2.0,
+        };
+    let ax = anchor(left, right, sides.left, sides.right);
+    let ay = anchor(top, bottom, sides.top, sides.bottom);
+    let limit = |extent: f32, room: f32| if extent > room { room / extent } else { 1.0 };
+    let scale = limit(ax - left, ax)
+        .min(limit(right - ax, screen_width - ax))
+        .min(limit(ay - top, ay))
+        .min(limit(bottom - ay, screen_height - ay))
+        .max(0.0);
+    (
+        ax - (ax - left) * scale,
+        ay - (ay - top) * scale,
+        ax + (right - ax) * scale,
+        ay + (bottom - ay) * scale,
+    )
+}
+
+/// Keep `rect` on a screen of `size` without changing its size, pushing it
+/// back from whichever edge it crosses (the right and bottom edges win when
+/// it is larger than the screen).
+pub fn clamp_to_screen(rect: Rect, (screen_width, screen_height): (f32, f32)) -> Rect {
+    let (left, top, right, bottom) = rect;
+    let (width, height) = (right - left, bottom - top);
+    let left = if right >= screen_width {
+        screen_width - width
+    } else {
+        left.max(0.0)
+    };
+    let top = if bottom >= screen_height {
+        screen_height - height
+    } else {
+        top.max(0.0)
+    };
+    (left, top, left + width, top + height)
+}
+
+/// Field of the numeric size entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    X,
+    Y,
+    Width,
+    Height,
+}
+
+pub const FIELDS: [Field; 4] = [Field::X, Field::Y, Field::Width, Field::Height];
+
+impl Field {
+    pub fn label(self) -> &'static str {
+        match self {
+            Field::X => "X",
+            Field::Y => "Y",
+            Field::Width => "W",
+            Field::Height => "H",
+        }
+    }
+
+    fn index(self) -> usize {
+        FIELDS.iter().position(|field| *field == self).unwrap_or(0)
+    }
+}
+
+// 每个字段最多输入的位数
+const MAX_DIGITS: usize = 5;
+
+/// Numeric entry of the capture box position and size in screenshot pixels.
+#[derive(Debug, Clone)]
+pub struct SizeEntry {
+    pub field: Field,
+    values: [String; 4],
+    // 刚切换到的字段，第一次输入时替换原值
+    fresh: bool,
+}
+
+impl SizeEntry {
+    /// Start editing the width of a box at (x, y) with the given size.
+    pub fn new((x, y, width, height): (u32, u32, u32, u32)) -> Self {
+        Self {
+            field: Field::Width,
+            values: [x, y, width, height].map(|value| value.to_string()),
+            fresh: true,
+        }
+    }
+
+    pub fn value(&self, field: Field) -> &str {
+        &self.values[field.index()]
+    }
+
+    pub fn select(&mut self, field: Field) {
+        self.field = field;
+        self.fresh = true;
+    }
+
+    /// Move to the next (or previous) field, wrapping around.
+    pub fn cycle(&mut self, forward: bool) {
+        let step = if forward { 1 } else { FIELDS.len() - 1 };
+        self.select(FIELDS[(self.field.index() + step) % FIELDS.len()]);
+    }
+
+    /// Type a digit into the current field; with a locked `ratio` editing
+    /// the width updates the height and vice versa.
+    pub fn input(&mut self, digit: char, ratio: Option<f32>) {
+        if !digit.is_ascii_digit() {
+            return;
+        }
+        let value = &mut self.values[self.field.index()];
+        if self.fresh {
+            value.clear();
+            self.fresh = false;
+        }
+        if value.len() < MAX_DIGITS {
+            value.push(digit);
+        }
+        self.apply_ratio(ratio);
+    }
+
+    pub fn backspace(&mut self, ratio: Option<f32>) {
+        self.fresh = false;
+        self.values[self.field.index()].pop();
+        self.apply_ratio(ratio);
+    }
+
+    /// The entered (x, y, width, height), `None` while a field is empty or
+    /// a dimension is zero.
+    pub fn rect(&self) -> Option<(u32, u32, u32, u32)> {
+        let [x, y, width, height] = self.parsed()?;
+        (width > 0 && height > 0).then_some((x, y, width, height))
+    }
+
+    fn parsed(&self) -> Option<[u32; 4]> {
+        let mut parsed = [0; 4];
+        for (value, text) in parsed.iter_mut().zip(&self.values) {
+            *value = text.parse().ok()?;
+        }
+        Some(parsed)
+    }
+
+    /// Re-derive the height (or the width, while the height is being
+    /// edited) from a locked `ratio`.
+    pub fn apply_ratio(&mut self, ratio: Option<f32>) {
+        let Some(ratio) = ratio.filter(|ratio| *ratio > 0.0) else {
+            return;
+        };
+        let width: Option<f32> = self.value(Field::Width).parse().ok();
+        let height: Option<f32> = self.value(Field::Height).parse().ok();
+        match (self.field, width, height) {
+            (Field::Height, _, Some(height)) => {
+                self.values[Field::Width.index()] = ((height * ratio).round() as u32).to_string();
+            }
+            (_, Some(width), _) => {
+                self.values[Field::Height.index()] = ((width / ratio).round() as u32).to_string();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio_cycle_ends_with_current_proportions() {
+        let mut ratio = AspectRatio::Free;
+        let mut labels = Vec::new();
+        for _ in 0..5 {
+            ratio = ratio.next((300.0, 200.0));
+            labels.push(ratio.label());
+        }
+        assert_eq!(labels, ["1:1", "4:3", "16:9", "1.50:1", "Free"]);
+    }
+
+    #[test]
+    fn fit_ratio_moves_only_dragged_sides() {
+        let sides = |left, top, right, bottom| Sides {
+            left,
+            top,
+            right,
+            bottom,
+        };
+        // 拖动右下角：宽度过大时加高下边
+        let rect = fit_ratio(
+            (0.0, 0.0, 160.0, 50.0),
+            sides(false, false, true, true),
+            16.0 / 9.0,
+        );
+        assert_eq!(rect, (0.0, 0.0, 160.0, 90.0));
+        // 拖动左上角：高度过大时向左加宽
+        let rect = fit_ratio(
+            (100.0, 0.0, 200.0, 200.0),
+            sides(true, true, false, false),
+            1.0,
+        );
+        assert_eq!(rect, (0.0, 0.0, 200.0, 200.0));
+        // 只拖动右边：高度以中心对称变化
+        let rect = fit_ratio(
+            (0.0, 40.0, 40.0, 60.0),
+            sides(false, false, true, false),
+            1.0,
+        );
+        assert_eq!(rect, (0.0, 30.0, 40.0, 70.0));
+    }
+
+    #[test]
+    fn fit_on_screen_keeps_ratio_at_edges() {
+        let screen = (800.0, 600.0);
+        // 拖动右下角：补齐高度后会超出下边，整体以左上角为中心缩小
+        let corner = Sides {
+            right: true,
+            bottom: true,
+            ..Sides::default()
+        };
+        let (left, top, right, bottom) =
+            fit_on_screen((0.0, 420.0, 700.0, 500.0), corner, Some(16.0 / 9.0), screen);
+        assert_eq!((left, top, bottom), (0.0, 420.0, 600.0));
+        assert!(((right - left) / (bottom - top) - 16.0 / 9.0).abs() < 1e-3);
+
+        // 拖出屏幕左边：先把左边限制在屏幕内，再按比例调整
+        let left_corner = Sides {
+            left: true,
+            top: true,
+            ..Sides::default()
+        };
+        let rect = fit_on_screen(
+            (-100.0, 100.0, 400.0, 500.0),
+            left_corner,
+            Some(1.0),
+            screen,
+        );
+        assert_eq!(rect, (0.0, 100.0, 400.0, 500.0));
+
+        // 只拖动右边：碰到上下边时以左边和垂直中心缩小
+        let right_edge = Sides {
+            right: true,
+            ..Sides::default()
+        };
+        let (left, top, right, bottom) =
+            fit_on_screen((100.0, 250.0, 800.0, 350.0), right_edge, Some(1.0), screen);
+        assert_eq!((left, top, bottom), (100.0, 0.0, 600.0));
+        assert!((right - 700.0).abs() < 1e-3);
+
+        // 不锁定比例时只限制每条边
+        assert_eq!(
+            fit_on_screen((-10.0, 20.0, 900.0, 700.0), corner, None, screen),
+            (0.0, 20.0, 800.0, 600.0)
+        );
+    }
+
+    #[test]
+    fn clamp_keeps_size_inside_screen() {
+        let screen = (800.0, 600.0);
+        assert_eq!(
+            clamp_to_screen((-30.0, 100.0, 170.0, 200.0), screen),
+            (0.0, 100.0, 200.0, 200.0)
+        );
+        assert_eq!(
+            clamp_to_screen((700.0, 550.0, 900.0, 650.0), screen),
+            (600.0, 500.0, 800.0, 600.0)
+        );
+        assert_eq!(
+            clamp_to_screen((10.0, 20.0, 110.0, 120.0), screen),
+            (10.0, 20.0, 110.0, 120.0)
+        );
+    }
+
+    #[test]
+    fn entry_replaces_then_appends_and_follows_ratio() {
+        let mut entry = SizeEntry::new((10, 20, 300, 200));
+        for digit in "640".chars() {
+            entry.input(digit, Some(16.0 / 9.0));
+        }
+        assert_eq!(entry.rect(), Some((10, 20, 640, 360)));
+
+        entry.cycle(true);
+        assert_eq!(entry.field, Field::Height);
+        entry.backspace(None);
+        assert_eq!(entry.value(Field::Height), "36");
+        entry.input('x', None);
+        entry.input('0', None);
+        assert_eq!(entry.rect(), Some((10, 20, 640, 360)));
+
+        entry.select(Field::X);
+        entry.backspace(None);
+        entry.backspace(None);
+        assert_eq!(entry.rect(), None);
+    }
+}
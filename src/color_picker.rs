@@ -0,0 +1,102 @@
+//! Color Picker tool: sampling a pixel of the original screenshot and the
+//! hex / RGB / HSL text shown for it.
+
+/// The RGB color of the screenshot pixel containing `(x, y)` (screenshot
+/// coordinates), `None` outside the image.
+pub fn pixel_at(rgba: &[u8], width: u32, height: u32, (x, y): (f32, f32)) -> Option<[u8; 3]> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    let (column, row) = (x.floor() as u32, y.floor() as u32);
+    if column >= width || row >= height {
+        return None;
+    }
+    let index = (row as usize * width as usize + column as usize) * 4;
+    let pixel = rgba.get(index..index + 3)?;
+    Some([pixel[0], pixel[1], pixel[2]])
+}
+
+/// "#RRGGBB"
+pub fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+/// Hue in degrees, saturation and lightness in percent, all rounded.
+pub fn hsl([r, g, b]: [u8; 3]) -> (u32, u32, u32) {
+    let [r, g, b] = [r, g, b].map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0, 0, (lightness * 100.0).round() as u32);
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (
+        hue.round() as u32 % 360,
+        (saturation * 100.0).round() as u32,
+        (lightness * 100.0).round() as u32,
+    )
+}
+
+/// The three readout lines: hex, RGB and HSL.
+pub fn describe(color: [u8; 3]) -> String {
+    let [r, g, b] = color;
+    let (h, s, l) = hsl(color);
+    format!(
+        "{}\nRGB {}, {}, {}\nHSL {}°, {}%, {}%",
+        hex(color),
+        r,
+        g,
+        b,
+        h,
+        s,
+        l
+    )
+}
+
+/// Annotation color (0.0-1.0 per channel) of a sampled pixel.
+pub fn to_annotation_color(color: [u8; 3]) -> [f32; 3] {
+    color.map(|c| c as f32 / 255.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_the_pixel_containing_the_point() {
+        // 2x2 图像：左上红、右上绿、左下蓝、右下白
+        let rgba = [
+            255, 0, 0, 255, 0, 255, 0, 255, //
+            0, 0, 255, 255, 255, 255, 255, 255,
+        ];
+        assert_eq!(pixel_at(&rgba, 2, 2, (0.9, 0.2)), Some([255, 0, 0]));
+        assert_eq!(pixel_at(&rgba, 2, 2, (1.0, 0.0)), Some([0, 255, 0]));
+        assert_eq!(pixel_at(&rgba, 2, 2, (0.5, 1.5)), Some([0, 0, 255]));
+        assert_eq!(pixel_at(&rgba, 2, 2, (1.99, 1.99)), Some([255, 255, 255]));
+        assert_eq!(pixel_at(&rgba, 2, 2, (2.0, 0.0)), None);
+        assert_eq!(pixel_at(&rgba, 2, 2, (-0.1, 0.0)), None);
+    }
+
+    #[test]
+    fn formats_hex_rgb_and_hsl() {
+        assert_eq!(hex([30, 144, 255]), "#1E90FF");
+        assert_eq!(hsl([30, 144, 255]), (210, 100, 56));
+        assert_eq!(hsl([255, 0, 0]), (0, 100, 50));
+        assert_eq!(hsl([255, 0, 128]), (330, 100, 50));
+        assert_eq!(hsl([128, 128, 128]), (0, 0, 50));
+        assert_eq!(
+            describe([0, 128, 0]),
+            "#008000\nRGB 0, 128, 0\nHSL 120°, 100%, 25%"
+        );
+    }
+}
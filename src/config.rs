@@ -0,0 +1,262 @@
+//! 应用配置：从 `wgpu-screenshot.toml` 读取，文件缺失或解析失败时使用默认值
+
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// 配置文件名，依次在可执行文件目录和当前工作目录中查找
+pub const CONFIG_FILE_NAME: &str = "wgpu-screenshot.toml";
+
+/// Top level application configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub pen: PenConfig,
+    pub export: ExportConfig,
+    pub fonts: FontConfig,
+    pub stamps: StampConfig,
+    pub history: HistoryConfig,
+    pub capture: CaptureConfig,
+    pub snap: SnapConfig,
+    pub detection: DetectionConfig,
+    pub measure: MeasureConfig,
+}
+
+/// Freehand pen stroke processing settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PenConfig {
+    /// 平滑强度，0.0 表示不平滑，1.0 最强
+    pub smoothing: f32,
+    /// Ramer–Douglas–Peucker 简化容差（像素），0 表示不简化
+    pub simplify_tolerance: f32,
+    /// 是否在绘制过程中实时平滑（否则只在笔画完成时处理）
+    pub live_smoothing: bool,
+}
+
+impl Default for PenConfig {
+    fn default() -> Self {
+        Self {
+            smoothing: 0.5,
+            simplify_tolerance: 1.0,
+            live_smoothing: false,
+        }
+    }
+}
+
+/// Export (Save button) settings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// 导出PNG的保存目录，未设置时保存到当前工作目录
+    pub directory: Option<PathBuf>,
+}
+
+/// Font sources and fallback settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FontConfig {
+    /// 是否加载系统字体（中日韩文字和表情符号通常来自系统字体）
+    pub system_fonts: bool,
+    /// 额外加载的字体目录
+    pub directories: Vec<PathBuf>,
+    /// 缺字时依次尝试的字体族，排在平台默认回退列表之前
+    pub fallback: Vec<String>,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            system_fonts: true,
+            directories: Vec::new(),
+            fallback: [
+                "Noto Sans CJK SC",
+                "Source Han Sans SC",
+                "Microsoft YaHei",
+                "PingFang SC",
+                "Noto Color Emoji",
+                "Segoe UI Emoji",
+                "Apple Color Emoji",
+            ]
+            .map(String::from)
+            .to_vec(),
+        }
+    }
+}
+
+/// Stamp tool settings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StampConfig {
+    /// 自定义印章目录，其中的 *.svg 文件排在内置印章之后
+    pub directory: Option<PathBuf>,
+}
+
+/// Undo/redo history limits
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// 最多保留的撤销步数，0 表示不限制
+    pub max_steps: usize,
+    /// 撤销历史最多占用的内存（MB），0 表示不限制
+    pub max_memory_mb: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: 0,
+            max_memory_mb: 64,
+        }
+    }
+}
+
+/// Capture region settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    /// 选区移动或缩放后，超出选区的标注如何处理
+    pub outside_annotations: OutsideAnnotations,
+    /// 固定尺寸预设，按 P / Shift+P 依次切换
+    pub presets: Vec<CapturePreset>,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            outside_annotations: OutsideAnnotations::default(),
+            presets: vec![
+                CapturePreset::new("720p", 1280, 720),
+                CapturePreset::new("1080p", 1920, 1080),
+                CapturePreset::new("Social card", 1200, 630),
+                CapturePreset::new("Square", 1080, 1080),
+            ],
+        }
+    }
+}
+
+/// A fixed capture size in screenshot pixels
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapturePreset {
+    /// 显示在尺寸标签中的名称
+    #[serde(default)]
+    pub name: Option<String>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CapturePreset {
+    fn new(name: &str, width: u32, height: u32) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            width,
+            height,
+        }
+    }
+
+    /// "name (W×H)", or just "W×H" without a name
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({}×{})", name, self.width, self.height),
+            None => format!("{}×{}", self.width, self.height),
+        }
+    }
+}
+
+/// What to do with annotations that fall outside the capture region
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutsideAnnotations {
+    /// 屏幕上在选区边缘裁剪，与导出结果一致（标注本身保留）
+    Clip,
+    /// 照常显示
+    Keep,
+    /// 照常显示，并用警告色框出超出选区的标注
+    #[default]
+    Warn,
+}
+
+/// Snapping of moved elements and of the capture region
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SnapConfig {
+    /// 是否默认吸附；拖动时按住 Alt 临时切换
+    pub enabled: bool,
+    /// 吸附距离（逻辑像素）
+    pub threshold: f32,
+    /// 网格间距（逻辑像素），0 表示不吸附到网格
+    pub grid: f32,
+}
+
+impl Default for SnapConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            threshold: 6.0,
+            grid: 0.0,
+        }
+    }
+}
+
+/// Automatic detection of windows and UI elements before a region is selected
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DetectionConfig {
+    /// 是否在框选前高亮鼠标下检测到的区域，单击即可选中
+    pub enabled: bool,
+    /// 相邻像素每个颜色通道允许的差值，越大区域越容易连成一片
+    pub tolerance: u8,
+    /// 区域的最小宽高（截图像素）
+    pub min_size: u32,
+}
+
+impl Default for DetectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            tolerance: 12,
+            min_size: 24,
+        }
+    }
+}
+
+/// Measure tool settings
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MeasureConfig {
+    /// 测量线默认保留为标注（带尺寸文字）；再次点击测量按钮切换
+    pub keep_as_annotation: bool,
+}
+
+impl AppConfig {
+    /// Load the configuration file, falling back to defaults
+    pub fn load() -> Self {
+        let Some(path) = Self::find_config_file() else {
+            return Self::default();
+        };
+
+        match std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| toml::from_str::<AppConfig>(&text).map_err(|e| e.to_string()))
+        {
+            Ok(config) => {
+                println!("🚀 已加载配置文件: {}", path.display());
+                config
+            }
+            Err(e) => {
+                eprintln!("配置文件 {} 无效，使用默认配置: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    fn find_config_file() -> Option<PathBuf> {
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(|dir| dir.join(CONFIG_FILE_NAME)));
+        let cwd = std::env::current_dir()
+            .ok()
+            .map(|dir| dir.join(CONFIG_FILE_NAME));
+
+        exe_dir.into_iter().chain(cwd).find(|path| path.is_file())
+    }
+}
@@ -0,0 +1,227 @@
+//! Detection of likely capture regions (windows, panels, buttons) in the
+//! screenshot itself: connected areas of similar color whose bounding box is
+//! outlined by the area, i.e. solid rectangles and rectangular frames.
+
+/// A rectangle in screenshot pixels as (left, top, right, bottom), right and
+/// bottom exclusive.
+pub type Rect = (u32, u32, u32, u32);
+
+/// Detector thresholds.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Largest per-channel difference between neighbouring pixels of one area.
+    pub tolerance: u8,
+    /// Smallest accepted width and height.
+    pub min_size: u32,
+    /// Fraction of each bounding box side the area has to cover.
+    pub edge_coverage: f32,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            tolerance: 12,
+            min_size: 24,
+            edge_coverage: 0.9,
+        }
+    }
+}
+
+// 差别在此范围内的两个矩形视为同一区域（例如窗口边框和它的内部）
+const DUPLICATE_DISTANCE: u32 = 2;
+
+/// Rectangular regions found in an RGBA image, largest first.
+pub fn detect_regions(rgba: &[u8], width: u32, height: u32, options: &Options) -> Vec<Rect> {
+    let (w, h) = (width as usize, height as usize);
+    if w == 0 || h == 0 || rgba.len() < w * h * 4 {
+        return Vec::new();
+    }
+    let similar = |a: usize, b: usize| {
+        (0..3).all(|c| rgba[a * 4 + c].abs_diff(rgba[b * 4 + c]) <= options.tolerance)
+    };
+
+    // 1. 把颜色相近的相邻像素连成区域，记录每个区域的包围盒
+    let mut labels = vec![u32::MAX; w * h];
+    let mut bounds: Vec<Rect> = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..w * h {
+        if labels[start] != u32::MAX {
+            continue;
+        }
+        let id = bounds.len() as u32;
+        let (x, y) = ((start % w) as u32, (start / w) as u32);
+        let (mut left, mut top, mut right, mut bottom) = (x, y, x, y);
+        labels[start] = id;
+        stack.push(start);
+        while let Some(pixel) = stack.pop() {
+            let (x, y) = (pixel % w, pixel / w);
+            left = left.min(x as u32);
+            right = right.max(x as u32);
+            top = top.min(y as u32);
+            bottom = bottom.max(y as u32);
+            let neighbours = [
+                (x > 0).then(|| pixel - 1),
+                (x + 1 < w).then(|| pixel + 1),
+                (y > 0).then(|| pixel - w),
+                (y + 1 < h).then(|| pixel + w),
+            ];
+            for next in neighbours.into_iter().flatten() {
+                if labels[next] == u32::MAX && similar(pixel, next) {
+                    labels[next] = id;
+                    stack.push(next);
+                }
+            }
+        }
+        bounds.push((left, top, right + 1, bottom + 1));
+    }
+
+    // 2. 足够大、并且沿包围盒四条边分布的区域是矩形（实心矩形或矩形边框）
+    let covers = |id: u32, pixels: &mut dyn Iterator<Item = (u32, u32)>, length: u32| {
+        let count = pixels
+            .filter(|&(x, y)| labels[y as usize * w + x as usize] == id)
+            .count();
+        count as f32 >= length as f32 * options.edge_coverage
+    };
+    let mut regions: Vec<Rect> = Vec::new();
+    for (id, &(left, top, right, bottom)) in bounds.iter().enumerate() {
+        let id = id as u32;
+        let (rect_width, rect_height) = (right - left, bottom - top);
+        if rect_width < options.min_size || rect_height < options.min_size {
+            continue;
+        }
+        let rectangular = covers(id, &mut (left..right).map(|x| (x, top)), rect_width)
+            && covers(id, &mut (left..right).map(|x| (x, bottom - 1)), rect_width)
+            && covers(id, &mut (top..bottom).map(|y| (left, y)), rect_height)
+            && covers(id, &mut (top..bottom).map(|y| (right - 1, y)), rect_height);
+        if rectangular {
+            regions.push((left, top, right, bottom));
+        }
+    }
+
+    // 3. 去掉几乎重合的矩形，保留较大的
+    regions.sort_by_key(|&rect| std::cmp::Reverse(area(rect)));
+    let mut unique: Vec<Rect> = Vec::new();
+    for rect in regions {
+        if !unique.iter().any(|&kept| near(kept, rect)) {
+            unique.push(rect);
+        }
+    }
+    unique
+}
+
+/// Add `extra` regions (e.g. window rectangles reported by the window
+/// system) that are not already near one of `regions`.
+pub fn merge_regions(regions: &mut Vec<Rect>, extra: impl IntoIterator<Item = Rect>) {
+    for rect in extra {
+        if !regions.iter().any(|&kept| near(kept, rect)) {
+            regions.push(rect);
+        }
+    }
+}
+
+/// The smallest region containing `point`.
+pub fn region_at(regions: &[Rect], (x, y): (f32, f32)) -> Option<Rect> {
+    regions
+        .iter()
+        .filter(|&&(left, top, right, bottom)| {
+            x >= left as f32 && x < right as f32 && y >= top as f32 && y < bottom as f32
+        })
+        .min_by_key(|&&rect| area(rect))
+        .copied()
+}
+
+fn area((left, top, right, bottom): Rect) -> u64 {
+    (right - left) as u64 * (bottom - top) as u64
+}
+
+fn near(a: Rect, b: Rect) -> bool {
+    a.0.abs_diff(b.0) <= DUPLICATE_DISTANCE
+        && a.1.abs_diff(b.1) <= DUPLICATE_DISTANCE
+        && a.2.abs_diff(b.2) <= DUPLICATE_DISTANCE
+        && a.3.abs_diff(b.3) <= DUPLICATE_DISTANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: [u8; 4] = [40, 40, 40, 255];
+
+    struct Fixture {
+        width: u32,
+        rgba: Vec<u8>,
+    }
+
+    impl Fixture {
+        fn new(width: u32, height: u32) -> Self {
+            Self {
+                width,
+                rgba: BACKGROUND.repeat((width * height) as usize),
+            }
+        }
+
+        fn fill(&mut self, (left, top, right, bottom): Rect, color: [u8; 4]) {
+            for y in top..bottom {
+                for x in left..right {
+                    let index = ((y * self.width + x) * 4) as usize;
+                    self.rgba[index..index + 4].copy_from_slice(&color);
+                }
+            }
+        }
+
+        fn regions(&self) -> Vec<Rect> {
+            let height = self.rgba.len() as u32 / 4 / self.width;
+            detect_regions(&self.rgba, self.width, height, &Options::default())
+        }
+    }
+
+    #[test]
+    fn finds_framed_window_and_background() {
+        let mut image = Fixture::new(200, 150);
+        // 带 1 像素黑色边框的白色窗口：边框和内部只保留一个（较大的）矩形
+        image.fill((30, 20, 130, 100), [0, 0, 0, 255]);
+        image.fill((31, 21, 129, 99), [250, 250, 250, 255]);
+
+        let regions = image.regions();
+        assert_eq!(regions, vec![(0, 0, 200, 150), (30, 20, 130, 100)]);
+        assert_eq!(region_at(&regions, (60.0, 50.0)), Some((30, 20, 130, 100)));
+        assert_eq!(region_at(&regions, (150.0, 120.0)), Some((0, 0, 200, 150)));
+    }
+
+    #[test]
+    fn nested_panels_pick_the_smallest() {
+        let mut image = Fixture::new(200, 200);
+        image.fill((20, 20, 180, 180), [200, 200, 220, 255]);
+        image.fill((40, 40, 100, 80), [30, 120, 240, 255]);
+        // 面板中的文字会留下空洞，只要边缘完整面板仍然是矩形
+        image.fill((120, 100, 126, 110), [0, 0, 0, 255]);
+
+        let regions = image.regions();
+        assert_eq!(region_at(&regions, (50.0, 50.0)), Some((40, 40, 100, 80)));
+        assert_eq!(
+            region_at(&regions, (150.0, 150.0)),
+            Some((20, 20, 180, 180))
+        );
+    }
+
+    #[test]
+    fn merge_skips_regions_already_found() {
+        let mut regions = vec![(0, 0, 200, 150), (30, 20, 130, 100)];
+        merge_regions(&mut regions, [(31, 21, 129, 99), (140, 10, 190, 60)]);
+        assert_eq!(
+            regions,
+            vec![(0, 0, 200, 150), (30, 20, 130, 100), (140, 10, 190, 60)]
+        );
+    }
+
+    #[test]
+    fn ignores_small_and_non_rectangular_areas() {
+        let mut image = Fixture::new(120, 120);
+        image.fill((5, 5, 15, 15), [255, 0, 0, 255]);
+        for y in 40..100 {
+            image.fill((40, y, 40 + (y - 40) + 1, y + 1), [0, 200, 0, 255]);
+        }
+
+        assert_eq!(image.regions(), vec![(0, 0, 120, 120)]);
+    }
+}
@@ -0,0 +1,103 @@
+//! 导出：把GPU读回的像素整理为RGBA并保存为PNG
+
+use std::path::{Path, PathBuf};
+
+/// Bytes per row for a texture-to-buffer copy of `width` RGBA pixels,
+/// rounded up to wgpu's copy alignment.
+pub fn padded_bytes_per_row(width: u32) -> u32 {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    (width * 4).div_ceil(align) * align
+}
+
+/// Strip the per-row padding of a texture readback and convert BGRA pixels
+/// to RGBA when `bgra` is set.
+pub fn unpad_rows(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    bgra: bool,
+) -> Vec<u8> {
+    let row_bytes = (width * 4) as usize;
+    let mut rgba = Vec::with_capacity(row_bytes * height as usize);
+    for row in data
+        .chunks(padded_bytes_per_row as usize)
+        .take(height as usize)
+    {
+        rgba.extend_from_slice(&row[..row_bytes]);
+    }
+    if bgra {
+        // 交换 B 和 R 通道
+        for pixel in rgba.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+    rgba
+}
+
+/// Output file `screenshot-<unix millis>.png` inside `directory`, or the
+/// current working directory when none is configured.
+pub fn output_path(directory: Option<&Path>) -> PathBuf {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let file_name = format!("screenshot-{}.png", millis);
+    match directory {
+        Some(dir) => dir.join(file_name),
+        None => PathBuf::from(file_name),
+    }
+}
+
+/// Save RGBA pixels as a PNG file
+pub fn save_png(path: &Path, width: u32, height: u32, rgba: Vec<u8>) -> Result<(), String> {
+    let image = image::RgbaImage::from_raw(width, height, rgba)
+        .ok_or_else(|| "像素数据大小与图像尺寸不匹配".to_string())?;
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    image.save(path).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_copy_alignment() {
+        // 10 像素 = 40 字节，补齐到 256 字节
+        assert_eq!(padded_bytes_per_row(10), 256);
+        assert_eq!(padded_bytes_per_row(64), 256);
+        assert_eq!(padded_bytes_per_row(65), 512);
+    }
+
+    #[test]
+    fn unpad_keeps_rows_in_order() {
+        let (width, height) = (3, 2);
+        let padded = padded_bytes_per_row(width);
+        let mut data = vec![0xEE; (padded * height) as usize];
+        for row in 0..height {
+            for byte in 0..width * 4 {
+                data[(row * padded + byte) as usize] = (row * 100 + byte) as u8;
+            }
+        }
+
+        let rgba = unpad_rows(&data, width, height, padded, false);
+        assert_eq!(rgba.len(), (width * 4 * height) as usize);
+        let expected: Vec<u8> = (0..height)
+            .flat_map(|row| (0..width * 4).map(move |byte| (row * 100 + byte) as u8))
+            .collect();
+        assert_eq!(rgba, expected);
+    }
+
+    #[test]
+    fn bgra_is_converted_to_rgba() {
+        let padded = padded_bytes_per_row(2);
+        let mut data = vec![0; padded as usize];
+        data[..8].copy_from_slice(&[1, 2, 3, 255, 10, 20, 30, 128]);
+        assert_eq!(
+            unpad_rows(&data, 2, 1, padded, true),
+            vec![3, 2, 1, 255, 30, 20, 10, 128]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Font database setup: bundled fonts (cargo feature `bundled-fonts`),
+//! system fonts, extra font directories from the config and the fallback
+//! chain used when a font lacks a glyph (CJK, emoji, ...).
+
+use glyphon::FontSystem;
+use glyphon::cosmic_text::{Fallback, PlatformFallback};
+use glyphon::fontdb::Database;
+use unicode_script::Script;
+
+use crate::config::FontConfig;
+
+/// 编译进可执行文件的字体
+#[cfg(feature = "bundled-fonts")]
+const BUNDLED_FONTS: &[(&str, &[u8])] =
+    &[("DejaVu Sans", include_bytes!("../fonts/DejaVuSans.ttf"))];
+
+/// Fallback chain: the families from the config first, then the platform
+/// defaults of cosmic-text. Fonts that match none of these are still tried
+/// in database order as a last resort by cosmic-text itself.
+struct ConfiguredFallback {
+    common: Vec<&'static str>,
+}
+
+impl ConfiguredFallback {
+    fn new(families: &[String]) -> Self {
+        // cosmic-text 需要 'static 的字体名；只在启动时创建一次，泄漏的内存很少
+        let mut common: Vec<&'static str> = families
+            .iter()
+            .map(|family| &*Box::leak(family.clone().into_boxed_str()))
+            .collect();
+        common.extend_from_slice(PlatformFallback.common_fallback());
+        Self { common }
+    }
+}
+
+impl Fallback for ConfiguredFallback {
+    fn common_fallback(&self) -> &[&'static str] {
+        &self.common
+    }
+
+    fn forbidden_fallback(&self) -> &[&'static str] {
+        PlatformFallback.forbidden_fallback()
+    }
+
+    fn script_fallback(&self, script: Script, locale: &str) -> &[&'static str] {
+        PlatformFallback.script_fallback(script, locale)
+    }
+}
+
+/// Build the font system from the configured sources.
+pub fn create_font_system(config: &FontConfig) -> FontSystem {
+    let mut db = Database::new();
+
+    #[cfg(feature = "bundled-fonts")]
+    for (name, data) in BUNDLED_FONTS {
+        println!("🚀 加载内置字体 {}，大小: {} 字节", name, data.len());
+        db.load_font_data(data.to_vec());
+    }
+
+    if config.system_fonts {
+        db.load_system_fonts();
+    }
+
+    for directory in &config.directories {
+        if directory.is_dir() {
+            db.load_fonts_dir(directory);
+        } else {
+            eprintln!("字体目录不存在，已跳过: {}", directory.display());
+        }
+    }
+
+    // 一个字体都没有时文字将完全不可见：最后再尝试一次系统字体
+    if db.is_empty() && !config.system_fonts {
+        eprintln!("没有可用的字体，尝试加载系统字体");
+        db.load_system_fonts();
+    }
+    if db.is_empty() {
+        eprintln!("没有找到任何字体，文字将无法显示");
+    }
+    println!("🚀 字体数据库中的字体数量: {}", db.len());
+
+    FontSystem::new_with_locale_and_db_and_fallback(
+        "en-US".to_string(),
+        db,
+        ConfiguredFallback::new(&config.fallback),
+    )
+}
@@ -0,0 +1,485 @@
+//! Undo/redo history as a log of operations on the document (add, remove,
+//! modify and reorder elements, change the capture region) instead of
+//! snapshots of the whole element list.
+//!
+//! A step is everything one user action changed, named for the history
+//! panel ("Add arrow", "Move text"). Edits recorded while a group is open
+//! (a drag or an eraser gesture) go into the same step, and repeated
+//! modifications of one element collapse into a single edit.
+
+use std::collections::VecDeque;
+
+/// Approximate memory held by a recorded value, used for the history budget.
+pub trait Footprint {
+    fn footprint(&self) -> usize;
+}
+
+/// The capture region as (min_x, min_y, max_x, max_y), `None` while no
+/// region is selected.
+pub type Region = Option<(f32, f32, f32, f32)>;
+
+/// One change to the document, with the data needed to apply it in both
+/// directions.
+#[derive(Debug, Clone)]
+pub enum Edit<T> {
+    Add { index: usize, element: T },
+    Remove { index: usize, element: T },
+    Modify { index: usize, before: T, after: T },
+    Reorder { from: usize, to: usize },
+    Region { before: Region, after: Region },
+}
+
+impl<T: Clone> Edit<T> {
+    fn apply(&self, elements: &mut Vec<T>, region: &mut Region) {
+        match self {
+            Edit::Add { index, element } => {
+                elements.insert((*index).min(elements.len()), element.clone());
+            }
+            Edit::Remove { index, .. } => {
+                if *index < elements.len() {
+                    elements.remove(*index);
+                }
+            }
+            Edit::Modify { index, after, .. } => {
+                if let Some(element) = elements.get_mut(*index) {
+                    *element = after.clone();
+                }
+            }
+            Edit::Reorder { from, to } => Self::move_element(elements, *from, *to),
+            Edit::Region { after, .. } => *region = *after,
+        }
+    }
+
+    fn revert(&self, elements: &mut Vec<T>, region: &mut Region) {
+        match self {
+            Edit::Add { index, .. } => {
+                if *index < elements.len() {
+                    elements.remove(*index);
+                }
+            }
+            Edit::Remove { index, element } => {
+                elements.insert((*index).min(elements.len()), element.clone());
+            }
+            Edit::Modify { index, before, .. } => {
+                if let Some(element) = elements.get_mut(*index) {
+                    *element = before.clone();
+                }
+            }
+            Edit::Reorder { from, to } => Self::move_element(elements, *to, *from),
+            Edit::Region { before, .. } => *region = *before,
+        }
+    }
+
+    fn move_element(elements: &mut Vec<T>, from: usize, to: usize) {
+        if from < elements.len() && to < elements.len() {
+            let element = elements.remove(from);
+            elements.insert(to, element);
+        }
+    }
+
+    /// The element values stored in this edit.
+    pub fn elements(&self) -> impl Iterator<Item = &T> {
+        let (first, second) = match self {
+            Edit::Add { element, .. } | Edit::Remove { element, .. } => (Some(element), None),
+            Edit::Modify { before, after, .. } => (Some(before), Some(after)),
+            Edit::Reorder { .. } | Edit::Region { .. } => (None, None),
+        };
+        first.into_iter().chain(second)
+    }
+}
+
+impl<T: Footprint> Edit<T> {
+    fn footprint(&self) -> usize {
+        let stored = match self {
+            Edit::Add { element, .. } | Edit::Remove { element, .. } => element.footprint(),
+            Edit::Modify { before, after, .. } => before.footprint() + after.footprint(),
+            Edit::Reorder { .. } | Edit::Region { .. } => 0,
+        };
+        std::mem::size_of::<Self>() + stored
+    }
+}
+
+#[derive(Debug)]
+struct Step<T> {
+    label: String,
+    edits: Vec<Edit<T>>,
+    footprint: usize,
+}
+
+impl<T: Footprint> Step<T> {
+    fn new(label: &str) -> Self {
+        Self {
+            label: label.to_string(),
+            edits: Vec::new(),
+            footprint: label.len(),
+        }
+    }
+
+    // 同一元素（或选区）的连续修改合并为一次：保留最早的 before，更新 after
+    fn push(&mut self, edit: Edit<T>) {
+        if let Edit::Region { after, .. } = &edit
+            && let Some(Edit::Region {
+                after: last_after, ..
+            }) = self.edits.last_mut()
+        {
+            *last_after = *after;
+            return;
+        }
+        if let Edit::Modify { index, after, .. } = &edit
+            && let Some(Edit::Modify {
+                index: last_index,
+                after: last_after,
+                ..
+            }) = self.edits.last_mut()
+            && last_index == index
+        {
+            self.footprint -= last_after.footprint();
+            self.footprint += after.footprint();
+            let Edit::Modify { after, .. } = edit else {
+                unreachable!()
+            };
+            *last_after = after;
+            return;
+        }
+        self.footprint += edit.footprint();
+        self.edits.push(edit);
+    }
+}
+
+/// Undo and redo stacks of recorded steps.
+#[derive(Debug)]
+pub struct History<T> {
+    undo: VecDeque<Step<T>>,
+    redo: Vec<Step<T>>,
+    group: Option<Step<T>>,
+    /// 最多保留的撤销步数，0 表示不限制
+    max_steps: usize,
+    /// 撤销和重做历史占用内存的上限（字节），0 表示不限制
+    max_bytes: usize,
+    bytes: usize,
+    /// 因超出限制而丢弃的最旧步骤数
+    evicted: usize,
+}
+
+impl<T: Clone + Footprint> History<T> {
+    pub fn new(max_steps: usize, max_bytes: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group: None,
+            max_steps,
+            max_bytes,
+            bytes: 0,
+            evicted: 0,
+        }
+    }
+
+    /// Record an edit that has already been applied to the element list,
+    /// as a step named `label` (or as part of the open group).
+    /// A new edit makes the redo history unreachable, so it is dropped.
+    pub fn record(&mut self, label: &str, edit: Edit<T>) {
+        self.clear_redo();
+        match &mut self.group {
+            Some(group) => group.push(edit),
+            None => {
+                let mut step = Step::new(label);
+                step.push(edit);
+                self.push_step(step);
+            }
+        }
+    }
+
+    /// Start collecting edits into one step named `label`, until `end_group`.
+    pub fn begin_group(&mut self, label: &str) {
+        if self.group.is_none() {
+            self.group = Some(Step::new(label));
+        }
+    }
+
+    /// Close the open group. A group without edits records nothing.
+    pub fn end_group(&mut self) {
+        if let Some(step) = self.group.take()
+            && !step.edits.is_empty()
+        {
+            self.push_step(step);
+        }
+    }
+
+    /// Revert the latest step. Returns its edits so the caller can refresh
+    /// whatever depends on the affected elements.
+    pub fn undo(&mut self, elements: &mut Vec<T>, region: &mut Region) -> Option<&[Edit<T>]> {
+        self.end_group();
+        let step = self.undo.pop_back()?;
+        for edit in step.edits.iter().rev() {
+            edit.revert(elements, region);
+        }
+        self.redo.push(step);
+        self.redo.last().map(|step| step.edits.as_slice())
+    }
+
+    /// Apply the latest undone step again.
+    pub fn redo(&mut self, elements: &mut Vec<T>, region: &mut Region) -> Option<&[Edit<T>]> {
+        self.end_group();
+        let step = self.redo.pop()?;
+        for edit in &step.edits {
+            edit.apply(elements, region);
+        }
+        self.undo.push_back(step);
+        self.undo.back().map(|step| step.edits.as_slice())
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.group.as_ref().is_some_and(|g| !g.edits.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Names of all steps, oldest first. The first `undo_len()` are applied,
+    /// the rest have been undone and can be redone.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.undo
+            .iter()
+            .chain(self.redo.iter().rev())
+            .map(|step| step.label.as_str())
+    }
+
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Number of oldest steps dropped over the limits. When non-zero, undoing
+    /// everything no longer returns to the initial state.
+    pub fn evicted(&self) -> usize {
+        self.evicted
+    }
+
+    /// Every element value still held by the history.
+    pub fn elements(&self) -> impl Iterator<Item = &T> {
+        self.undo
+            .iter()
+            .chain(&self.redo)
+            .chain(&self.group)
+            .flat_map(|step| &step.edits)
+            .flat_map(Edit::elements)
+    }
+
+    fn clear_redo(&mut self) {
+        for step in self.redo.drain(..) {
+            self.bytes -= step.footprint;
+        }
+    }
+
+    // 超出步数或内存限制时丢弃最旧的步骤（至少保留最新的一步）
+    fn push_step(&mut self, step: Step<T>) {
+        self.bytes += step.footprint;
+        self.undo.push_back(step);
+        while self.undo.len() > 1
+            && ((self.max_steps > 0 && self.undo.len() > self.max_steps)
+                || (self.max_bytes > 0 && self.bytes > self.max_bytes))
+        {
+            if let Some(oldest) = self.undo.pop_front() {
+                self.bytes -= oldest.footprint;
+                self.evicted += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    impl Footprint for String {
+        fn footprint(&self) -> usize {
+            self.len()
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn undo_and_redo_replay_each_kind_of_edit() {
+        let mut history = History::new(0, 0);
+        let mut elements = strings(&["a", "b"]);
+
+        elements.push("c".into());
+        history.record(
+            "edit",
+            Edit::Add {
+                index: 2,
+                element: "c".into(),
+            },
+        );
+        let removed = elements.remove(0);
+        history.record(
+            "edit",
+            Edit::Remove {
+                index: 0,
+                element: removed,
+            },
+        );
+        elements[0] = "B".into();
+        history.record(
+            "edit",
+            Edit::Modify {
+                index: 0,
+                before: "b".into(),
+                after: "B".into(),
+            },
+        );
+        elements.swap(0, 1);
+        history.record("edit", Edit::Reorder { from: 0, to: 1 });
+        assert_eq!(elements, strings(&["c", "B"]));
+
+        while history.undo(&mut elements, &mut None).is_some() {}
+        assert_eq!(elements, strings(&["a", "b"]));
+        while history.redo(&mut elements, &mut None).is_some() {}
+        assert_eq!(elements, strings(&["c", "B"]));
+    }
+
+    #[test]
+    fn group_coalesces_modifications_into_one_step() {
+        let mut history = History::new(0, 0);
+        let mut elements = strings(&["x0"]);
+
+        history.begin_group("drag");
+        for position in 1..=3 {
+            let before = elements[0].clone();
+            elements[0] = format!("x{position}");
+            history.record(
+                "edit",
+                Edit::Modify {
+                    index: 0,
+                    before,
+                    after: elements[0].clone(),
+                },
+            );
+        }
+        history.end_group();
+        assert_eq!(history.undo_len(), 1);
+
+        let edits = history.undo(&mut elements, &mut None).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(elements, strings(&["x0"]));
+    }
+
+    #[test]
+    fn empty_group_records_nothing() {
+        let mut history: History<String> = History::new(0, 0);
+        history.begin_group("drag");
+        history.end_group();
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut history = History::new(0, 0);
+        let mut elements = Vec::new();
+        elements.push("a".to_string());
+        history.record(
+            "edit",
+            Edit::Add {
+                index: 0,
+                element: "a".into(),
+            },
+        );
+        history.undo(&mut elements, &mut None);
+        assert_eq!(history.redo_len(), 1);
+
+        elements.push("b".to_string());
+        history.record(
+            "edit",
+            Edit::Add {
+                index: 0,
+                element: "b".into(),
+            },
+        );
+        assert_eq!(history.redo_len(), 0);
+        assert_eq!(history.undo_len(), 1);
+    }
+
+    #[test]
+    fn region_changes_coalesce_and_undo_with_elements() {
+        let mut history = History::new(0, 0);
+        let mut elements = strings(&["a"]);
+        let mut region = Some((0.0, 0.0, 10.0, 10.0));
+
+        history.begin_group("Move selection");
+        for offset in [5.0, 10.0] {
+            let before = region;
+            region = Some((offset, 0.0, offset + 10.0, 10.0));
+            history.record(
+                "",
+                Edit::Region {
+                    before,
+                    after: region,
+                },
+            );
+        }
+        history.end_group();
+        let removed = elements.remove(0);
+        history.record(
+            "edit",
+            Edit::Remove {
+                index: 0,
+                element: removed,
+            },
+        );
+
+        while history.undo(&mut elements, &mut region).is_some() {}
+        assert_eq!(region, Some((0.0, 0.0, 10.0, 10.0)));
+        assert_eq!(elements, strings(&["a"]));
+        let edits = history.redo(&mut elements, &mut region).unwrap();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(region, Some((10.0, 0.0, 20.0, 10.0)));
+    }
+
+    #[test]
+    fn labels_list_applied_then_undone_steps() {
+        let mut history = History::new(0, 0);
+        let mut elements = Vec::new();
+        for (index, name) in ["a", "b", "c"].into_iter().enumerate() {
+            elements.push(name.to_string());
+            history.record(
+                &format!("Add {name}"),
+                Edit::Add {
+                    index,
+                    element: name.to_string(),
+                },
+            );
+        }
+        history.undo(&mut elements, &mut None);
+        history.undo(&mut elements, &mut None);
+        let labels: Vec<_> = history.labels().collect();
+        assert_eq!(labels, ["Add a", "Add b", "Add c"]);
+        assert_eq!(history.undo_len(), 1);
+        assert_eq!(elements, strings(&["a"]));
+    }
+
+    #[test]
+    fn oldest_steps_are_dropped_over_the_limits() {
+        let mut by_steps = History::new(2, 0);
+        let mut by_bytes = History::new(0, 3 * std::mem::size_of::<Edit<String>>());
+        for (index, name) in ["a", "b", "c"].into_iter().enumerate() {
+            let edit = Edit::Add {
+                index,
+                element: name.to_string(),
+            };
+            by_steps.record("", edit.clone());
+            by_bytes.record("", edit);
+        }
+        assert_eq!(by_steps.undo_len(), 2);
+        assert_eq!(by_steps.evicted(), 1);
+        // 每步占用 size_of::<Edit>() + 1 字节，三步超出预算
+        assert_eq!(by_bytes.undo_len(), 2);
+        assert_eq!(by_bytes.evicted(), 1);
+    }
+}
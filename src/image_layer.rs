@@ -0,0 +1,117 @@
+//! Images pasted from the clipboard or dropped onto the window, placed on
+//! the capture as movable annotation layers.
+
+use std::path::Path;
+
+/// Decoded layer pixels: premultiplied RGBA, ready to upload to a texture.
+pub struct LayerImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl LayerImage {
+    /// Premultiply `image`, first scaling it down so that neither side
+    /// exceeds `max_dimension` (the GPU texture size limit).
+    pub fn new(image: image::RgbaImage, max_dimension: u32) -> Self {
+        let (width, height) = image.dimensions();
+        let image = if width > max_dimension || height > max_dimension {
+            let scale = max_dimension as f32 / width.max(height) as f32;
+            let new_width = ((width as f32 * scale).round() as u32).clamp(1, max_dimension);
+            let new_height = ((height as f32 * scale).round() as u32).clamp(1, max_dimension);
+            println!(
+                "🚀 图像 {}x{} 超过纹理限制，缩小到 {}x{}",
+                width, height, new_width, new_height
+            );
+            image::imageops::resize(
+                &image,
+                new_width,
+                new_height,
+                image::imageops::FilterType::Triangle,
+            )
+        } else {
+            image
+        };
+
+        let (width, height) = image.dimensions();
+        let mut rgba = image.into_raw();
+        premultiply(&mut rgba);
+        Self {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    /// Image data read from the system clipboard.
+    pub fn from_clipboard(data: arboard::ImageData, max_dimension: u32) -> Result<Self, String> {
+        let image = image::RgbaImage::from_raw(
+            data.width as u32,
+            data.height as u32,
+            data.bytes.into_owned(),
+        )
+        .ok_or_else(|| "剪贴板图像数据大小与尺寸不匹配".to_string())?;
+        Ok(Self::new(image, max_dimension))
+    }
+
+    /// Decode an image file (PNG, JPEG or BMP).
+    pub fn load(path: &Path, max_dimension: u32) -> Result<Self, String> {
+        let image = image::open(path).map_err(|e| e.to_string())?;
+        Ok(Self::new(image.into_rgba8(), max_dimension))
+    }
+}
+
+/// Multiply the color channels by alpha (the textured quad pipeline blends
+/// premultiplied colors).
+pub fn premultiply(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+/// Initial size of a layer in window pixels: one image pixel per capture
+/// pixel (`scale` = capture pixels per window pixel), shrunk to fit inside
+/// `max_size` while keeping the aspect ratio.
+pub fn fit_size(image_size: (u32, u32), scale: (f32, f32), max_size: (f32, f32)) -> (f32, f32) {
+    let width = image_size.0 as f32 / scale.0.max(f32::EPSILON);
+    let height = image_size.1 as f32 / scale.1.max(f32::EPSILON);
+    let fit = (max_size.0 / width).min(max_size.1 / height).min(1.0);
+    (width * fit, height * fit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn premultiply_scales_color_by_alpha() {
+        let mut rgba = vec![255, 128, 0, 255, 255, 128, 0, 128, 255, 255, 255, 0];
+        premultiply(&mut rgba);
+        assert_eq!(rgba, vec![255, 128, 0, 255, 128, 64, 0, 128, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn fit_size_keeps_capture_pixels_and_aspect_ratio() {
+        // 2x 缩放的屏幕上，一个图像像素对应半个窗口像素
+        assert_eq!(
+            fit_size((200, 100), (2.0, 2.0), (1000.0, 1000.0)),
+            (100.0, 50.0)
+        );
+        // 放不下时等比缩小
+        assert_eq!(
+            fit_size((400, 200), (1.0, 1.0), (100.0, 100.0)),
+            (100.0, 50.0)
+        );
+    }
+
+    #[test]
+    fn large_images_are_scaled_to_the_texture_limit() {
+        let image = image::RgbaImage::new(300, 100);
+        let layer = LayerImage::new(image, 150);
+        assert_eq!((layer.width, layer.height), (150, 50));
+        assert_eq!(layer.rgba.len(), 150 * 50 * 4);
+    }
+}
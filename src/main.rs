@@ -3,11 +3,35 @@
     windows_subsystem = "windows"
 )]
 
+mod capture_size;
+mod color_picker;
+mod config;
+mod detect;
+mod export;
+mod fonts;
+mod history;
+mod image_layer;
+mod measure;
+mod snap;
+mod stamp;
+mod stroke;
+mod text_editor;
 mod text_renderer;
+mod text_style;
+mod window_geometry;
 
+use capture_size::{AspectRatio, SizeEntry};
+use config::{AppConfig, OutsideAnnotations};
+use history::{Edit, Footprint, History, Region};
+use image_layer::LayerImage;
 use resvg::tiny_skia::Pixmap;
+use stamp::Stamp;
 use std::sync::Arc;
+use text_editor::TextEditor;
 use text_renderer::{CURSOR_CHAR, TextRenderer};
+use text_style::{
+    BACKGROUND_ALPHA, BACKGROUND_PADDING, BACKGROUND_RADIUS, TextAlign, TextStyle, contrast_color,
+};
 use wgpu::util::DeviceExt;
 use winit::{
     application::ApplicationHandler,
@@ -17,9 +41,12 @@ use winit::{
 };
 
 // ===== 配置常量定义区域 =====
+// 🚀 HiDPI：工具栏、手柄、默认字号等界面尺寸均为逻辑单位，使用时乘以窗口缩放系数（见 State::ui）
 const TOOLBAR_HEIGHT: f32 = 40.0;
 const TOOLBAR_BUTTON_SIZE: f32 = 30.0;
 const TOOLBAR_BUTTON_MARGIN: f32 = 10.0;
+const TOOLBAR_ICON_SIZE: f32 = 32.0; // 图标光栅化尺寸
+const DEFAULT_FONT_SIZE: f32 = 24.0;
 
 // 颜色常量
 const CYAN: [f32; 3] = [0.0, 1.0, 1.0];
@@ -42,6 +69,81 @@ const DEFAULT_HANDLE_COLOR: [f32; 3] = CYAN;
 // 拖拽配置常量
 const MIN_BOX_SIZE: f32 = 20.0;
 
+// 🚀 旋转配置
+const ROTATE_HANDLE_OFFSET: f32 = 25.0; // 旋转手柄距离选区上边的距离（逻辑像素）
+const ROTATION_SNAP_STEP: f32 = std::f32::consts::PI / 12.0; // 按住Shift时吸附到15°
+const TEXT_BOX_PADDING: f32 = 4.0; // 文本选区（手柄、虚线框）与文字之间的间距
+
+// 🚀 画笔交互配置
+const PEN_HIT_TOLERANCE: f32 = 6.0; // 画笔命中检测的额外容差（逻辑像素）
+const MIN_PEN_BOUNDS_SIZE: f32 = 5.0; // 缩放画笔时包围盒的最小尺寸，防止笔画塌缩
+// 🚀 橡皮擦配置
+const DEFAULT_ERASER_RADIUS: f32 = 12.0;
+const MIN_ERASER_RADIUS: f32 = 4.0;
+const MAX_ERASER_RADIUS: f32 = 60.0;
+const ERASER_RADIUS_STEP: f32 = 2.0;
+const ERASER_CURSOR_COLOR: [f32; 3] = [0.7, 0.7, 0.7];
+
+const TEXT_SELECTION_COLOR: [f32; 4] = [0.2, 0.5, 1.0, 0.35]; // 选中文本的半透明高亮
+
+// 🚀 文本属性栏（逻辑像素）
+const TEXT_PROPERTY_BUTTON_SIZE: f32 = 26.0;
+const TEXT_PROPERTY_FAMILY_WIDTH: f32 = 120.0;
+const TEXT_PROPERTY_SPACING_WIDTH: f32 = 44.0;
+const TEXT_PROPERTY_MARGIN: f32 = 4.0;
+const TEXT_PROPERTY_LABEL_SIZE: f32 = 14.0;
+const TEXT_PROPERTY_BAR_COLOR: [f32; 4] = [0.12, 0.12, 0.12, 0.9];
+const TEXT_PROPERTY_BUTTON_COLOR: [f32; 4] = [0.25, 0.25, 0.25, 1.0];
+const TEXT_PROPERTY_ACTIVE_COLOR: [f32; 4] = [0.2, 0.5, 1.0, 1.0];
+const TEXT_PROPERTY_LABEL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+// 🚀 印章工具
+const DEFAULT_STAMP_SIZE: f32 = 48.0; // 单击放置时的印章边长（逻辑像素）
+const MIN_STAMP_SIZE: f32 = 12.0; // 拖动或缩放时的最小边长（像素）
+const STAMP_BUTTON_SIZE: f32 = 32.0; // 印章面板按钮（逻辑像素）
+const STAMP_EMOJI_COLOR: [f32; 3] = [0.1, 0.1, 0.1]; // 没有彩色字形时表情符号的颜色
+const IMAGE_LAYER_MAX_FILL: f32 = 0.8; // 粘贴的图像最多占选区宽高的比例
+const IMAGE_OPACITY_STEP: f32 = 0.1;
+const MIN_IMAGE_OPACITY: f32 = 0.1;
+const SNAP_GUIDE_COLOR: [f32; 4] = [1.0, 0.2, 0.6, 0.9]; // 吸附参考线
+const DETECTED_REGION_BORDER_COLOR: [f32; 4] = [0.0, 1.0, 1.0, 0.9]; // 自动检测区域的高亮边框
+const DETECTED_REGION_FILL_COLOR: [f32; 4] = [0.0, 1.0, 1.0, 0.12];
+const SIZE_LABEL_HEIGHT: f32 = 22.0; // 选区尺寸标签（逻辑像素）
+const SIZE_LABEL_GAP: f32 = 4.0; // 尺寸标签与选区边缘的距离
+const COLOR_READOUT_OFFSET: f32 = 16.0; // 取色读数与鼠标的距离（逻辑像素）
+const COLOR_SWATCH_WIDTH: f32 = 24.0; // 取色读数中的颜色块宽度
+const MEASURE_READOUT_OFFSET: f32 = 12.0; // 测量读数与测量线终点的距离（逻辑像素）
+const MEASURE_LABEL_SIZE: f32 = 14.0; // 保留为标注的测量线的尺寸文字字号（逻辑像素）
+const MEASURE_LABEL_GAP: f32 = 4.0; // 尺寸文字与测量线的距离
+const MEASURE_TICK_SIZE: f32 = 6.0; // 测量线两端短横线的半长
+const OUTSIDE_WARNING_COLOR: [f32; 4] = [1.0, 0.6, 0.0, 0.9]; // 超出选区的标注的警告框
+
+// 🚀 历史记录面板
+const HISTORY_PANEL_WIDTH: f32 = 220.0;
+const HISTORY_ROW_HEIGHT: f32 = 22.0;
+const HISTORY_PANEL_MAX_ROWS: usize = 12;
+const HISTORY_REDO_LABEL_COLOR: [f32; 3] = [0.55, 0.55, 0.55]; // 已撤销（可重做）的步骤
+// 🚀 压感配置：笔画宽度 = 线宽 × 压力映射系数
+const PRESSURE_MIN_WIDTH_FACTOR: f32 = 0.2; // 压力为0时的宽度系数
+const PRESSURE_MAX_WIDTH_FACTOR: f32 = 1.5; // 压力为1时的宽度系数
+
+// 🚀 颜色与线宽配置
+const DEFAULT_THICKNESS: f32 = 2.0;
+const MIN_THICKNESS: f32 = 1.0;
+const MAX_THICKNESS: f32 = 30.0;
+const THICKNESS_STEP: f32 = 1.0;
+// 数字键1-8对应的调色板颜色
+const COLOR_PALETTE: [[f32; 3]; 8] = [
+    RED,
+    [1.0, 0.5, 0.0],  // 橙色
+    [1.0, 0.85, 0.0], // 黄色
+    [0.0, 0.8, 0.2],  // 绿色
+    [0.0, 0.5, 1.0],  // 蓝色
+    [0.6, 0.2, 0.9],  // 紫色
+    [1.0, 1.0, 1.0],  // 白色
+    [0.0, 0.0, 0.0],  // 黑色
+];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Tool {
     None, // 🚀 无选择状态
@@ -50,11 +152,25 @@ enum Tool {
     Arrow,
     Pen,
     Text,
+    Stamp,
+    Eraser,
+    ColorPicker,
+    Measure,
     Undo,
+    Redo,
+    History,
     Save,
     Exit,
     Complete,
 }
+
+// 🚀 橡皮擦模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EraserMode {
+    Object, // 擦除经过的整个元素
+    Stroke, // 只擦除画笔笔画中被经过的部分，笔画会被拆分
+}
+
 // 新增：绘图元素类型
 #[derive(Debug, Clone)]
 enum DrawingElement {
@@ -63,6 +179,7 @@ enum DrawingElement {
         end: (f32, f32),
         color: [f32; 3],
         thickness: f32,
+        rotation: f32, // 🚀 绕矩形中心的旋转角度（弧度），start/end 为未旋转时的坐标
     },
     Circle {
         center: (f32, f32),
@@ -70,6 +187,7 @@ enum DrawingElement {
         radius_y: f32, // 垂直半径
         color: [f32; 3],
         thickness: f32,
+        rotation: f32, // 🚀 绕圆心的旋转角度（弧度）
     },
     Arrow {
         start: (f32, f32),
@@ -77,8 +195,16 @@ enum DrawingElement {
         color: [f32; 3],
         thickness: f32,
     },
+    // 🚀 保留为标注的测量线：两端带短横线，尺寸文字按截图像素计算
+    Measure {
+        start: (f32, f32),
+        end: (f32, f32),
+        color: [f32; 3],
+        thickness: f32,
+    },
     Pen {
         points: Vec<(f32, f32)>,
+        widths: Vec<f32>, // 🚀 每个点的笔画宽度（来自压感），为空时使用固定线宽
         color: [f32; 3],
         thickness: f32,
     },
@@ -89,8 +215,61 @@ enum DrawingElement {
         font_size: f32,
         is_editing: bool,      // 是否正在编辑状态
         rotation: Option<f32>, // 🚀 新增：旋转角度（弧度）
+        size: (f32, f32), // 🚀 排版后的文本框大小，内容或字号改变时由 TextRenderer::measure 更新
+        style: TextStyle, // 🚀 字体、粗斜体、装饰线、对齐和行距
+    },
+    Stamp {
+        center: (f32, f32),
+        size: f32,     // 🚀 正方形边长
+        rotation: f32, // 🚀 绕中心的旋转角度（弧度）
+        stamp: Stamp,
     },
+    Image {
+        start: (f32, f32),
+        end: (f32, f32),
+        rotation: f32, // 🚀 绕中心的旋转角度（弧度），start/end 为未旋转时的坐标
+        opacity: f32,  // 🚀 不透明度 0.0-1.0
+        image_id: u64, // 🚀 image_layers 中对应的纹理
+    },
+}
+// 🚀 撤销历史的内存预算：元素本身加上点列表、文本等堆内存
+// （印章SVG数据共享，图像像素在GPU纹理中，都不计入）
+impl Footprint for DrawingElement {
+    fn footprint(&self) -> usize {
+        let heap = match self {
+            DrawingElement::Pen { points, widths, .. } => {
+                points.len() * std::mem::size_of::<(f32, f32)>()
+                    + widths.len() * std::mem::size_of::<f32>()
+            }
+            DrawingElement::Text { content, style, .. } => {
+                content.len() + style.family.as_ref().map_or(0, String::len)
+            }
+            DrawingElement::Stamp {
+                stamp: Stamp::Emoji(emoji),
+                ..
+            } => emoji.len(),
+            _ => 0,
+        };
+        std::mem::size_of::<Self>() + heap
+    }
+}
+
+impl DrawingElement {
+    // 🚀 历史面板中显示的元素名称
+    fn name(&self) -> &'static str {
+        match self {
+            DrawingElement::Rectangle { .. } => "rectangle",
+            DrawingElement::Circle { .. } => "ellipse",
+            DrawingElement::Arrow { .. } => "arrow",
+            DrawingElement::Measure { .. } => "measurement",
+            DrawingElement::Pen { .. } => "pen stroke",
+            DrawingElement::Text { .. } => "text",
+            DrawingElement::Stamp { .. } => "stamp",
+            DrawingElement::Image { .. } => "image",
+        }
+    }
 }
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 enum DrawingState {
     Idle,
@@ -129,6 +308,35 @@ struct Handle {
     element_index: usize, // 关联的绘图元素索引
 }
 
+// 🚀 离屏光栅化的文本纹理（用于绘制旋转文本）
+struct TextTexture {
+    _texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+}
+
+// 🚀 纹理四边形：(未旋转的矩形 x/y/w/h, 旋转中心, 旋转角度, 不透明度, 纹理绑定组)
+type TexturedQuad<'a> = (
+    (f32, f32, f32, f32),
+    (f32, f32),
+    f32,
+    f32,
+    &'a wgpu::BindGroup,
+);
+
+// 🚀 一条需要以旋转四边形绘制的文本
+struct RotatedText {
+    key: String,
+    text: String,
+    font_size: f32,
+    style: TextStyle,
+    color: [f32; 3],
+    position: (f32, f32),
+    center: (f32, f32),
+    rotation: f32,
+    centered: bool, // 🚀 文字以 center 为中心放置（测量线的尺寸文字），否则左上角在 position
+}
+
 // 🚀 选中的绘图元素信息
 #[derive(Debug, Clone)]
 struct SelectedElement {
@@ -136,14 +344,100 @@ struct SelectedElement {
     handles: Vec<Handle>,
     is_moving: bool,
     move_offset: (f32, f32),
+    snap_correction: (f32, f32), // 🚀 当前叠加在鼠标移动上的吸附修正量
+}
+
+// 🚀 文本属性栏按钮
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TextProperty {
+    Family, // 点击循环切换字体库中的字体
+    Bold,
+    Italic,
+    Underline,
+    Strikethrough,
+    Align(TextAlign),
+    LineSpacing, // 点击循环切换行距
+    Background,  // 文字后面的圆角背景框
+    Outline,     // 文字描边
+}
+
+const TEXT_PROPERTIES: [TextProperty; 11] = [
+    TextProperty::Family,
+    TextProperty::Bold,
+    TextProperty::Italic,
+    TextProperty::Underline,
+    TextProperty::Strikethrough,
+    TextProperty::Background,
+    TextProperty::Outline,
+    TextProperty::Align(TextAlign::Left),
+    TextProperty::Align(TextAlign::Center),
+    TextProperty::Align(TextAlign::Right),
+    TextProperty::LineSpacing,
+];
+
+// 属性栏背景矩形 + 每个按钮的 (属性, x 坐标)
+type TextPropertiesLayout = ((f32, f32, f32, f32), Vec<(TextProperty, f32)>);
+// 🚀 历史记录面板：背景矩形和各行 (历史位置, 名称, 矩形)
+type HistoryPanelLayout = (
+    (f32, f32, f32, f32),
+    Vec<(usize, String, (f32, f32, f32, f32))>,
+);
+
+// 🚀 选区尺寸标签的单元格：平时只有尺寸文字，数值输入时为各字段和宽高比按钮
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SizeLabelCell {
+    Size,
+    Field(capture_size::Field),
+    Ratio,
 }
 
+// 🚀 选区上的标签文字和所在的矩形（文字在矩形中居中）
+type OverlayLabel = (String, (f32, f32, f32, f32));
+
+// 尺寸标签背景矩形 + 各单元格 (单元格, 文字, 矩形)
+type SizeLabelLayout = (
+    (f32, f32, f32, f32),
+    Vec<(SizeLabelCell, String, (f32, f32, f32, f32))>,
+);
+
 struct ToolbarButton {
     tool: Tool,
     rect: (f32, f32, f32, f32),
     is_selected: bool,
 }
 
+// 🚀 着色器的 Uniforms，字段顺序和对齐必须与 shader.wgsl 保持一致
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct Uniforms {
+    box_min: [f32; 2],
+    box_max: [f32; 2],
+    screen_size: [f32; 2],
+    border_width: f32,
+    handle_size: f32,
+    handle_border_width: f32,
+    show_toolbar: f32,
+    toolbar_height: f32,
+    hovered_button: f32,
+    toolbar_active: f32,
+    selected_button: f32,
+    toolbar_button_size: f32,
+    toolbar_button_margin: f32,
+    border_color: [f32; 4],
+    handle_color: [f32; 4],
+    toolbar_button_count: f32,
+    background_cache_valid: f32,
+    force_background_update: f32,
+    show_handles: f32,
+    undo_button_enabled: f32,
+    undo_button_index: f32,
+    ui_scale: f32,
+    redo_button_enabled: f32,
+    redo_button_index: f32,
+    // WGSL 结构体大小按 16 字节对齐
+    _padding: [f32; 3],
+}
+
 struct State {
     window: Arc<Window>,
     device: wgpu::Device,
@@ -193,6 +487,9 @@ struct State {
     drawing_state: DrawingState,
     drawing_start_pos: Option<(f32, f32)>,
     pen_points: Vec<(f32, f32)>,
+    pen_smoother: stroke::LiveSmoother,
+    pen_widths: Vec<f32>,      // 🚀 原始点对应的压感宽度，鼠标输入时为空
+    pen_pressure: Option<f32>, // 🚀 当前触控/手写笔压力（0.0-1.0），鼠标输入为None
 
     // 🚀 绘图元素选择和编辑系统
     selected_element: Option<SelectedElement>,
@@ -202,20 +499,37 @@ struct State {
     // 🚀 鼠标指针状态
     current_cursor: winit::window::CursorIcon,
 
-    // 🚀 撤销系统
-    undo_stack: Vec<Vec<DrawingElement>>, // 撤销栈，存储历史状态
-    redo_stack: Vec<Vec<DrawingElement>>, // 重做栈
+    // 🚀 撤销系统：记录对元素的操作，撤销时执行反向操作
+    history: History<DrawingElement>,
+    editing_text_origin: Option<(usize, DrawingElement)>, // 正在编辑的已有文本：原位置和编辑前的内容
 
     // 🚀 修饰键状态
     modifiers: winit::event::Modifiers,
 
     // 🚀 文本输入状态
     text_input_active: bool,
-    current_text_input: String,
-    text_cursor_position: usize,
+    text_editor: TextEditor,
+    text_selecting: bool, // 正在用鼠标拖动选择文本
+    clipboard: Option<arboard::Clipboard>,
+
+    // 🚀 橡皮擦状态：一次按下-拖动-释放的手势只记录一次撤销
+    eraser_mode: EraserMode,
+    eraser_radius: f32,
+    erasing: bool,
+    last_erase_pos: Option<(f32, f32)>,
+
+    // 🚀 测量：是否把测量线保留为标注；不保留时最近一次测量线只在屏幕上显示
+    measure_keep: bool,
+    measurement: Option<DrawingElement>,
+
+    // 🚀 当前绘图颜色和线宽（新元素使用，选中元素时也会同步修改）
+    current_color: [f32; 3],
+    current_thickness: f32,
+    current_text_style: TextStyle, // 🚀 新文本使用的样式，属性栏修改
 
     // 绘图渲染相关
     drawing_render_pipeline: wgpu::RenderPipeline,
+    drawing_triangle_pipeline: wgpu::RenderPipeline, // 🚀 三角形管线，用于有宽度的笔画
     drawing_vertex_buffer: Option<wgpu::Buffer>,
 
     // 🚀 绘图元素缓存系统
@@ -225,6 +539,35 @@ struct State {
     // 🚀 文本渲染器
     text_renderer: TextRenderer,
 
+    // 🚀 旋转文本：离屏光栅化后的纹理缓存，按文本内容/字号/颜色索引
+    textured_quad_pipeline: wgpu::RenderPipeline,
+    rotated_text_textures: std::collections::HashMap<String, TextTexture>,
+
+    // 🚀 印章：面板（内置 + 配置目录 + 输入的表情符号）、当前印章和光栅化纹理缓存
+    stamp_palette: Vec<Stamp>,
+    current_stamp: usize,
+    stamp_textures: std::collections::HashMap<String, TextTexture>,
+
+    // 🚀 图像图层：粘贴或拖放的图像纹理，按元素中的 image_id 索引
+    image_layers: std::collections::HashMap<u64, TextTexture>,
+    next_image_id: u64,
+
+    // 🚀 历史记录面板是否打开
+    show_history_panel: bool,
+
+    // 🚀 拖动时的吸附参考线，松开鼠标时清除
+    snap_guides: Vec<snap::Guide>,
+
+    // 🚀 框选前自动检测的候选区域（截图像素，后台线程计算）和鼠标下高亮的区域（窗口坐标）
+    region_detection: Option<std::thread::JoinHandle<Vec<detect::Rect>>>,
+    detected_regions: Vec<detect::Rect>,
+    hovered_region: Option<(f32, f32, f32, f32)>,
+
+    // 🚀 选区的宽高比锁定和数值输入（打开时优先接收键盘输入）
+    aspect_ratio: AspectRatio,
+    size_entry: Option<SizeEntry>,
+    capture_preset: Option<usize>, // 🚀 最近一次使用的固定尺寸预设（config.capture.presets 的下标）
+
     // 🚀 文本缓存
     text_buffer_cache: Option<glyphon::Buffer>,
     cached_text_content: String,
@@ -233,6 +576,17 @@ struct State {
     last_click_time: std::time::Instant,
     last_click_position: Option<(f32, f32)>,
     double_click_threshold: std::time::Duration,
+
+    // 🚀 用户配置
+    config: AppConfig,
+
+    // 🚀 坐标映射：逻辑UI单位 ×scale_factor→ 窗口物理像素 ×capture_scale→ 截图像素
+    scale_factor: f32,
+    capture_size: Option<(u32, u32)>,
+    // 🚀 截图所在屏幕在桌面上的位置，用于把其他窗口的位置换算到截图坐标
+    capture_origin: (i32, i32),
+    // 🚀 原始截图像素（RGBA），取色器从这里取色，不受遮罩和标注影响
+    capture_pixels: Option<Arc<Vec<u8>>>,
 }
 
 impl State {
@@ -290,7 +644,9 @@ impl State {
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
-                        min_binding_size: None,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Uniforms>() as u64
+                        ),
                     },
                     count: None,
                 },
@@ -428,6 +784,61 @@ impl State {
                 multiview: None,
                 cache: pipeline_cache.as_ref(),
             });
+        // 🚀 三角形绘图管线：与线段管线共用顶点格式，用于渲染有实际宽度的画笔笔画
+        let drawing_triangle_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Drawing Triangle Pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_drawing"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: 28, // 7 floats: x, y, r, g, b, a, thickness
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                offset: 0,
+                                shader_location: 0,
+                                format: wgpu::VertexFormat::Float32x2, // position
+                            },
+                            wgpu::VertexAttribute {
+                                offset: 8,
+                                shader_location: 1,
+                                format: wgpu::VertexFormat::Float32x4, // color
+                            },
+                            wgpu::VertexAttribute {
+                                offset: 24,
+                                shader_location: 2,
+                                format: wgpu::VertexFormat::Float32, // thickness
+                            },
+                        ],
+                    }],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_drawing"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: surface_format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Ccw,
+                    cull_mode: None,
+                    unclipped_depth: false,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: pipeline_cache.as_ref(),
+            });
         let vertices = [
             [-1.0f32, -1.0, 0.0, 1.0],
             [1.0, -1.0, 1.0, 1.0],
@@ -444,44 +855,40 @@ impl State {
         });
 
         let size = window.inner_size();
-        let box_data = [
-            -1.0f32,
-            -1.0f32,
-            -1.0f32,
-            -1.0f32, // box coordinates
-            size.width as f32,
-            size.height as f32, // screen size
-            DEFAULT_BORDER_WIDTH,
-            DEFAULT_HANDLE_SIZE,
-            DEFAULT_HANDLE_BORDER_WIDTH, // border/handle params
-            0.0,
-            TOOLBAR_HEIGHT,
-            -1.0,
-            0.0,
-            -1.0, // toolbar params
-            TOOLBAR_BUTTON_SIZE,
-            TOOLBAR_BUTTON_MARGIN, // button params
-            DEFAULT_BORDER_COLOR[0],
-            DEFAULT_BORDER_COLOR[1],
-            DEFAULT_BORDER_COLOR[2],
-            1.0, // border color
-            DEFAULT_HANDLE_COLOR[0],
-            DEFAULT_HANDLE_COLOR[1],
-            DEFAULT_HANDLE_COLOR[2],
-            1.0, // handle color
-            9.0,
-            0.0,
-            0.0,
-            0.0, // button count + padding
-            0.0,
-            0.0,
-            0.0,
-            0.0, // extra padding
-        ];
+        let scale_factor = window.scale_factor() as f32;
+        let uniforms = Uniforms {
+            box_min: [-1.0, -1.0],
+            box_max: [-1.0, -1.0],
+            screen_size: [size.width as f32, size.height as f32],
+            border_width: DEFAULT_BORDER_WIDTH * scale_factor,
+            handle_size: DEFAULT_HANDLE_SIZE * scale_factor,
+            handle_border_width: DEFAULT_HANDLE_BORDER_WIDTH * scale_factor,
+            toolbar_height: TOOLBAR_HEIGHT * scale_factor,
+            hovered_button: -1.0,
+            selected_button: -1.0,
+            toolbar_button_size: TOOLBAR_BUTTON_SIZE * scale_factor,
+            toolbar_button_margin: TOOLBAR_BUTTON_MARGIN * scale_factor,
+            border_color: [
+                DEFAULT_BORDER_COLOR[0],
+                DEFAULT_BORDER_COLOR[1],
+                DEFAULT_BORDER_COLOR[2],
+                1.0,
+            ],
+            handle_color: [
+                DEFAULT_HANDLE_COLOR[0],
+                DEFAULT_HANDLE_COLOR[1],
+                DEFAULT_HANDLE_COLOR[2],
+                1.0,
+            ],
+            toolbar_button_count: 9.0,
+            ui_scale: scale_factor,
+            redo_button_index: -1.0,
+            ..Uniforms::default()
+        };
 
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("Uniform Buffer"),
-            contents: bytemuck::cast_slice(&box_data),
+            contents: bytemuck::bytes_of(&uniforms),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
@@ -562,10 +969,62 @@ impl State {
                 cache: pipeline_cache.as_ref(),
             });
 
+        // 🚀 旋转文本、印章和图像图层的纹理四边形管线（纹理为预乘alpha）
+        // 顶点：[ndc x, ndc y, u, v, 不透明度]
+        let textured_quad_pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Textured Quad Pipeline"),
+                layout: Some(&background_cache_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_textured"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    buffers: &[wgpu::VertexBufferLayout {
+                        array_stride: 20,
+                        step_mode: wgpu::VertexStepMode::Vertex,
+                        attributes: &[
+                            wgpu::VertexAttribute {
+                                offset: 0,
+                                shader_location: 0,
+                                format: wgpu::VertexFormat::Float32x4,
+                            },
+                            wgpu::VertexAttribute {
+                                offset: 16,
+                                shader_location: 1,
+                                format: wgpu::VertexFormat::Float32,
+                            },
+                        ],
+                    }],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some("fs_textured"),
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: surface_format,
+                        blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+                cache: pipeline_cache.as_ref(),
+            });
+
+        let config = AppConfig::load();
+
         // 🚀 初始化文本渲染器
-        let text_renderer =
-            TextRenderer::new(&device, &queue, size.width, size.height, surface_format)
-                .expect("Failed to create text renderer");
+        let text_renderer = TextRenderer::new(
+            &device,
+            &queue,
+            size.width,
+            size.height,
+            surface_format,
+            &config.fonts,
+        )
+        .expect("Failed to create text renderer");
 
         let mut state = State {
             window,
@@ -578,9 +1037,9 @@ impl State {
             vertex_buffer,
             bind_group: None,
             uniform_buffer,
-            border_width: DEFAULT_BORDER_WIDTH,
-            handle_size: DEFAULT_HANDLE_SIZE,
-            handle_border_width: DEFAULT_HANDLE_BORDER_WIDTH,
+            border_width: DEFAULT_BORDER_WIDTH * scale_factor,
+            handle_size: DEFAULT_HANDLE_SIZE * scale_factor,
+            handle_border_width: DEFAULT_HANDLE_BORDER_WIDTH * scale_factor,
             border_color: DEFAULT_BORDER_COLOR,
             handle_color: DEFAULT_HANDLE_COLOR,
             toolbar_buttons: Vec::new(),
@@ -599,6 +1058,9 @@ impl State {
             drawing_state: DrawingState::Idle,
             drawing_start_pos: None,
             pen_points: Vec::new(),
+            pen_smoother: stroke::LiveSmoother::default(),
+            pen_widths: Vec::new(),
+            pen_pressure: None,
             // 🚀 绘图元素选择和编辑系统初始化
             selected_element: None,
             hovered_handle: None,
@@ -607,17 +1069,32 @@ impl State {
             current_cursor: winit::window::CursorIcon::Default,
 
             // 🚀 撤销系统初始化
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            history: History::new(
+                config.history.max_steps,
+                config.history.max_memory_mb * 1024 * 1024,
+            ),
+            editing_text_origin: None,
 
             // 🚀 修饰键状态初始化
             modifiers: winit::event::Modifiers::default(),
 
             // 🚀 文本输入状态初始化
             text_input_active: false,
-            current_text_input: String::new(),
-            text_cursor_position: 0,
+            text_editor: TextEditor::default(),
+            text_selecting: false,
+            clipboard: None,
+            current_color: RED,
+            current_thickness: DEFAULT_THICKNESS,
+            current_text_style: TextStyle::default(),
+
+            eraser_mode: EraserMode::Stroke,
+            eraser_radius: DEFAULT_ERASER_RADIUS * scale_factor,
+            erasing: false,
+            last_erase_pos: None,
+            measure_keep: config.measure.keep_as_annotation,
+            measurement: None,
             drawing_render_pipeline,
+            drawing_triangle_pipeline,
             drawing_vertex_buffer: None,
             // 🚀 绘图元素缓存系统初始化
             cached_drawing_vertices: std::collections::HashMap::new(),
@@ -634,6 +1111,21 @@ impl State {
             background_cache_pipeline,
             // 🚀 文本渲染器
             text_renderer,
+            textured_quad_pipeline,
+            rotated_text_textures: std::collections::HashMap::new(),
+            stamp_palette: stamp::load_palette(config.stamps.directory.as_deref()),
+            current_stamp: 0,
+            stamp_textures: std::collections::HashMap::new(),
+            image_layers: std::collections::HashMap::new(),
+            next_image_id: 0,
+            show_history_panel: false,
+            snap_guides: Vec::new(),
+            region_detection: None,
+            detected_regions: Vec::new(),
+            hovered_region: None,
+            aspect_ratio: AspectRatio::Free,
+            size_entry: None,
+            capture_preset: None,
 
             // 🚀 文本缓存初始化
             text_buffer_cache: None,
@@ -643,6 +1135,12 @@ impl State {
             last_click_time: std::time::Instant::now(),
             last_click_position: None,
             double_click_threshold: std::time::Duration::from_millis(500),
+
+            config,
+            scale_factor,
+            capture_size: None,
+            capture_origin: (0, 0),
+            capture_pixels: None,
         };
 
         state.configure_surface();
@@ -674,8 +1172,9 @@ impl State {
                 self.current_drawing = Some(DrawingElement::Rectangle {
                     start: (x, y),
                     end: (x, y),
-                    color: RED,
-                    thickness: 2.0,
+                    color: self.current_color,
+                    thickness: self.current_thickness,
+                    rotation: 0.0,
                 });
             }
             Tool::Circle => {
@@ -683,51 +1182,86 @@ impl State {
                     center: (x, y),
                     radius_x: 0.0,
                     radius_y: 0.0,
-                    color: RED,
-                    thickness: 2.0,
+                    color: self.current_color,
+                    thickness: self.current_thickness,
+                    rotation: 0.0,
                 });
             }
             Tool::Arrow => {
                 self.current_drawing = Some(DrawingElement::Arrow {
                     start: (x, y),
                     end: (x, y),
-                    color: RED,
-                    thickness: 2.0,
+                    color: self.current_color,
+                    thickness: self.current_thickness,
+                });
+            }
+            Tool::Measure => {
+                // 🚀 开始新的测量时移除上一次未保留的测量线
+                self.measurement = None;
+                self.current_drawing = Some(DrawingElement::Measure {
+                    start: (x, y),
+                    end: (x, y),
+                    color: self.current_color,
+                    thickness: self.current_thickness,
                 });
             }
             Tool::Pen => {
                 self.pen_points.clear();
                 self.pen_points.push((x, y));
+                self.pen_smoother = stroke::LiveSmoother::default();
+                self.pen_widths.clear();
+                if let Some(pressure) = self.pen_pressure {
+                    self.pen_widths
+                        .push(Self::pressure_to_width(self.current_thickness, pressure));
+                }
                 self.current_drawing = Some(DrawingElement::Pen {
                     points: vec![(x, y)],
-                    color: RED,
-                    thickness: 2.0, // 恢复正常厚度
+                    widths: self.pen_widths.clone(),
+                    color: self.current_color,
+                    thickness: self.current_thickness,
                 });
             }
             Tool::Text => {
                 // 🚀 文本工具：创建文本元素并开始文本输入
+                let font_size = self.ui(DEFAULT_FONT_SIZE);
                 self.current_drawing = Some(DrawingElement::Text {
                     position: (x, y),
                     content: String::new(),
-                    color: RED,
-                    font_size: 24.0, // 增大字体
+                    color: self.current_color,
+                    font_size,
                     is_editing: true,
                     rotation: None, // 🚀 初始无旋转
+                    size: self
+                        .text_renderer
+                        .measure("", font_size, &self.current_text_style),
+                    style: self.current_text_style.clone(),
                 });
 
                 // 激活文本输入模式
                 self.text_input_active = true;
-                self.current_text_input.clear();
-                self.text_cursor_position = 0;
+                self.text_editor.clear();
 
                 // 🚀 启用IME以支持真实的文本输入
                 self.window.set_ime_allowed(true);
+                self.update_ime_cursor_area();
 
                 // 🚀 确保进入正确的绘图状态
                 self.drawing_state = DrawingState::Drawing;
 
                 println!("🚀 开始文本输入模式，位置: ({}, {})，IME已启用", x, y);
             }
+            Tool::Stamp => {
+                // 🚀 单击按默认大小放置，拖动可以改变大小
+                let Some(stamp) = self.stamp_palette.get(self.current_stamp).cloned() else {
+                    return;
+                };
+                self.current_drawing = Some(DrawingElement::Stamp {
+                    center: (x, y),
+                    size: self.ui(DEFAULT_STAMP_SIZE),
+                    rotation: 0.0,
+                    stamp,
+                });
+            }
             _ => {}
         }
 
@@ -747,6 +1281,7 @@ impl State {
             return;
         }
 
+        let shift = self.modifiers.state().shift_key();
         if let Some(ref mut drawing) = self.current_drawing {
             match drawing {
                 DrawingElement::Rectangle { end, .. } => {
@@ -766,6 +1301,14 @@ impl State {
                 DrawingElement::Arrow { end, .. } => {
                     *end = (x, y);
                 }
+                DrawingElement::Measure { start, end, .. } => {
+                    // 🚀 按住 Shift 约束为水平或垂直测量
+                    *end = if shift {
+                        measure::constrain(*start, (x, y))
+                    } else {
+                        (x, y)
+                    };
+                }
                 DrawingElement::Pen { .. } => {
                     // 🔧 修复：画笔实时渲染，立即添加点并重绘
                     self.add_pen_point(x, y);
@@ -773,6 +1316,16 @@ impl State {
                 DrawingElement::Text { .. } => {
                     // 🚀 文本元素不需要在拖拽时更新
                 }
+                DrawingElement::Stamp { center, size, .. } => {
+                    // 🚀 以按下位置为中心拖出正方形，拖动距离太小时保持默认大小
+                    let side = (x - center.0).abs().max((y - center.1).abs()) * 2.0;
+                    if side >= MIN_STAMP_SIZE {
+                        *size = side;
+                    }
+                }
+                DrawingElement::Image { .. } => {
+                    // 🚀 图像图层由粘贴或拖放创建，不会处于绘制状态
+                }
             }
         }
 
@@ -786,7 +1339,7 @@ impl State {
     }
     // 新增：完成当前绘图
     fn finish_current_drawing(&mut self) {
-        if let Some(drawing) = self.current_drawing.take() {
+        if let Some(mut drawing) = self.current_drawing.take() {
             println!(
                 "🚀 完成绘图，元素类型: {:?}",
                 match &drawing {
@@ -794,7 +1347,10 @@ impl State {
                     DrawingElement::Rectangle { .. } => "Rectangle".to_string(),
                     DrawingElement::Circle { .. } => "Circle".to_string(),
                     DrawingElement::Arrow { .. } => "Arrow".to_string(),
+                    DrawingElement::Measure { .. } => "Measure".to_string(),
                     DrawingElement::Pen { .. } => "Pen".to_string(),
+                    DrawingElement::Stamp { stamp, .. } => format!("Stamp({})", stamp.key()),
+                    DrawingElement::Image { image_id, .. } => format!("Image({})", image_id),
                 }
             );
 
@@ -806,21 +1362,67 @@ impl State {
                         DrawingElement::Rectangle { .. } => "Rectangle",
                         DrawingElement::Circle { .. } => "Circle",
                         DrawingElement::Arrow { .. } => "Arrow",
+                        DrawingElement::Measure { .. } => "Measure",
                         _ => "Other",
                     }
                 );
                 self.drawing_state = DrawingState::Idle;
                 self.drawing_start_pos = None;
                 self.pen_points.clear();
+                self.pen_widths.clear();
+                self.needs_redraw = true;
+                return;
+            }
+
+            // 🚀 不保留为标注的测量线只显示读数，直到下一次测量或切换工具
+            if matches!(drawing, DrawingElement::Measure { .. }) && !self.measure_keep {
+                self.measurement = Some(drawing);
+                self.drawing_state = DrawingState::Idle;
+                self.drawing_start_pos = None;
                 self.needs_redraw = true;
                 return;
             }
 
-            // 🚀 保存状态到撤销栈（在添加新元素之前）
-            self.save_state_for_undo();
+            // 🚀 画笔完成时对原始点做简化和平滑
+            if let DrawingElement::Pen { points, widths, .. } = &mut drawing
+                && !self.pen_points.is_empty()
+            {
+                (*points, *widths) = stroke::process(
+                    &self.pen_points,
+                    &self.pen_widths,
+                    self.config.pen.simplify_tolerance,
+                    self.config.pen.smoothing,
+                );
+            }
 
-            let new_index = self.drawing_elements.len();
-            self.drawing_elements.push(drawing);
+            // 🚀 记录撤销：编辑已有文本放回原位置并记为修改，其他为新增元素
+            let new_index = match self.editing_text_origin.take() {
+                Some((index, before)) => {
+                    let index = index.min(self.drawing_elements.len());
+                    self.drawing_elements.insert(index, drawing.clone());
+                    self.record_edit(
+                        "Edit text",
+                        Edit::Modify {
+                            index,
+                            before,
+                            after: drawing,
+                        },
+                    );
+                    index
+                }
+                None => {
+                    let index = self.drawing_elements.len();
+                    self.drawing_elements.push(drawing.clone());
+                    self.record_edit(
+                        &format!("Add {}", drawing.name()),
+                        Edit::Add {
+                            index,
+                            element: drawing,
+                        },
+                    );
+                    index
+                }
+            };
 
             println!(
                 "🚀 绘图元素已添加到列表，索引: {}, 总数: {}",
@@ -836,6 +1438,7 @@ impl State {
         self.drawing_state = DrawingState::Idle;
         self.drawing_start_pos = None;
         self.pen_points.clear();
+        self.pen_widths.clear();
 
         // 🔧 修复：完成绘图时标记需要重绘
         self.needs_redraw = true;
@@ -855,7 +1458,8 @@ impl State {
             DrawingElement::Circle {
                 radius_x, radius_y, ..
             } => *radius_x >= MIN_SAVE_RADIUS && *radius_y >= MIN_SAVE_RADIUS,
-            DrawingElement::Arrow { start, end, .. } => {
+            DrawingElement::Arrow { start, end, .. }
+            | DrawingElement::Measure { start, end, .. } => {
                 let dx = end.0 - start.0;
                 let dy = end.1 - start.1;
                 let length = (dx * dx + dy * dy).sqrt();
@@ -869,6 +1473,9 @@ impl State {
                 // 笔画元素总是保存，因为用户手绘的内容都有意义
                 !points.is_empty()
             }
+            // 印章至少是默认大小或拖出的大小，总是保存
+            DrawingElement::Stamp { .. } => true,
+            DrawingElement::Image { .. } => true,
         }
     }
 
@@ -893,10 +1500,46 @@ impl State {
 
     // 🔧 实时画笔：确保每次添加点都立即渲染
     fn add_pen_point(&mut self, x: f32, y: f32) {
-        if let Some(DrawingElement::Pen { points, .. }) = &mut self.current_drawing {
-            // 直接添加所有点，不做任何过滤
-            points.push((x, y));
+        if let Some(DrawingElement::Pen {
+            points,
+            widths,
+            thickness,
+            ..
+        }) = &mut self.current_drawing
+        {
+            // 忽略与上一个原始点重合的点，避免鼠标静止时堆积重复点
+            if let Some(&(lx, ly)) = self.pen_points.last()
+                && (lx - x).abs() < 0.5
+                && (ly - y).abs() < 0.5
+            {
+                return;
+            }
             self.pen_points.push((x, y));
+            // 🚀 有压力数据时记录每个点的宽度；笔画以鼠标开始时保持固定线宽
+            let width = self
+                .pen_pressure
+                .filter(|_| self.pen_widths.len() + 1 == self.pen_points.len())
+                .map(|pressure| Self::pressure_to_width(*thickness, pressure));
+            if let Some(width) = width {
+                self.pen_widths.push(width);
+            }
+
+            // 🚀 原始点保存在 pen_points 中，显示用的点可以实时平滑
+            if self.config.pen.live_smoothing {
+                // 只重新平滑末尾几段，前面的采样点保留
+                self.pen_smoother.update(
+                    &self.pen_points,
+                    &self.pen_widths,
+                    self.config.pen.smoothing,
+                    points,
+                    widths,
+                );
+            } else {
+                points.push((x, y));
+                if let Some(width) = width {
+                    widths.push(width);
+                }
+            }
 
             // 🔧 实时渲染：立即标记重绘
             self.needs_redraw = true; // 立即重绘
@@ -904,45 +1547,427 @@ impl State {
         }
     }
 
-    // 🚀 生成绘图元素的缓存键
-    fn generate_element_cache_key(&self, element: &DrawingElement) -> String {
+    // 🚀 橡皮擦：按下时开始一次擦除手势
+    fn start_erasing(&mut self, pos: (f32, f32)) {
+        self.deselect_element();
+        self.erasing = true;
+        self.history.begin_group("Erase");
+        self.last_erase_pos = None;
+        self.erase_at(pos);
+    }
+
+    // 🚀 橡皮擦：沿鼠标移动路径擦除，移动较快时插值采样避免漏擦
+    fn erase_at(&mut self, pos: (f32, f32)) {
+        let from = self.last_erase_pos.unwrap_or(pos);
+        let distance = ((pos.0 - from.0).powi(2) + (pos.1 - from.1).powi(2)).sqrt();
+        let steps = ((distance / (self.eraser_radius * 0.5)).ceil() as usize).max(1);
+        for step in 1..=steps {
+            let t = step as f32 / steps as f32;
+            self.erase_at_point((from.0 + (pos.0 - from.0) * t, from.1 + (pos.1 - from.1) * t));
+        }
+        self.last_erase_pos = Some(pos);
+    }
+
+    fn erase_at_point(&mut self, pos: (f32, f32)) {
+        let radius = self.eraser_radius;
+        let mut new_elements = Vec::with_capacity(self.drawing_elements.len());
+        // 🚀 按顺序应用即可得到新列表的操作：删除被擦除的元素，拆分的笔画在原位置插入各段
+        let mut edits = Vec::new();
+
+        for element in &self.drawing_elements {
+            match (self.eraser_mode, element) {
+                (EraserMode::Object, _) => {
+                    if self.eraser_hits_element(pos, radius, element) {
+                        edits.push(Edit::Remove {
+                            index: new_elements.len(),
+                            element: element.clone(),
+                        });
+                    } else {
+                        new_elements.push(element.clone());
+                    }
+                }
+                (
+                    EraserMode::Stroke,
+                    DrawingElement::Pen {
+                        points,
+                        widths,
+                        color,
+                        thickness,
+                    },
+                ) => {
+                    // 擦除半径加上笔画半宽，碰到墨迹边缘即可擦除
+                    let max_width = widths.iter().copied().fold(*thickness, f32::max);
+                    match stroke::erase_from_polyline(points, widths, pos, radius + max_width / 2.0)
+                    {
+                        None => new_elements.push(element.clone()),
+                        Some(pieces) => {
+                            edits.push(Edit::Remove {
+                                index: new_elements.len(),
+                                element: element.clone(),
+                            });
+                            for (points, widths) in pieces {
+                                let piece = DrawingElement::Pen {
+                                    points,
+                                    widths,
+                                    color: *color,
+                                    thickness: *thickness,
+                                };
+                                edits.push(Edit::Add {
+                                    index: new_elements.len(),
+                                    element: piece.clone(),
+                                });
+                                new_elements.push(piece);
+                            }
+                        }
+                    }
+                }
+                // 笔画擦除模式只作用于画笔，其他元素保持不变
+                (EraserMode::Stroke, _) => new_elements.push(element.clone()),
+            }
+        }
+
+        if edits.is_empty() {
+            return;
+        }
+
+        // 🚀 整个擦除手势的操作记录在同一个撤销步骤中（见 start_erasing），
+        // 只失效被删除和新增元素的缓存
+        for edit in edits {
+            for element in edit.elements() {
+                self.invalidate_element_cache(element);
+            }
+            self.history.record("Erase", edit);
+        }
+        self.drawing_elements = new_elements;
+        self.needs_redraw = true;
+        self.render_cache_valid = false;
+    }
+
+    fn finish_erasing(&mut self) {
+        self.erasing = false;
+        self.end_history_group();
+        self.last_erase_pos = None;
+        self.needs_redraw = true;
+    }
+
+    fn toggle_eraser_mode(&mut self) {
+        self.eraser_mode = match self.eraser_mode {
+            EraserMode::Object => EraserMode::Stroke,
+            EraserMode::Stroke => EraserMode::Object,
+        };
+        self.needs_redraw = true;
+        println!("🧽 橡皮擦模式: {:?}", self.eraser_mode);
+    }
+
+    fn adjust_eraser_radius(&mut self, delta: f32) {
+        self.eraser_radius =
+            (self.eraser_radius + delta).clamp(MIN_ERASER_RADIUS, MAX_ERASER_RADIUS);
+        self.needs_redraw = true;
+        println!("🧽 橡皮擦半径: {}", self.eraser_radius);
+    }
+
+    // 🚀 对象擦除的碰撞检测：橡皮擦圆与元素的线条（而不是内部区域）相交
+    fn eraser_hits_element(&self, pos: (f32, f32), radius: f32, element: &DrawingElement) -> bool {
         match element {
             DrawingElement::Rectangle {
                 start,
                 end,
-                color,
                 thickness,
+                rotation,
+                ..
             } => {
-                let (sx, sy) = start;
-                let (ex, ey) = end;
-                let (r, g, b) = (color[0], color[1], color[2]);
-                format!("rect_{sx}_{sy}_{ex}_{ey}_{r}_{g}_{b}_{thickness}")
+                let corners = Self::rectangle_corners(*start, *end, *rotation);
+                let outline = [corners[0], corners[1], corners[2], corners[3], corners[0]];
+                Self::distance_to_polyline(pos, &outline) <= radius + thickness / 2.0
             }
             DrawingElement::Circle {
                 center,
                 radius_x,
                 radius_y,
-                color,
                 thickness,
+                rotation,
+                ..
             } => {
-                let (cx, cy) = center;
-                let (r, g, b) = (color[0], color[1], color[2]);
-                format!("circle_{cx}_{cy}_{radius_x}_{radius_y}_{r}_{g}_{b}_{thickness}")
+                let outline =
+                    Self::ellipse_outline_points(*center, *radius_x, *radius_y, *rotation);
+                Self::distance_to_polyline(pos, &outline) <= radius + thickness / 2.0
             }
             DrawingElement::Arrow {
                 start,
                 end,
-                color,
                 thickness,
-            } => {
-                let (sx, sy) = start;
-                let (ex, ey) = end;
-                let (r, g, b) = (color[0], color[1], color[2]);
-                format!("arrow_{sx}_{sy}_{ex}_{ey}_{r}_{g}_{b}_{thickness}")
+                ..
             }
+            | DrawingElement::Measure {
+                start,
+                end,
+                thickness,
+                ..
+            } => Self::distance_to_polyline(pos, &[*start, *end]) <= radius + thickness / 2.0,
             DrawingElement::Pen {
                 points,
-                color,
+                widths,
+                thickness,
+                ..
+            } => {
+                let max_width = widths.iter().copied().fold(*thickness, f32::max);
+                Self::distance_to_polyline(pos, points) <= radius + max_width / 2.0
+            }
+            DrawingElement::Text { .. }
+            | DrawingElement::Stamp { .. }
+            | DrawingElement::Image { .. } => self.hit_test_element(pos, element),
+        }
+    }
+
+    // 🚀 椭圆轮廓的闭合折线近似（绕圆心旋转 rotation 弧度）
+    fn ellipse_outline_points(
+        center: (f32, f32),
+        radius_x: f32,
+        radius_y: f32,
+        rotation: f32,
+    ) -> Vec<(f32, f32)> {
+        const SEGMENTS: usize = 64;
+        (0..=SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 / SEGMENTS as f32 * 2.0 * std::f32::consts::PI;
+                let point = (
+                    center.0 + radius_x * angle.cos(),
+                    center.1 + radius_y * angle.sin(),
+                );
+                Self::rotate_point(point, center, rotation)
+            })
+            .collect()
+    }
+
+    // 🚀 点绕 center 旋转 angle 弧度（屏幕坐标系，y轴向下，正角度为顺时针）
+    fn rotate_point(point: (f32, f32), center: (f32, f32), angle: f32) -> (f32, f32) {
+        if angle == 0.0 {
+            return point;
+        }
+        let (sin, cos) = angle.sin_cos();
+        let dx = point.0 - center.0;
+        let dy = point.1 - center.1;
+        (
+            center.0 + dx * cos - dy * sin,
+            center.1 + dx * sin + dy * cos,
+        )
+    }
+
+    // 🚀 旋转后矩形的四个角：左上、右上、右下、左下
+    fn rectangle_corners(start: (f32, f32), end: (f32, f32), rotation: f32) -> [(f32, f32); 4] {
+        let center = ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0);
+        [
+            (start.0, start.1),
+            (end.0, start.1),
+            (end.0, end.1),
+            (start.0, end.1),
+        ]
+        .map(|corner| Self::rotate_point(corner, center, rotation))
+    }
+
+    // 🚀 带旋转角度的文本无法由 glyphon 直接绘制，需要走离屏纹理
+    fn is_rotated_text(element: &DrawingElement) -> bool {
+        matches!(
+            element,
+            DrawingElement::Text {
+                rotation: Some(rotation),
+                ..
+            } if *rotation != 0.0
+        )
+    }
+
+    // 🚀 可旋转元素（矩形/椭圆/文本）的旋转中心、旋转角度和未旋转时的半高
+    fn shape_rotation_frame(element: &DrawingElement) -> Option<((f32, f32), f32, f32)> {
+        match element {
+            DrawingElement::Text {
+                position,
+                rotation,
+                size: (width, height),
+                ..
+            } => Some((
+                (position.0 + width / 2.0, position.1 + height / 2.0),
+                rotation.unwrap_or(0.0),
+                height / 2.0 + TEXT_BOX_PADDING,
+            )),
+            DrawingElement::Rectangle {
+                start,
+                end,
+                rotation,
+                ..
+            }
+            | DrawingElement::Image {
+                start,
+                end,
+                rotation,
+                ..
+            } => Some((
+                ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0),
+                *rotation,
+                (end.1 - start.1).abs() / 2.0,
+            )),
+            DrawingElement::Circle {
+                center,
+                radius_y,
+                rotation,
+                ..
+            } => Some((*center, *rotation, *radius_y)),
+            DrawingElement::Stamp {
+                center,
+                size,
+                rotation,
+                ..
+            } => Some((*center, *rotation, size / 2.0)),
+            _ => None,
+        }
+    }
+
+    // 🚀 旋转过的元素在局部坐标系中改变大小后中心会移动，平移元素使其
+    // 局部坐标在屏幕上的位置保持不变（old_frame 为修改前的旋转参数）
+    fn reanchor_rotated_element(
+        element: &mut DrawingElement,
+        old_frame: Option<((f32, f32), f32, f32)>,
+    ) {
+        if let Some((old_center, rotation, _)) = old_frame
+            && rotation != 0.0
+            && let Some((local_center, _, _)) = Self::shape_rotation_frame(element)
+        {
+            let world_center = Self::rotate_point(local_center, old_center, rotation);
+            let shift = (
+                world_center.0 - local_center.0,
+                world_center.1 - local_center.1,
+            );
+            Self::move_element_static(element, shift);
+        }
+    }
+
+    // 🚀 箭头的旋转手柄：位于中点的垂直方向上（箭头朝右时在上方）
+    fn arrow_rotate_handle_position(start: (f32, f32), end: (f32, f32), offset: f32) -> (f32, f32) {
+        let mid = ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0);
+        let angle = (end.1 - start.1).atan2(end.0 - start.0);
+        (mid.0 + offset * angle.sin(), mid.1 - offset * angle.cos())
+    }
+
+    // 🚀 拖动旋转手柄：矩形/椭圆/文本修改旋转角度，箭头绕中点旋转，snap 时吸附到15°
+    fn rotate_element_to(element: &mut DrawingElement, pos: (f32, f32), snap: bool) {
+        let snap_angle = |angle: f32| {
+            let angle = if snap {
+                (angle / ROTATION_SNAP_STEP).round() * ROTATION_SNAP_STEP
+            } else {
+                angle
+            };
+            angle.rem_euclid(2.0 * std::f32::consts::PI)
+        };
+
+        match element {
+            DrawingElement::Rectangle { .. }
+            | DrawingElement::Circle { .. }
+            | DrawingElement::Text { .. }
+            | DrawingElement::Stamp { .. }
+            | DrawingElement::Image { .. } => {
+                let Some((center, _, _)) = Self::shape_rotation_frame(element) else {
+                    return;
+                };
+                // 旋转手柄在局部坐标系的正上方，因此角度需要加90°
+                let new_rotation = snap_angle(
+                    (pos.1 - center.1).atan2(pos.0 - center.0) + std::f32::consts::FRAC_PI_2,
+                );
+                if let DrawingElement::Rectangle { rotation, .. }
+                | DrawingElement::Circle { rotation, .. }
+                | DrawingElement::Stamp { rotation, .. }
+                | DrawingElement::Image { rotation, .. } = element
+                {
+                    *rotation = new_rotation;
+                } else if let DrawingElement::Text { rotation, .. } = element {
+                    *rotation = Some(new_rotation);
+                }
+            }
+            DrawingElement::Arrow { start, end, .. }
+            | DrawingElement::Measure { start, end, .. } => {
+                let mid = ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0);
+                let half_len = ((end.0 - start.0).powi(2) + (end.1 - start.1).powi(2)).sqrt() / 2.0;
+                let angle =
+                    snap_angle((pos.1 - mid.1).atan2(pos.0 - mid.0) + std::f32::consts::FRAC_PI_2);
+                let (sin, cos) = angle.sin_cos();
+                *start = (mid.0 - half_len * cos, mid.1 - half_len * sin);
+                *end = (mid.0 + half_len * cos, mid.1 + half_len * sin);
+            }
+            _ => {}
+        }
+    }
+
+    // 🚀 橡皮擦光标：笔画模式为实线圆，对象模式为虚线圆
+    fn add_eraser_cursor_vertices(&self, pos: (f32, f32), vertices: &mut Vec<f32>) {
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        let color = ERASER_CURSOR_COLOR;
+        let outline =
+            Self::ellipse_outline_points(pos, self.eraser_radius, self.eraser_radius, 0.0);
+
+        for (i, segment) in outline.windows(2).enumerate() {
+            if self.eraser_mode == EraserMode::Object && i % 2 == 1 {
+                continue;
+            }
+            for (x, y) in [segment[0], segment[1]] {
+                let ndc_x = (x / screen_width) * 2.0 - 1.0;
+                let ndc_y = 1.0 - (y / screen_height) * 2.0;
+                vertices.extend_from_slice(&[ndc_x, ndc_y, color[0], color[1], color[2], 1.0, 1.0]);
+            }
+        }
+    }
+
+    // 🚀 生成绘图元素的缓存键
+    fn generate_element_cache_key(&self, element: &DrawingElement) -> String {
+        match element {
+            DrawingElement::Rectangle {
+                start,
+                end,
+                color,
+                thickness,
+                rotation,
+            } => {
+                let (sx, sy) = start;
+                let (ex, ey) = end;
+                let (r, g, b) = (color[0], color[1], color[2]);
+                format!("rect_{sx}_{sy}_{ex}_{ey}_{r}_{g}_{b}_{thickness}_{rotation}")
+            }
+            DrawingElement::Circle {
+                center,
+                radius_x,
+                radius_y,
+                color,
+                thickness,
+                rotation,
+            } => {
+                let (cx, cy) = center;
+                let (r, g, b) = (color[0], color[1], color[2]);
+                format!("circle_{cx}_{cy}_{radius_x}_{radius_y}_{r}_{g}_{b}_{thickness}_{rotation}")
+            }
+            DrawingElement::Arrow {
+                start,
+                end,
+                color,
+                thickness,
+            } => {
+                let (sx, sy) = start;
+                let (ex, ey) = end;
+                let (r, g, b) = (color[0], color[1], color[2]);
+                format!("arrow_{sx}_{sy}_{ex}_{ey}_{r}_{g}_{b}_{thickness}")
+            }
+            DrawingElement::Measure {
+                start,
+                end,
+                color,
+                thickness,
+            } => {
+                let (sx, sy) = start;
+                let (ex, ey) = end;
+                let (r, g, b) = (color[0], color[1], color[2]);
+                format!("measure_{sx}_{sy}_{ex}_{ey}_{r}_{g}_{b}_{thickness}")
+            }
+            DrawingElement::Pen {
+                points,
+                widths,
+                color,
                 thickness,
             } => {
                 // 对于画笔，使用点的哈希值
@@ -951,8 +1976,13 @@ impl State {
                     .map(|(x, y)| format!("{x}_{y}"))
                     .collect::<Vec<_>>()
                     .join("_");
+                let widths_hash = widths
+                    .iter()
+                    .map(|w| w.to_string())
+                    .collect::<Vec<_>>()
+                    .join("_");
                 let (r, g, b) = (color[0], color[1], color[2]);
-                format!("pen_{points_hash}_{r}_{g}_{b}_{thickness}")
+                format!("pen_{points_hash}_w{widths_hash}_{r}_{g}_{b}_{thickness}")
             }
             DrawingElement::Text {
                 position,
@@ -965,6 +1995,27 @@ impl State {
                 let (r, g, b) = (color[0], color[1], color[2]);
                 format!("text_{px}_{py}_{content}_{r}_{g}_{b}_{font_size}")
             }
+            DrawingElement::Stamp {
+                center,
+                size,
+                rotation,
+                stamp,
+            } => {
+                let (cx, cy) = center;
+                let key = stamp.key();
+                format!("stamp_{cx}_{cy}_{size}_{rotation}_{key}")
+            }
+            DrawingElement::Image {
+                start,
+                end,
+                rotation,
+                opacity,
+                image_id,
+            } => {
+                let (sx, sy) = start;
+                let (ex, ey) = end;
+                format!("image_{sx}_{sy}_{ex}_{ey}_{rotation}_{opacity}_{image_id}")
+            }
         }
     }
 
@@ -991,6 +2042,26 @@ impl State {
         vertices
     }
 
+    // 🚀 缓存的绘图元素三角形顶点生成（与线段顶点共用缓存表，使用独立的键前缀）
+    fn get_cached_element_triangles(&mut self, element: &DrawingElement) -> Vec<f32> {
+        let cache_key = format!("tri_{}", self.generate_element_cache_key(element));
+
+        if let Some(cached_vertices) = self.cached_drawing_vertices.get(&cache_key)
+            && *self.drawing_cache_valid.get(&cache_key).unwrap_or(&false)
+        {
+            return cached_vertices.clone();
+        }
+
+        let mut vertices = Vec::new();
+        self.add_element_triangles_uncached(element, &mut vertices);
+
+        self.cached_drawing_vertices
+            .insert(cache_key.clone(), vertices.clone());
+        self.drawing_cache_valid.insert(cache_key, true);
+
+        vertices
+    }
+
     // 🚀 失效绘图元素缓存
     fn invalidate_drawing_cache(&mut self) {
         self.drawing_cache_valid.clear();
@@ -999,6 +2070,8 @@ impl State {
     // 🚀 失效特定元素的缓存
     fn invalidate_element_cache(&mut self, element: &DrawingElement) {
         let cache_key = self.generate_element_cache_key(element);
+        self.drawing_cache_valid
+            .insert(format!("tri_{}", cache_key), false);
         self.drawing_cache_valid.insert(cache_key, false);
     }
 
@@ -1011,8 +2084,9 @@ impl State {
         let mut handles = Vec::new();
 
         match element {
-            DrawingElement::Rectangle { start, end, .. } => {
-                // 矩形的8个调整手柄
+            DrawingElement::Rectangle { start, end, .. }
+            | DrawingElement::Image { start, end, .. } => {
+                // 矩形（和图像图层）的8个调整手柄
                 let min_x = start.0.min(end.0);
                 let max_x = start.0.max(end.0);
                 let min_y = start.1.min(end.1);
@@ -1136,8 +2210,9 @@ impl State {
                 });
                 // 不再需要专门的移动手柄，点击元素内部即可拖动
             }
-            DrawingElement::Arrow { start, end, .. } => {
-                // 箭头的2个调整手柄
+            DrawingElement::Arrow { start, end, .. }
+            | DrawingElement::Measure { start, end, .. } => {
+                // 箭头（测量线）的2个调整手柄
                 handles.push(Handle {
                     handle_type: HandleType::ArrowStart,
                     position: *start,
@@ -1152,36 +2227,42 @@ impl State {
                 });
                 // 不再需要专门的移动手柄，点击元素内部即可拖动
             }
-            DrawingElement::Pen { .. } => {
-                // 🚀 画笔不生成手柄，画完后直接固化，不可编辑
-                // 这符合画笔工具的使用习惯：一次性绘制，不可修改
+            DrawingElement::Pen { points, .. } => {
+                // 🚀 画笔使用包围盒的8个手柄，拖动手柄整体缩放笔画
+                if let Some((left, top, right, bottom)) = Self::pen_bounds(points) {
+                    let center_x = (left + right) / 2.0;
+                    let center_y = (top + bottom) / 2.0;
+                    let positions = [
+                        (HandleType::TopLeft, (left, top)),
+                        (HandleType::TopCenter, (center_x, top)),
+                        (HandleType::TopRight, (right, top)),
+                        (HandleType::MiddleLeft, (left, center_y)),
+                        (HandleType::MiddleRight, (right, center_y)),
+                        (HandleType::BottomLeft, (left, bottom)),
+                        (HandleType::BottomCenter, (center_x, bottom)),
+                        (HandleType::BottomRight, (right, bottom)),
+                    ];
+                    for (handle_type, position) in positions {
+                        handles.push(Handle {
+                            handle_type,
+                            position,
+                            size: self.handle_size,
+                            element_index,
+                        });
+                    }
+                }
             }
             DrawingElement::Text {
                 position,
                 is_editing,
-                content,
-                font_size,
+                size: (text_width, text_height),
                 ..
             } => {
                 // 🚀 修复：输入文字时也显示手柄
                 // 🚀 为文本添加四个角的调整手柄（类似矩形）
-                let lines: Vec<&str> = content.split('\n').collect();
-                let line_count = lines.len() as f32;
-                let max_line_width = lines
-                    .iter()
-                    .map(|line| {
-                        // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                        let char_count = line.chars().count() as f32;
-                        let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                        estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                    })
-                    .fold(0.0, f32::max);
-
-                let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                let text_height = font_size * 1.2 * line_count;
 
                 // 🚀 添加padding到手柄位置计算
-                let padding = 4.0; // 减少padding，避免过多空白
+                let padding = TEXT_BOX_PADDING;
                 let left = position.0 - padding;
                 let top = position.1 - padding;
                 let right = left + text_width + padding * 2.0;
@@ -1214,9 +2295,58 @@ impl State {
                     size: self.handle_size,
                     element_index,
                 });
+            }
+            DrawingElement::Stamp { center, size, .. } => {
+                // 🚀 印章保持正方形，只有四个角的缩放手柄
+                let half = size / 2.0;
+                let positions = [
+                    (HandleType::TopLeft, (center.0 - half, center.1 - half)),
+                    (HandleType::TopRight, (center.0 + half, center.1 - half)),
+                    (HandleType::BottomLeft, (center.0 - half, center.1 + half)),
+                    (HandleType::BottomRight, (center.0 + half, center.1 + half)),
+                ];
+                for (handle_type, position) in positions {
+                    handles.push(Handle {
+                        handle_type,
+                        position,
+                        size: self.handle_size,
+                        element_index,
+                    });
+                }
+            }
+        }
 
-                // 🚀 移除旋转手柄
+        // 🚀 矩形/椭圆/文本/印章的手柄按局部坐标计算，这里绕中心旋转到实际位置，并在上方添加旋转手柄
+        if let Some((center, rotation, half_height)) = Self::shape_rotation_frame(element) {
+            for handle in &mut handles {
+                handle.position = Self::rotate_point(handle.position, center, rotation);
             }
+            handles.push(Handle {
+                handle_type: HandleType::Rotate,
+                position: Self::rotate_point(
+                    (
+                        center.0,
+                        center.1 - half_height - self.ui(ROTATE_HANDLE_OFFSET),
+                    ),
+                    center,
+                    rotation,
+                ),
+                size: self.handle_size,
+                element_index,
+            });
+        } else if let DrawingElement::Arrow { start, end, .. }
+        | DrawingElement::Measure { start, end, .. } = element
+        {
+            handles.push(Handle {
+                handle_type: HandleType::Rotate,
+                position: Self::arrow_rotate_handle_position(
+                    *start,
+                    *end,
+                    self.ui(ROTATE_HANDLE_OFFSET),
+                ),
+                size: self.handle_size,
+                element_index,
+            });
         }
 
         handles
@@ -1224,8 +2354,14 @@ impl State {
 
     // 🚀 检测点击是否在绘图元素上
     fn hit_test_element(&self, pos: (f32, f32), element: &DrawingElement) -> bool {
+        // 🚀 旋转过的矩形/椭圆/文本：把测试点转换到元素的局部（未旋转）坐标系
+        let pos = match Self::shape_rotation_frame(element) {
+            Some((center, rotation, _)) => Self::rotate_point(pos, center, -rotation),
+            None => pos,
+        };
         match element {
-            DrawingElement::Rectangle { start, end, .. } => {
+            DrawingElement::Rectangle { start, end, .. }
+            | DrawingElement::Image { start, end, .. } => {
                 let min_x = start.0.min(end.0);
                 let max_x = start.0.max(end.0);
                 let min_y = start.1.min(end.1);
@@ -1248,7 +2384,8 @@ impl State {
                 let normalized_y = dy / radius_y;
                 (normalized_x * normalized_x + normalized_y * normalized_y) <= 1.0
             }
-            DrawingElement::Arrow { start, end, .. } => {
+            DrawingElement::Arrow { start, end, .. }
+            | DrawingElement::Measure { start, end, .. } => {
                 // 简化的线段碰撞检测
                 let threshold = 10.0;
                 let dx = end.0 - start.0;
@@ -1265,14 +2402,21 @@ impl State {
                 let dist = ((pos.0 - closest_x).powi(2) + (pos.1 - closest_y).powi(2)).sqrt();
                 dist <= threshold
             }
-            DrawingElement::Pen { .. } => {
-                // 🚀 画笔不参与交互，画完后固化，不可选中或移动
-                false
+            DrawingElement::Pen {
+                points,
+                widths,
+                thickness,
+                ..
+            } => {
+                // 🚀 画笔碰撞检测：点到折线的最短距离，容差随笔画粗细变化（压感笔画取最大宽度）
+                let max_width = widths.iter().copied().fold(*thickness, f32::max);
+                let threshold = max_width / 2.0 + self.ui(PEN_HIT_TOLERANCE);
+                Self::distance_to_polyline(pos, points) <= threshold
             }
             DrawingElement::Text {
                 position,
                 content,
-                font_size,
+                size: (text_width, text_height),
                 ..
             } => {
                 // 🚀 文本碰撞检测：只有非空文本才能被点击
@@ -1280,27 +2424,14 @@ impl State {
                     return false;
                 }
 
-                // 🚀 改进：支持多行文本的碰撞检测
-                let lines: Vec<&str> = content.split('\n').collect();
-                let line_count = lines.len() as f32;
-                let max_line_width = lines
-                    .iter()
-                    .map(|line| {
-                        // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                        let char_count = line.chars().count() as f32;
-                        let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                        estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                    })
-                    .fold(0.0, f32::max);
-
-                let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                let text_height = font_size * 1.2 * line_count;
-
                 pos.0 >= position.0
                     && pos.0 <= position.0 + text_width
                     && pos.1 >= position.1
                     && pos.1 <= position.1 + text_height
             }
+            DrawingElement::Stamp { center, size, .. } => {
+                (pos.0 - center.0).abs() <= size / 2.0 && (pos.1 - center.1).abs() <= size / 2.0
+            }
         }
     }
 
@@ -1326,6 +2457,7 @@ impl State {
                 handles,
                 is_moving: false,
                 move_offset: (0.0, 0.0),
+                snap_correction: (0.0, 0.0),
             });
             self.drawing_state = DrawingState::Editing;
             self.needs_redraw = true;
@@ -1343,14 +2475,30 @@ impl State {
 
     // 🚀 处理手柄拖拽
     fn handle_drag(&mut self, pos: (f32, f32)) {
+        let shift = self.modifiers.state().shift_key();
         if let Some(mut dragging_handle) = self.dragging_handle.clone() {
             if let Some(selected_index) = self.selected_element.as_ref().map(|s| s.index) {
                 if selected_index < self.drawing_elements.len() {
                     let element = &mut self.drawing_elements[selected_index];
+                    let before = element.clone();
+
+                    // 🚀 旋转手柄：直接按鼠标方向设置角度（按住Shift吸附到15°）
+                    if dragging_handle.handle_type == HandleType::Rotate {
+                        Self::rotate_element_to(element, pos, shift);
+                    }
+
+                    // 🚀 旋转过的矩形/椭圆/文本：在局部（未旋转）坐标系中处理缩放
+                    let rotation_frame = Self::shape_rotation_frame(element);
+                    let pos = match rotation_frame {
+                        Some((center, rotation, _)) => Self::rotate_point(pos, center, -rotation),
+                        None => pos,
+                    };
 
-                    // 🚀 对于矩形，检测是否需要动态切换手柄类型
+                    // 🚀 对于矩形和图像图层，检测是否需要动态切换手柄类型
                     let (new_handle_type, should_update_handles) =
-                        if let DrawingElement::Rectangle { start, end, .. } = element {
+                        if let DrawingElement::Rectangle { start, end, .. }
+                        | DrawingElement::Image { start, end, .. } = element
+                        {
                             let new_handle_type = Self::get_dynamic_handle_type_static(
                                 &dragging_handle,
                                 pos,
@@ -1369,9 +2517,18 @@ impl State {
                         self.dragging_handle = Some(dragging_handle.clone());
                     }
 
+                    // 🚀 画笔：按包围盒缩放所有点（下面的各分支不处理画笔）
+                    if let DrawingElement::Pen { points, .. } = element {
+                        Self::scale_pen_element(points, dragging_handle.handle_type, pos);
+                    }
+
+                    Self::resize_stamp_element(element, dragging_handle.handle_type, pos);
+
                     match dragging_handle.handle_type {
                         HandleType::TopLeft => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 start.0 = pos.0;
                                 start.1 = pos.1;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
@@ -1391,7 +2548,7 @@ impl State {
                             } else if let DrawingElement::Text {
                                 position,
                                 font_size,
-                                content,
+                                size,
                                 ..
                             } = element
                             {
@@ -1399,14 +2556,16 @@ impl State {
                                 Self::scale_text_element(
                                     position,
                                     font_size,
-                                    content,
+                                    *size,
                                     pos,
                                     HandleType::TopLeft,
                                 );
                             }
                         }
                         HandleType::TopRight => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 end.0 = pos.0;
                                 start.1 = pos.1;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
@@ -1426,7 +2585,7 @@ impl State {
                             } else if let DrawingElement::Text {
                                 position,
                                 font_size,
-                                content,
+                                size,
                                 ..
                             } = element
                             {
@@ -1434,14 +2593,16 @@ impl State {
                                 Self::scale_text_element(
                                     position,
                                     font_size,
-                                    content,
+                                    *size,
                                     pos,
                                     HandleType::TopRight,
                                 );
                             }
                         }
                         HandleType::BottomLeft => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 start.0 = pos.0;
                                 end.1 = pos.1;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
@@ -1461,7 +2622,7 @@ impl State {
                             } else if let DrawingElement::Text {
                                 position,
                                 font_size,
-                                content,
+                                size,
                                 ..
                             } = element
                             {
@@ -1469,14 +2630,16 @@ impl State {
                                 Self::scale_text_element(
                                     position,
                                     font_size,
-                                    content,
+                                    *size,
                                     pos,
                                     HandleType::BottomLeft,
                                 );
                             }
                         }
                         HandleType::BottomRight => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 end.0 = pos.0;
                                 end.1 = pos.1;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
@@ -1496,7 +2659,7 @@ impl State {
                             } else if let DrawingElement::Text {
                                 position,
                                 font_size,
-                                content,
+                                size,
                                 ..
                             } = element
                             {
@@ -1504,14 +2667,16 @@ impl State {
                                 Self::scale_text_element(
                                     position,
                                     font_size,
-                                    content,
+                                    *size,
                                     pos,
                                     HandleType::BottomRight,
                                 );
                             }
                         }
                         HandleType::TopCenter => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 start.1 = pos.1;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
                                 Self::normalize_rectangle(start, end);
@@ -1524,7 +2689,9 @@ impl State {
                             }
                         }
                         HandleType::BottomCenter => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 end.1 = pos.1;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
                                 Self::normalize_rectangle(start, end);
@@ -1537,7 +2704,9 @@ impl State {
                             }
                         }
                         HandleType::MiddleLeft => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 start.0 = pos.0;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
                                 Self::normalize_rectangle(start, end);
@@ -1550,7 +2719,9 @@ impl State {
                             }
                         }
                         HandleType::MiddleRight => {
-                            if let DrawingElement::Rectangle { start, end, .. } = element {
+                            if let DrawingElement::Rectangle { start, end, .. }
+                            | DrawingElement::Image { start, end, .. } = element
+                            {
                                 end.0 = pos.0;
                                 // 🚀 规范化矩形坐标，防止坐标混乱
                                 Self::normalize_rectangle(start, end);
@@ -1566,26 +2737,64 @@ impl State {
                             if let DrawingElement::Arrow { start, .. } = element {
                                 start.0 = pos.0;
                                 start.1 = pos.1;
+                            } else if let DrawingElement::Measure { start, end, .. } = element {
+                                // 🚀 按住 Shift 约束为水平或垂直测量
+                                *start = if shift {
+                                    measure::constrain(*end, pos)
+                                } else {
+                                    pos
+                                };
                             }
                         }
                         HandleType::ArrowEnd => {
                             if let DrawingElement::Arrow { end, .. } = element {
                                 end.0 = pos.0;
                                 end.1 = pos.1;
+                            } else if let DrawingElement::Measure { start, end, .. } = element {
+                                *end = if shift {
+                                    measure::constrain(*start, pos)
+                                } else {
+                                    pos
+                                };
                             }
                         }
                         HandleType::Move => {
                             // Move手柄已移除，这个分支不应该被执行
                         }
                         HandleType::Rotate => {
-                            // 🚀 旋转手柄已移除，这个分支不应该被执行
+                            // 🚀 旋转已在上面处理
                         }
                     }
 
+                    // 🚀 旋转过的元素：局部坐标系中缩放后中心会移动，
+                    // 重新计算位置使对边（对角）在屏幕上保持不动
+                    // 🚀 文本缩放后按新字号重新测量排版大小
+                    if let DrawingElement::Text {
+                        content,
+                        font_size,
+                        size,
+                        style,
+                        ..
+                    } = element
+                    {
+                        *size = self.text_renderer.measure(content, *font_size, style);
+                    }
+
+                    Self::reanchor_rotated_element(element, rotation_frame);
+
                     // 更新手柄位置和缓存
                     let element_clone = element.clone();
                     let selected_index = selected_index;
                     self.invalidate_element_cache(&element_clone);
+                    // 🚀 拖拽中的连续修改在历史中合并为一次（见鼠标按下时的 begin_group）
+                    self.history.record(
+                        "",
+                        Edit::Modify {
+                            index: selected_index,
+                            before,
+                            after: element_clone.clone(),
+                        },
+                    );
                     let new_handles =
                         self.generate_handles_for_element(&element_clone, selected_index);
                     if let Some(ref mut selected) = self.selected_element {
@@ -1600,7 +2809,8 @@ impl State {
     // 🚀 移动绘图元素（静态版本）
     fn move_element_static(element: &mut DrawingElement, offset: (f32, f32)) {
         match element {
-            DrawingElement::Rectangle { start, end, .. } => {
+            DrawingElement::Rectangle { start, end, .. }
+            | DrawingElement::Image { start, end, .. } => {
                 start.0 += offset.0;
                 start.1 += offset.1;
                 end.0 += offset.0;
@@ -1610,7 +2820,8 @@ impl State {
                 center.0 += offset.0;
                 center.1 += offset.1;
             }
-            DrawingElement::Arrow { start, end, .. } => {
+            DrawingElement::Arrow { start, end, .. }
+            | DrawingElement::Measure { start, end, .. } => {
                 start.0 += offset.0;
                 start.1 += offset.1;
                 end.0 += offset.0;
@@ -1626,70 +2837,242 @@ impl State {
                 position.0 += offset.0;
                 position.1 += offset.1;
             }
+            DrawingElement::Stamp { center, .. } => {
+                center.0 += offset.0;
+                center.1 += offset.1;
+            }
         }
     }
 
-    // 新增：添加单个元素的顶点数据（无缓存版本）
-    fn add_element_vertices_uncached(&self, element: &DrawingElement, vertices: &mut Vec<f32>) {
-        // 🚀 使用缓存优化的几何图形计算
-        let screen_width = self.size.width as f32;
-        let screen_height = self.size.height as f32;
+    // 🚀 压力（0.0-1.0）映射为笔画宽度
+    fn pressure_to_width(thickness: f32, pressure: f32) -> f32 {
+        let factor = PRESSURE_MIN_WIDTH_FACTOR
+            + (PRESSURE_MAX_WIDTH_FACTOR - PRESSURE_MIN_WIDTH_FACTOR) * pressure.clamp(0.0, 1.0);
+        thickness * factor
+    }
 
-        match element {
+    // 🚀 元素的轴对齐包围盒 (left, top, right, bottom)，旋转过的元素取旋转后四个角的范围
+    fn element_bounds(element: &DrawingElement) -> Option<(f32, f32, f32, f32)> {
+        let corners = match element {
             DrawingElement::Rectangle {
                 start,
                 end,
-                color,
-                thickness,
-            } => {
-                // 🚀 使用缓存的矩形顶点计算
-                let x1 = (start.0 / screen_width) * 2.0 - 1.0;
-                let y1 = 1.0 - (start.1 / screen_height) * 2.0;
-                let x2 = (end.0 / screen_width) * 2.0 - 1.0;
-                let y2 = 1.0 - (end.1 / screen_height) * 2.0;
-
-                // 预计算的矩形边线
-                let lines = [
-                    (x1, y1, x2, y1), // 上边
-                    (x2, y1, x2, y2), // 右边
-                    (x2, y2, x1, y2), // 下边
-                    (x1, y2, x1, y1), // 左边
-                ];
-
-                for (sx, sy, ex, ey) in lines.iter() {
-                    vertices.extend_from_slice(&[
-                        *sx, *sy, color[0], color[1], color[2], 1.0, *thickness, *ex, *ey,
-                        color[0], color[1], color[2], 1.0, *thickness,
-                    ]);
-                }
+                rotation,
+                ..
             }
+            | DrawingElement::Image {
+                start,
+                end,
+                rotation,
+                ..
+            } => Self::rectangle_corners(*start, *end, *rotation),
             DrawingElement::Circle {
                 center,
                 radius_x,
                 radius_y,
-                color,
-                thickness,
-            } => {
-                // 🚀 使用缓存的椭圆顶点计算，减少三角函数调用
-                const SEGMENTS: i32 = 32;
-                let cx = (center.0 / screen_width) * 2.0 - 1.0;
-                let cy = 1.0 - (center.1 / screen_height) * 2.0;
-                let r_x = radius_x / screen_width * 2.0;
-                let r_y = radius_y / screen_height * 2.0;
-
-                // 预计算角度增量
-                const ANGLE_STEP: f32 = 2.0 * std::f32::consts::PI / SEGMENTS as f32;
+                rotation,
+                ..
+            } => Self::rectangle_corners(
+                (center.0 - radius_x, center.1 - radius_y),
+                (center.0 + radius_x, center.1 + radius_y),
+                *rotation,
+            ),
+            DrawingElement::Arrow { start, end, .. }
+            | DrawingElement::Measure { start, end, .. } => {
+                return Self::pen_bounds(&[*start, *end]);
+            }
+            DrawingElement::Pen { points, .. } => return Self::pen_bounds(points),
+            DrawingElement::Text {
+                position,
+                size,
+                rotation,
+                ..
+            } => Self::rectangle_corners(
+                *position,
+                (position.0 + size.0, position.1 + size.1),
+                rotation.unwrap_or(0.0),
+            ),
+            DrawingElement::Stamp {
+                center,
+                size,
+                rotation,
+                ..
+            } => Self::rectangle_corners(
+                (center.0 - size / 2.0, center.1 - size / 2.0),
+                (center.0 + size / 2.0, center.1 + size / 2.0),
+                *rotation,
+            ),
+        };
+        Self::pen_bounds(&corners)
+    }
 
-                for i in 0..SEGMENTS {
-                    let angle1 = (i as f32) * ANGLE_STEP;
-                    let angle2 = ((i + 1) as f32) * ANGLE_STEP;
+    // 🚀 计算画笔点集的包围盒 (left, top, right, bottom)
+    fn pen_bounds(points: &[(f32, f32)]) -> Option<(f32, f32, f32, f32)> {
+        let first = points.first()?;
+        let mut bounds = (first.0, first.1, first.0, first.1);
+        for (x, y) in points.iter().skip(1) {
+            bounds.0 = bounds.0.min(*x);
+            bounds.1 = bounds.1.min(*y);
+            bounds.2 = bounds.2.max(*x);
+            bounds.3 = bounds.3.max(*y);
+        }
+        Some(bounds)
+    }
 
-                    let x1 = cx + r_x * angle1.cos();
-                    let y1 = cy + r_y * angle1.sin();
-                    let x2 = cx + r_x * angle2.cos();
-                    let y2 = cy + r_y * angle2.sin();
+    // 🚀 点到折线的最短距离
+    fn distance_to_polyline(pos: (f32, f32), points: &[(f32, f32)]) -> f32 {
+        if points.len() == 1 {
+            let (x, y) = points[0];
+            return ((pos.0 - x).powi(2) + (pos.1 - y).powi(2)).sqrt();
+        }
 
-                    vertices.extend_from_slice(&[
+        let mut min_dist = f32::INFINITY;
+        for segment in points.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let dx = end.0 - start.0;
+            let dy = end.1 - start.1;
+            let len_squared = dx * dx + dy * dy;
+            let t = if len_squared > 0.0 {
+                (((pos.0 - start.0) * dx + (pos.1 - start.1) * dy) / len_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let closest_x = start.0 + t * dx;
+            let closest_y = start.1 + t * dy;
+            let dist = ((pos.0 - closest_x).powi(2) + (pos.1 - closest_y).powi(2)).sqrt();
+            min_dist = min_dist.min(dist);
+        }
+        min_dist
+    }
+
+    // 🚀 印章：角手柄绕中心等比缩放（与椭圆角手柄一致），旋转手柄不改变大小
+    fn resize_stamp_element(
+        element: &mut DrawingElement,
+        handle_type: HandleType,
+        pos: (f32, f32),
+    ) {
+        if matches!(handle_type, HandleType::Rotate | HandleType::Move) {
+            return;
+        }
+        if let DrawingElement::Stamp { center, size, .. } = element {
+            let half = (pos.0 - center.0).abs().max((pos.1 - center.1).abs());
+            *size = (half * 2.0).max(MIN_STAMP_SIZE);
+        }
+    }
+
+    // 🚀 按手柄拖拽缩放画笔：移动包围盒对应的边，所有点按比例映射到新包围盒
+    fn scale_pen_element(points: &mut [(f32, f32)], handle_type: HandleType, pos: (f32, f32)) {
+        let Some((left, top, right, bottom)) = Self::pen_bounds(points) else {
+            return;
+        };
+
+        let (mut new_left, mut new_top, mut new_right, mut new_bottom) = (left, top, right, bottom);
+        let (moves_left, moves_top, moves_right, moves_bottom) = match handle_type {
+            HandleType::TopLeft => (true, true, false, false),
+            HandleType::TopCenter => (false, true, false, false),
+            HandleType::TopRight => (false, true, true, false),
+            HandleType::MiddleLeft => (true, false, false, false),
+            HandleType::MiddleRight => (false, false, true, false),
+            HandleType::BottomLeft => (true, false, false, true),
+            HandleType::BottomCenter => (false, false, false, true),
+            HandleType::BottomRight => (false, false, true, true),
+            _ => return,
+        };
+
+        // 🚀 拖动的边不能越过对边，防止笔画翻转或塌缩
+        if moves_left {
+            new_left = pos.0.min(right - MIN_PEN_BOUNDS_SIZE);
+        }
+        if moves_right {
+            new_right = pos.0.max(left + MIN_PEN_BOUNDS_SIZE);
+        }
+        if moves_top {
+            new_top = pos.1.min(bottom - MIN_PEN_BOUNDS_SIZE);
+        }
+        if moves_bottom {
+            new_bottom = pos.1.max(top + MIN_PEN_BOUNDS_SIZE);
+        }
+
+        let old_width = right - left;
+        let old_height = bottom - top;
+        // 水平或垂直的直线在该方向上没有尺寸，无法按比例缩放，保持不变
+        let scale_x = if old_width > f32::EPSILON {
+            (new_right - new_left) / old_width
+        } else {
+            1.0
+        };
+        let scale_y = if old_height > f32::EPSILON {
+            (new_bottom - new_top) / old_height
+        } else {
+            1.0
+        };
+        let origin_x = if old_width > f32::EPSILON {
+            new_left
+        } else {
+            left
+        };
+        let origin_y = if old_height > f32::EPSILON {
+            new_top
+        } else {
+            top
+        };
+
+        for point in points.iter_mut() {
+            point.0 = origin_x + (point.0 - left) * scale_x;
+            point.1 = origin_y + (point.1 - top) * scale_y;
+        }
+    }
+
+    // 新增：添加单个元素的顶点数据（无缓存版本）
+    fn add_element_vertices_uncached(&self, element: &DrawingElement, vertices: &mut Vec<f32>) {
+        // 🚀 使用缓存优化的几何图形计算
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+
+        match element {
+            DrawingElement::Rectangle {
+                start,
+                end,
+                color,
+                thickness,
+                rotation,
+            } => {
+                // 🚀 按旋转角度计算四个角，再转换到NDC坐标
+                let corners = Self::rectangle_corners(*start, *end, *rotation).map(|(x, y)| {
+                    (
+                        (x / screen_width) * 2.0 - 1.0,
+                        1.0 - (y / screen_height) * 2.0,
+                    )
+                });
+
+                // 上边、右边、下边、左边
+                for i in 0..4 {
+                    let (sx, sy) = corners[i];
+                    let (ex, ey) = corners[(i + 1) % 4];
+                    vertices.extend_from_slice(&[
+                        sx, sy, color[0], color[1], color[2], 1.0, *thickness, ex, ey, color[0],
+                        color[1], color[2], 1.0, *thickness,
+                    ]);
+                }
+            }
+            DrawingElement::Circle {
+                center,
+                radius_x,
+                radius_y,
+                color,
+                thickness,
+                rotation,
+            } => {
+                // 🚀 在像素坐标系中计算（旋转后的）椭圆轮廓，避免NDC非等比缩放导致旋转变形
+                let outline =
+                    Self::ellipse_outline_points(*center, *radius_x, *radius_y, *rotation);
+                for segment in outline.windows(2) {
+                    let x1 = (segment[0].0 / screen_width) * 2.0 - 1.0;
+                    let y1 = 1.0 - (segment[0].1 / screen_height) * 2.0;
+                    let x2 = (segment[1].0 / screen_width) * 2.0 - 1.0;
+                    let y2 = 1.0 - (segment[1].1 / screen_height) * 2.0;
+
+                    vertices.extend_from_slice(&[
                         x1, y1, color[0], color[1], color[2], 1.0, *thickness, x2, y2, color[0],
                         color[1], color[2], 1.0, *thickness,
                     ]);
@@ -1746,30 +3129,120 @@ impl State {
                     ]);
                 }
             }
-            DrawingElement::Pen {
-                points,
+            DrawingElement::Measure {
+                start,
+                end,
                 color,
                 thickness,
             } => {
-                // 去掉步长优化，直接渲染所有点以获得最佳质量
-                for i in 0..points.len().saturating_sub(1) {
-                    let x1 = (points[i].0 / screen_width) * 2.0 - 1.0;
-                    let y1 = 1.0 - (points[i].1 / screen_height) * 2.0;
-                    let x2 = (points[i + 1].0 / screen_width) * 2.0 - 1.0;
-                    let y2 = 1.0 - (points[i + 1].1 / screen_height) * 2.0;
-
+                // 🚀 测量线和两端的短横线，尺寸文字见 collect_rotated_texts
+                let tick = self.ui(MEASURE_TICK_SIZE) + thickness / 2.0;
+                let [start_tick, end_tick] = measure::end_ticks(*start, *end, tick);
+                for (a, b) in [(*start, *end), start_tick, end_tick] {
                     vertices.extend_from_slice(&[
-                        x1, y1, color[0], color[1], color[2], 1.0, *thickness, x2, y2, color[0],
-                        color[1], color[2], 1.0, *thickness,
+                        (a.0 / screen_width) * 2.0 - 1.0,
+                        1.0 - (a.1 / screen_height) * 2.0,
+                        color[0],
+                        color[1],
+                        color[2],
+                        1.0,
+                        *thickness,
+                        (b.0 / screen_width) * 2.0 - 1.0,
+                        1.0 - (b.1 / screen_height) * 2.0,
+                        color[0],
+                        color[1],
+                        color[2],
+                        1.0,
+                        *thickness,
                     ]);
                 }
             }
+            DrawingElement::Pen { .. } => {
+                // 🚀 画笔需要实际线宽，使用三角形渲染，见 add_element_triangles_uncached
+            }
             DrawingElement::Text { .. } => {
                 // 🚀 文本渲染现在通过 wgpu-text 处理，不再添加到顶点缓冲区
                 // 文本将在单独的渲染通道中处理
                 // 🚀 修复：已完成的文本不需要边框，只有正在编辑的文本才需要边框
                 // 边框渲染在其他地方处理
             }
+            DrawingElement::Stamp { .. } => {
+                // 🚀 印章以纹理四边形绘制，见 render_stamps
+            }
+            DrawingElement::Image { .. } => {
+                // 🚀 图像图层以纹理四边形绘制，见 render_image_layers
+            }
+        }
+    }
+
+    // 🚀 添加单个元素的三角形顶点数据（无缓存版本），用于需要实际线宽的元素
+    fn add_element_triangles_uncached(&self, element: &DrawingElement, vertices: &mut Vec<f32>) {
+        if let DrawingElement::Pen {
+            points,
+            widths,
+            color,
+            thickness,
+        } = element
+        {
+            self.add_stroke_triangles(points, widths, *color, *thickness, vertices);
+        }
+    }
+
+    // 🚀 将折线笔画展开为三角形：每段一个四边形（压感笔画为梯形），每个点一个圆盘作为圆角连接和端点
+    fn add_stroke_triangles(
+        &self,
+        points: &[(f32, f32)],
+        widths: &[f32],
+        color: [f32; 3],
+        thickness: f32,
+        vertices: &mut Vec<f32>,
+    ) {
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        // 没有压感宽度的点使用固定线宽
+        let half_width_at = |i: usize| (widths.get(i).copied().unwrap_or(thickness) / 2.0).max(0.5);
+
+        let mut push_vertex = |x: f32, y: f32| {
+            let ndc_x = (x / screen_width) * 2.0 - 1.0;
+            let ndc_y = 1.0 - (y / screen_height) * 2.0;
+            vertices
+                .extend_from_slice(&[ndc_x, ndc_y, color[0], color[1], color[2], 1.0, thickness]);
+        };
+
+        // 线段四边形
+        for (i, segment) in points.windows(2).enumerate() {
+            let (start, end) = (segment[0], segment[1]);
+            let dx = end.0 - start.0;
+            let dy = end.1 - start.1;
+            let len = (dx * dx + dy * dy).sqrt();
+            if len <= f32::EPSILON {
+                continue;
+            }
+            let (dir_x, dir_y) = (-dy / len, dx / len);
+            let (start_half, end_half) = (half_width_at(i), half_width_at(i + 1));
+            let (snx, sny) = (dir_x * start_half, dir_y * start_half);
+            let (enx, eny) = (dir_x * end_half, dir_y * end_half);
+
+            push_vertex(start.0 + snx, start.1 + sny);
+            push_vertex(start.0 - snx, start.1 - sny);
+            push_vertex(end.0 + enx, end.1 + eny);
+            push_vertex(start.0 - snx, start.1 - sny);
+            push_vertex(end.0 - enx, end.1 - eny);
+            push_vertex(end.0 + enx, end.1 + eny);
+        }
+
+        // 圆角连接
+        const JOIN_SEGMENTS: i32 = 10;
+        const JOIN_ANGLE_STEP: f32 = 2.0 * std::f32::consts::PI / JOIN_SEGMENTS as f32;
+        for (index, (x, y)) in points.iter().enumerate() {
+            let half_width = half_width_at(index);
+            for i in 0..JOIN_SEGMENTS {
+                let angle1 = (i as f32) * JOIN_ANGLE_STEP;
+                let angle2 = ((i + 1) as f32) * JOIN_ANGLE_STEP;
+                push_vertex(*x, *y);
+                push_vertex(x + half_width * angle1.cos(), y + half_width * angle1.sin());
+                push_vertex(x + half_width * angle2.cos(), y + half_width * angle2.sin());
+            }
         }
     }
 
@@ -1779,6 +3252,12 @@ impl State {
         vertices.extend_from_slice(&cached_vertices);
     }
 
+    // 🚀 缓存优化的元素三角形顶点添加函数
+    fn add_element_triangles(&mut self, element: &DrawingElement, vertices: &mut Vec<f32>) {
+        let cached_vertices = self.get_cached_element_triangles(element);
+        vertices.extend_from_slice(&cached_vertices);
+    }
+
     // 🚀 准备并渲染文本元素
     fn render_text_elements(&mut self, view: &wgpu::TextureView) {
         use glyphon::{Color, TextArea, TextBounds};
@@ -1810,15 +3289,10 @@ impl State {
             } = element
             {
                 if !content.is_empty() {
-                    // 🚀 改进：使用更准确的宽度估算
-                    let char_count = content.chars().count() as f32;
-                    let estimated_width =
-                        (char_count * font_size * 0.7 + font_size * 0.2).max(100.0);
                     let buffer = self.text_renderer.create_buffer(
                         content,
                         *font_size,
-                        estimated_width,  // 改进的宽度估算
-                        *font_size * 1.3, // 增加行高
+                        &TextStyle::default(),
                     );
 
                     buffers.push(buffer);
@@ -1890,9 +3364,8 @@ impl State {
 
                 let buffer = self.text_renderer.create_buffer(
                     display_text,
-                    64.0,  // 使用很大的字体
-                    800.0, // 足够的宽度
-                    80.0,  // 足够的高度
+                    64.0, // 使用很大的字体
+                    &TextStyle::default(),
                 );
 
                 self.text_buffer_cache = Some(buffer);
@@ -1971,9 +3444,48 @@ impl State {
         }
     }
 
+    // 🚀 将元素颜色转换为 glyphon 颜色
+    fn to_glyphon_color(color: [f32; 3]) -> glyphon::Color {
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        glyphon::Color::rgba(to_u8(color[0]), to_u8(color[1]), to_u8(color[2]), 255)
+    }
+
+    // 🚀 文本的 TextArea 列表：开启描边时先在四周偏移位置用对比色绘制几遍，
+    // 最后绘制文字本身（glyphon 按 TextArea 的顺序绘制，后面的在上层）
+    fn outlined_text_areas<'a>(
+        area: glyphon::TextArea<'a>,
+        color: [f32; 3],
+        style: &TextStyle,
+        font_size: f32,
+    ) -> Vec<glyphon::TextArea<'a>> {
+        let outline_color = Self::to_glyphon_color(contrast_color(color));
+        let mut areas: Vec<_> = style
+            .outline_offsets(font_size)
+            .into_iter()
+            .map(|(dx, dy)| glyphon::TextArea {
+                left: area.left + dx * area.scale,
+                top: area.top + dy * area.scale,
+                default_color: outline_color,
+                ..area.clone()
+            })
+            .collect();
+        areas.push(area);
+        areas
+    }
+
+    // 🚀 文本的裁剪边界：排版大小四周加 padding，避免字形的抗锯齿边缘被裁掉
+    fn text_bounds(position: (f32, f32), size: (f32, f32)) -> glyphon::TextBounds {
+        glyphon::TextBounds {
+            left: (position.0 - TEXT_BOX_PADDING).floor() as i32,
+            top: (position.1 - TEXT_BOX_PADDING).floor() as i32,
+            right: (position.0 + size.0 + TEXT_BOX_PADDING).ceil() as i32,
+            bottom: (position.1 + size.1 + TEXT_BOX_PADDING).ceil() as i32,
+        }
+    }
+
     // 🚀 渲染已完成的文本
-    fn render_completed_text<'a>(&'a mut self, render_pass: &mut wgpu::RenderPass<'a>) {
-        use glyphon::{Color, TextArea, TextBounds};
+    fn render_completed_text(&mut self, render_pass: &mut wgpu::RenderPass) {
+        use glyphon::TextArea;
 
         // 🚀 修复策略：只渲染已完成的文本，正在编辑的文本通过其他方式显示
         println!(
@@ -1985,9 +3497,16 @@ impl State {
         let mut completed_buffers = Vec::new();
 
         // 收集已完成的文本元素
-        for (index, element) in self.drawing_elements.iter().enumerate() {
+        for (index, element) in self.drawing_elements.clone().iter().enumerate() {
+            // 🚀 旋转文本通过离屏纹理绘制（见 render_rotated_text）
+            if Self::is_rotated_text(element) {
+                continue;
+            }
             if let DrawingElement::Text {
-                content, font_size, ..
+                content,
+                font_size,
+                style,
+                ..
             } = element
             {
                 println!(
@@ -1997,16 +3516,7 @@ impl State {
                     content.len()
                 );
                 if !content.is_empty() {
-                    // 🚀 改进：使用更准确的宽度估算
-                    let char_count = content.chars().count() as f32;
-                    let estimated_width =
-                        (char_count * font_size * 0.7 + font_size * 0.2).max(100.0);
-                    let buffer = self.text_renderer.create_buffer(
-                        content,
-                        *font_size,
-                        estimated_width,  // 改进的宽度估算
-                        *font_size * 1.3, // 增加行高
-                    );
+                    let buffer = self.text_renderer.create_buffer(content, *font_size, style);
                     completed_buffers.push(buffer);
                     println!("🚀 为文本元素 {} 创建了buffer", index);
                 }
@@ -2016,45 +3526,33 @@ impl State {
         // 创建已完成文本的 TextArea
         let mut buffer_index = 0;
         for element in &self.drawing_elements {
+            // 🚀 旋转文本通过离屏纹理绘制（见 render_rotated_text）
+            if Self::is_rotated_text(element) {
+                continue;
+            }
             if let DrawingElement::Text {
                 position,
                 content,
+                color,
+                size,
                 font_size,
+                style,
                 ..
             } = element
             {
                 if !content.is_empty() && buffer_index < completed_buffers.len() {
-                    // 🚀 修复：计算多行文本的边界
-                    let lines: Vec<&str> = content.split('\n').collect();
-                    let line_count = lines.len() as f32;
-                    let max_line_width = lines
-                        .iter()
-                        .map(|line| {
-                            // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                            let char_count = line.chars().count() as f32;
-                            let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                            estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                        })
-                        .fold(0.0, f32::max);
-
-                    let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                    let text_height = font_size * 1.2 * line_count;
-
                     let text_area = TextArea {
                         buffer: &completed_buffers[buffer_index],
                         left: position.0,
                         top: position.1,
                         scale: 1.0,
-                        bounds: TextBounds {
-                            left: position.0 as i32,
-                            top: position.1 as i32,
-                            right: (position.0 + text_width) as i32,
-                            bottom: (position.1 + text_height) as i32,
-                        },
-                        default_color: Color::rgba(255, 0, 0, 255), // 红色文字
+                        bounds: Self::text_bounds(*position, *size),
+                        default_color: Self::to_glyphon_color(*color),
                         custom_glyphs: &[],
                     };
-                    completed_text_areas.push(text_area);
+                    completed_text_areas.extend(Self::outlined_text_areas(
+                        text_area, *color, style, *font_size,
+                    ));
                     buffer_index += 1;
                 }
             }
@@ -2082,74 +3580,57 @@ impl State {
     }
 
     // 🚀 渲染所有文本（已完成的文本 + 正在编辑的文本）
-    fn render_all_text_with_editing<'a>(&'a mut self, render_pass: &mut wgpu::RenderPass<'a>) {
-        use glyphon::{Color, TextArea, TextBounds};
+    fn render_all_text_with_editing(&mut self, render_pass: &mut wgpu::RenderPass) {
+        use glyphon::TextArea;
 
         println!("🚀 开始渲染所有文本（包括正在编辑的）");
 
         let mut all_text_areas = Vec::new();
         let mut all_buffers = Vec::new();
-
-        // 移除测试文字，现在只显示用户输入的文字
+        let editing_rotated = self
+            .current_drawing
+            .as_ref()
+            .is_some_and(Self::is_rotated_text);
 
         // 1. 首先为已完成的文本创建 buffers
-        for (index, element) in self.drawing_elements.iter().enumerate() {
+        for (index, element) in self.drawing_elements.clone().iter().enumerate() {
+            // 🚀 旋转文本通过离屏纹理绘制（见 render_rotated_text）
+            if Self::is_rotated_text(element) {
+                continue;
+            }
             if let DrawingElement::Text {
-                content, font_size, ..
+                content,
+                font_size,
+                style,
+                ..
             } = element
             {
                 if !content.is_empty() {
                     println!("🚀 为已完成文本 {} 创建buffer: '{}'", index, content);
-                    // 🚀 改进：使用更准确的宽度估算
-                    let char_count = content.chars().count() as f32;
-                    let estimated_width =
-                        (char_count * font_size * 0.7 + font_size * 0.2).max(100.0);
-                    let buffer = self.text_renderer.create_buffer(
-                        content,
-                        *font_size,
-                        estimated_width,  // 改进的宽度估算
-                        *font_size * 1.3, // 增加行高
-                    );
+                    let buffer = self.text_renderer.create_buffer(content, *font_size, style);
                     all_buffers.push(buffer);
                 }
             }
         }
 
-        // 2. 为正在编辑的文本创建 buffer
+        // 2. 为正在编辑的文本创建 buffer（显示内容包含光标，需要单独测量）
+        let mut editing_size = (0.0, 0.0);
         if let Some(DrawingElement::Text {
             is_editing,
             font_size,
+            style,
             ..
-        }) = &self.current_drawing
+        }) = self.current_drawing.clone()
         {
-            if *is_editing {
+            if is_editing && !editing_rotated {
                 // 🚀 修复：不显示默认提示文字，只显示用户输入的内容
-                let display_text = if self.current_text_input.is_empty() {
-                    CURSOR_CHAR.to_string() // 只显示光标
-                } else {
-                    // 🚀 修复：使用正确的光标位置显示（与其他地方保持一致）
-                    let mut chars: Vec<char> = self.current_text_input.chars().collect();
-                    let cursor_pos = self.text_cursor_position.min(chars.len());
-                    chars.insert(cursor_pos, CURSOR_CHAR);
-                    let result = chars.into_iter().collect::<String>();
-                    println!(
-                        "🚀 Buffer创建文本（带光标）: {:?}, 光标位置: {}",
-                        result, cursor_pos
-                    );
-                    result
-                };
-
+                let display_text = self.editing_display_text();
                 println!("🚀 为正在编辑的文本创建buffer: '{}'", display_text);
 
-                // 🚀 改进：使用更准确的宽度估算
-                let char_count = display_text.chars().count() as f32;
-                let estimated_width = (char_count * font_size * 0.7 + font_size * 0.2).max(100.0);
-                let editing_buffer = self.text_renderer.create_buffer(
-                    &display_text,
-                    *font_size,
-                    estimated_width, // 改进的宽度估算
-                    font_size * 1.3, // 增加行高
-                );
+                editing_size = self.text_renderer.measure(&display_text, font_size, &style);
+                let editing_buffer =
+                    self.text_renderer
+                        .create_buffer(&display_text, font_size, &style);
                 all_buffers.push(editing_buffer);
             }
         }
@@ -2159,45 +3640,33 @@ impl State {
 
         // 为已完成的文本创建 TextAreas
         for element in &self.drawing_elements {
+            // 🚀 旋转文本通过离屏纹理绘制（见 render_rotated_text）
+            if Self::is_rotated_text(element) {
+                continue;
+            }
             if let DrawingElement::Text {
                 position,
                 content,
+                color,
+                size,
                 font_size,
+                style,
                 ..
             } = element
             {
                 if !content.is_empty() && buffer_index < all_buffers.len() {
-                    // 🚀 修复：计算多行文本的边界
-                    let lines: Vec<&str> = content.split('\n').collect();
-                    let line_count = lines.len() as f32;
-                    let max_line_width = lines
-                        .iter()
-                        .map(|line| {
-                            // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                            let char_count = line.chars().count() as f32;
-                            let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                            estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                        })
-                        .fold(0.0, f32::max);
-
-                    let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                    let text_height = font_size * 1.2 * line_count;
-
                     let text_area = TextArea {
                         buffer: &all_buffers[buffer_index],
                         left: position.0,
                         top: position.1,
                         scale: 1.0,
-                        bounds: TextBounds {
-                            left: position.0 as i32,
-                            top: position.1 as i32,
-                            right: (position.0 + text_width) as i32,
-                            bottom: (position.1 + text_height) as i32,
-                        },
-                        default_color: Color::rgba(255, 0, 0, 255), // 红色文字
+                        bounds: Self::text_bounds(*position, *size),
+                        default_color: Self::to_glyphon_color(*color),
                         custom_glyphs: &[],
                     };
-                    all_text_areas.push(text_area);
+                    all_text_areas.extend(Self::outlined_text_areas(
+                        text_area, *color, style, *font_size,
+                    ));
                     buffer_index += 1;
                 }
             }
@@ -2207,75 +3676,36 @@ impl State {
         if let Some(DrawingElement::Text {
             position,
             is_editing,
+            color,
             font_size,
+            style,
             ..
         }) = &self.current_drawing
         {
-            if *is_editing && buffer_index < all_buffers.len() {
-                // 🚀 修复：不显示默认提示文字，只显示用户输入的内容
-                let display_text = if self.current_text_input.is_empty() {
-                    CURSOR_CHAR.to_string() // 只显示光标
-                } else {
-                    // 🚀 修复：正确处理光标位置和字符索引
-                    let mut chars: Vec<char> = self.current_text_input.chars().collect();
-                    let cursor_pos = self.text_cursor_position.min(chars.len());
-                    chars.insert(cursor_pos, CURSOR_CHAR);
-                    let result = chars.into_iter().collect::<String>();
-                    println!(
-                        "🚀 显示文本（带光标）: {:?}, 光标位置: {}",
-                        result, cursor_pos
-                    );
-                    result
-                };
-
-                // 🚀 修复：计算多行文本的边界，增加额外空间防止截断
-                let lines: Vec<&str> = display_text.split('\n').collect();
-                let line_count = lines.len() as f32;
-                let max_line_width = lines
-                    .iter()
-                    .map(|line| {
-                        // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                        let char_count = line.chars().count() as f32;
-                        let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                        estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                    })
-                    .fold(0.0, f32::max);
-
-                let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                let text_height = font_size * 1.3 * line_count; // 增加行高系数
-
-                // 🚀 修复：为文本渲染添加padding，防止文字被截断
-                let padding = 4.0; // 减少padding，避免过多空白
+            if *is_editing && !editing_rotated && buffer_index < all_buffers.len() {
+                let bounds = Self::text_bounds(*position, editing_size);
+                println!(
+                    "🚀 创建TextArea - 位置: ({}, {}), 边界: ({}, {}, {}, {})",
+                    position.0, position.1, bounds.left, bounds.top, bounds.right, bounds.bottom
+                );
                 let editing_text_area = TextArea {
                     buffer: &all_buffers[buffer_index],
                     left: position.0,
                     top: position.1,
                     scale: 1.0,
-                    bounds: TextBounds {
-                        left: (position.0 - padding) as i32,
-                        top: (position.1 - padding) as i32,
-                        right: (position.0 + text_width + padding) as i32,
-                        bottom: (position.1 + text_height + padding) as i32,
-                    },
-                    default_color: Color::rgba(255, 0, 0, 255), // 红色文字，更容易看见
+                    bounds,
+                    default_color: Self::to_glyphon_color(*color),
                     custom_glyphs: &[],
                 };
-
-                println!(
-                    "🚀 创建TextArea - 位置: ({}, {}), 边界: ({}, {}, {}, {}), 颜色: 白色",
-                    position.0,
-                    position.1,
-                    (position.0 - padding) as i32,
-                    (position.1 - padding) as i32,
-                    (position.0 + text_width + padding) as i32,
-                    (position.1 + text_height + padding) as i32
-                );
-                all_text_areas.push(editing_text_area);
+                all_text_areas.extend(Self::outlined_text_areas(
+                    editing_text_area,
+                    *color,
+                    style,
+                    *font_size,
+                ));
             }
         }
 
-        // 不再显示测试文字，只显示用户输入的文字
-
         // 3. 一次性渲染所有文本
         if !all_text_areas.is_empty() {
             if let Err(e) =
@@ -2292,55 +3722,38 @@ impl State {
     }
 
     // 🚀 渲染文本外框
-    fn render_text_border(&self, vertices: &mut Vec<f32>) {
-        if let Some(DrawingElement::Text {
-            position,
-            is_editing,
-            font_size,
-            ..
-        }) = &self.current_drawing
+    fn render_text_border(&mut self, vertices: &mut Vec<f32>) {
+        if let Some(
+            ref element @ DrawingElement::Text {
+                position,
+                is_editing,
+                font_size,
+                ref style,
+                ..
+            },
+        ) = self.current_drawing.clone()
+            && is_editing
         {
-            if *is_editing {
-                // 🚀 使用实际的用户输入内容和字体大小来计算边框
-                let display_text = if self.current_text_input.is_empty() {
-                    CURSOR_CHAR.to_string() // 只显示光标
-                } else {
-                    // 🚀 修复：正确处理光标位置和字符索引
-                    let mut chars: Vec<char> = self.current_text_input.chars().collect();
-                    let cursor_pos = self.text_cursor_position.min(chars.len());
-                    chars.insert(cursor_pos, CURSOR_CHAR);
-                    let result = chars.into_iter().collect::<String>();
-                    println!(
-                        "🚀 边框文本（带光标）: {:?}, 光标位置: {}",
-                        result, cursor_pos
-                    );
-                    result
-                };
-
-                // 🚀 修复：动态计算多行文本的宽度和高度，增加额外空间防止截断
-                let lines: Vec<&str> = display_text.split('\n').collect();
-                let line_count = lines.len() as f32;
-                let max_line_width = lines
-                    .iter()
-                    .map(|line| {
-                        // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                        let char_count = line.chars().count() as f32;
-                        let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                        estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                    })
-                    .fold(0.0, f32::max);
-
-                let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                let text_height = font_size * 1.3 * line_count; // 增加行高系数
-
-                // 🚀 修复：使用黑色虚线边框代替青色实线边框
-                self.add_dashed_text_border(*position, text_width, text_height, vertices);
-
-                println!(
-                    "🚀 添加文本边框: 位置=({}, {}) 大小={}x{}",
-                    position.0, position.1, text_width, text_height
+            // 🚀 使用实际的用户输入内容（含光标）测量边框大小
+            let display_text = self.editing_display_text();
+            let (text_width, text_height) =
+                self.text_renderer.measure(&display_text, font_size, style);
+
+            // 🚀 修复：使用黑色虚线边框代替青色实线边框
+            if let Some((center, rotation, _)) = Self::shape_rotation_frame(element) {
+                self.add_dashed_text_border(
+                    position,
+                    text_width,
+                    text_height,
+                    (center, rotation),
+                    vertices,
                 );
             }
+
+            println!(
+                "🚀 添加文本边框: 位置=({}, {}) 大小={}x{}",
+                position.0, position.1, text_width, text_height
+            );
         }
     }
 
@@ -2480,6 +3893,49 @@ impl State {
             "</svg>"
         );
 
+        const STAMP_SVG: &str = concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" ",
+            "viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" ",
+            "stroke-linecap=\"round\" stroke-linejoin=\"round\" ",
+            "class=\"lucide lucide-stamp-icon lucide-stamp\">",
+            "<path d=\"M5 22h14\"/>",
+            "<path d=\"M19.27 13.73A2.5 2.5 0 0 0 17.5 13h-11A2.5 2.5 0 0 0 4 15.5V17a1 1 0 0 0 1 1h14",
+            "a1 1 0 0 0 1-1v-1.5c0-.66-.26-1.3-.73-1.77Z\"/>",
+            "<path d=\"M14 13V8.5C14 7 15 7 15 5a3 3 0 0 0-3-3c-1.69 0-3 1-3 3s1 2 1 3.5V13\"/>",
+            "</svg>"
+        );
+
+        const ERASER_SVG: &str = concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" ",
+            "viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" ",
+            "stroke-linecap=\"round\" stroke-linejoin=\"round\" ",
+            "class=\"lucide lucide-eraser-icon lucide-eraser\">",
+            "<path d=\"M21 21H8a2 2 0 0 1-1.42-.587l-3.994-3.999a2 2 0 0 1 0-2.828l10-10",
+            "a2 2 0 0 1 2.829 0l5.999 6a2 2 0 0 1 0 2.828L12.834 21\"/>",
+            "<path d=\"m5.082 11.09 8.828 8.828\"/></svg>"
+        );
+
+        const COLOR_PICKER_SVG: &str = concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" ",
+            "viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" ",
+            "stroke-linecap=\"round\" stroke-linejoin=\"round\" ",
+            "class=\"lucide lucide-pipette-icon lucide-pipette\">",
+            "<path d=\"m2 22 1-1h3l9-9\"/><path d=\"M3 21v-3l9-9\"/>",
+            "<path d=\"m15 6 3.4-3.4a2.1 2.1 0 1 1 3 3L18 9l.4.4a2.1 2.1 0 1 1-3 3l-3.8-3.8",
+            "a2.1 2.1 0 1 1 3-3l.4.4Z\"/></svg>"
+        );
+
+        const MEASURE_SVG: &str = concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" ",
+            "viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" ",
+            "stroke-linecap=\"round\" stroke-linejoin=\"round\" ",
+            "class=\"lucide lucide-ruler-icon lucide-ruler\">",
+            "<path d=\"M21.3 15.3a2.4 2.4 0 0 1 0 3.4l-2.6 2.6a2.4 2.4 0 0 1-3.4 0L2.7 8.7",
+            "a2.41 2.41 0 0 1 0-3.4l2.6-2.6a2.41 2.41 0 0 1 3.4 0Z\"/>",
+            "<path d=\"m14.5 12.5 2-2\"/><path d=\"m11.5 9.5 2-2\"/>",
+            "<path d=\"m8.5 6.5 2-2\"/><path d=\"m17.5 15.5 2-2\"/></svg>"
+        );
+
         const UNDO_SVG: &str = concat!(
             "<svg viewBox=\"0 0 24 24\" xmlns=\"http://www.w3.org/2000/svg\">",
             "<path d=\"M1 4v6h6\" stroke=\"#000000\" stroke-width=\"2\" fill=\"none\"/>",
@@ -2487,6 +3943,22 @@ impl State {
             "</svg>"
         );
 
+        const REDO_SVG: &str = concat!(
+            "<svg viewBox=\"0 0 24 24\" xmlns=\"http://www.w3.org/2000/svg\">",
+            "<path d=\"M23 4v6h-6\" stroke=\"#000000\" stroke-width=\"2\" fill=\"none\"/>",
+            "<path d=\"M20.49 15a9 9 0 1 1-2.13-9.36L23 10\" stroke=\"#000000\" stroke-width=\"2\" fill=\"none\"/>",
+            "</svg>"
+        );
+
+        const HISTORY_SVG: &str = concat!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" ",
+            "viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" ",
+            "stroke-linecap=\"round\" stroke-linejoin=\"round\" ",
+            "class=\"lucide lucide-history-icon lucide-history\">",
+            "<path d=\"M3 12a9 9 0 1 0 9-9 9.75 9.75 0 0 0-6.74 2.74L3 8\"/>",
+            "<path d=\"M3 3v5h5\"/><path d=\"M12 7v5l4 2\"/></svg>"
+        );
+
         const SAVE_SVG: &str = concat!(
             "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" ",
             "viewBox=\"0 0 24 24\" fill=\"none\" stroke=\"currentColor\" stroke-width=\"2\" ",
@@ -2519,13 +3991,19 @@ impl State {
             (Tool::Arrow, ARROW_SVG),
             (Tool::Pen, PEN_SVG),
             (Tool::Text, TEXT_SVG),
+            (Tool::Stamp, STAMP_SVG),
+            (Tool::Eraser, ERASER_SVG),
+            (Tool::ColorPicker, COLOR_PICKER_SVG),
+            (Tool::Measure, MEASURE_SVG),
             (Tool::Undo, UNDO_SVG),
+            (Tool::Redo, REDO_SVG),
+            (Tool::History, HISTORY_SVG),
             (Tool::Save, SAVE_SVG),
             (Tool::Exit, EXIT_SVG),
             (Tool::Complete, COMPLETE_SVG),
         ];
 
-        let icon_size = 32;
+        let icon_size = self.ui(TOOLBAR_ICON_SIZE).round() as u32;
 
         for (tool, svg_data) in icons.iter() {
             let texture = self.load_svg_texture(svg_data, icon_size);
@@ -2614,46 +4092,80 @@ impl State {
                 is_selected: false,
             },
             ToolbarButton {
-                tool: Tool::Undo,
+                tool: Tool::Stamp,
                 rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
                 is_selected: false,
             },
             ToolbarButton {
-                tool: Tool::Save,
+                tool: Tool::Eraser,
                 rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
                 is_selected: false,
             },
             ToolbarButton {
-                tool: Tool::Exit,
+                tool: Tool::ColorPicker,
                 rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
                 is_selected: false,
             },
             ToolbarButton {
-                tool: Tool::Complete,
+                tool: Tool::Measure,
                 rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
                 is_selected: false,
             },
-        ];
-        self.update_toolbar_layout();
-    }
-
-    fn update_toolbar_layout(&mut self) {
+            ToolbarButton {
+                tool: Tool::Undo,
+                rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
+                is_selected: false,
+            },
+            ToolbarButton {
+                tool: Tool::Redo,
+                rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
+                is_selected: false,
+            },
+            ToolbarButton {
+                tool: Tool::History,
+                rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
+                is_selected: false,
+            },
+            ToolbarButton {
+                tool: Tool::Save,
+                rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
+                is_selected: false,
+            },
+            ToolbarButton {
+                tool: Tool::Exit,
+                rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
+                is_selected: false,
+            },
+            ToolbarButton {
+                tool: Tool::Complete,
+                rect: (0.0, 0.0, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE),
+                is_selected: false,
+            },
+        ];
+        self.update_toolbar_layout();
+    }
+
+    fn update_toolbar_layout(&mut self) {
         if let Some((box_min_x, box_min_y, _box_max_x, box_max_y)) = self.current_box_coords {
+            let toolbar_height = self.ui(TOOLBAR_HEIGHT);
+            let button_size = self.ui(TOOLBAR_BUTTON_SIZE);
+            let button_margin = self.ui(TOOLBAR_BUTTON_MARGIN);
+
             // 计算工具栏宽度（与shader中的计算保持一致）
-            let total_width = (self.toolbar_buttons.len() as f32) * TOOLBAR_BUTTON_SIZE
-                + ((self.toolbar_buttons.len() - 1) as f32) * TOOLBAR_BUTTON_MARGIN;
+            let total_width = (self.toolbar_buttons.len() as f32) * button_size
+                + ((self.toolbar_buttons.len() - 1) as f32) * button_margin;
 
             // 首先尝试在框的下方
-            let mut toolbar_y = box_max_y + 5.0;
-            let toolbar_bottom = toolbar_y + TOOLBAR_HEIGHT;
+            let mut toolbar_y = box_max_y + self.ui(5.0);
+            let toolbar_bottom = toolbar_y + toolbar_height;
 
             // 如果超出屏幕下边界，移到框的上方
             if toolbar_bottom > self.size.height as f32 {
-                toolbar_y = box_min_y - TOOLBAR_HEIGHT - 10.0;
+                toolbar_y = box_min_y - toolbar_height - self.ui(10.0);
 
                 // 如果移到上方还是超出屏幕，则放在屏幕顶部
                 if toolbar_y < 0.0 {
-                    toolbar_y = 10.0;
+                    toolbar_y = self.ui(10.0);
                 }
             }
 
@@ -2667,14 +4179,13 @@ impl State {
 
             // 更新每个按钮的位置（考虑垂直居中）
             for (i, button) in self.toolbar_buttons.iter_mut().enumerate() {
-                let x =
-                    toolbar_start_x + (i as f32) * (TOOLBAR_BUTTON_SIZE + TOOLBAR_BUTTON_MARGIN);
+                let x = toolbar_start_x + (i as f32) * (button_size + button_margin);
 
                 // 按钮在工具栏内垂直居中
-                let button_y_offset = (TOOLBAR_HEIGHT - TOOLBAR_BUTTON_SIZE) * 0.5;
+                let button_y_offset = (toolbar_height - button_size) * 0.5;
                 let y = toolbar_y + button_y_offset;
 
-                button.rect = (x, y, TOOLBAR_BUTTON_SIZE, TOOLBAR_BUTTON_SIZE);
+                button.rect = (x, y, button_size, button_size);
             }
         }
     }
@@ -2719,28 +4230,734 @@ impl State {
         match tool {
             Tool::Undo => {
                 // 撤销按钮：无撤销历史时禁用
-                self.undo_stack.is_empty()
+                !self.history.can_undo()
             }
+            // 重做按钮：无重做历史时禁用
+            Tool::Redo => !self.history.can_redo(),
             _ => false, // 其他按钮默认启用
         }
     }
 
+    // 🚀 文本属性栏：选择文本工具、编辑或选中文本时显示在工具栏旁边
+    fn text_properties_visible(&self) -> bool {
+        self.show_toolbar
+            && !self.history_panel_visible()
+            && (self.current_tool == Tool::Text
+                || self.text_input_active
+                || self.selected_text_element().is_some())
+    }
+
+    fn selected_text_element(&self) -> Option<&DrawingElement> {
+        let index = self.selected_element.as_ref()?.index;
+        self.drawing_elements
+            .get(index)
+            .filter(|element| matches!(element, DrawingElement::Text { .. }))
+    }
+
+    // 🚀 属性栏显示的样式：正在编辑的文本 > 选中的文本 > 新文本使用的样式
+    fn active_text_style(&self) -> TextStyle {
+        let element = self
+            .current_drawing
+            .as_ref()
+            .filter(|_| self.text_input_active)
+            .or(self.selected_text_element());
+        match element {
+            Some(DrawingElement::Text { style, .. }) => style.clone(),
+            _ => self.current_text_style.clone(),
+        }
+    }
+
+    // 🚀 工具栏附属面板（属性栏、印章面板）的左上角：紧贴工具栏下方，放不下时放到工具栏上方
+    fn toolbar_panel_origin(&self, height: f32) -> Option<(f32, f32)> {
+        let (toolbar_x, button_y, _, button_size) = self.toolbar_buttons.first()?.rect;
+        let toolbar_height = self.ui(TOOLBAR_HEIGHT);
+        let toolbar_top = button_y - (toolbar_height - button_size) * 0.5;
+        let mut y = toolbar_top + toolbar_height + self.ui(5.0);
+        if y + height > self.size.height as f32 {
+            y = (toolbar_top - self.ui(5.0) - height).max(0.0);
+        }
+        Some((toolbar_x, y))
+    }
+
+    // 🚀 印章面板：选择印章工具或选中印章时显示在工具栏旁边
+    fn stamp_palette_visible(&self) -> bool {
+        self.show_toolbar && !self.history_panel_visible() && self.current_tool == Tool::Stamp
+    }
+
+    // 🚀 印章面板的背景矩形
+    fn stamp_palette_bar(&self) -> Option<(f32, f32, f32, f32)> {
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let button_size = self.ui(STAMP_BUTTON_SIZE);
+        let height = button_size + margin * 2.0;
+        let width = self.stamp_palette.len() as f32 * (button_size + margin) + margin;
+        let (x, y) = self.toolbar_panel_origin(height)?;
+        Some((x, y, width, height))
+    }
+
+    // 🚀 印章面板各按钮的矩形 (x, y, w, h)，与 stamp_palette 一一对应
+    fn stamp_palette_rects(&self) -> Vec<(f32, f32, f32, f32)> {
+        let Some((bar_x, bar_y, _, _)) = self.stamp_palette_bar() else {
+            return Vec::new();
+        };
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let size = self.ui(STAMP_BUTTON_SIZE);
+        (0..self.stamp_palette.len())
+            .map(|index| {
+                let x = bar_x + margin + index as f32 * (size + margin);
+                (x, bar_y + margin, size, size)
+            })
+            .collect()
+    }
+
+    fn stamp_palette_at(&self, pos: (f32, f32)) -> Option<usize> {
+        if !self.stamp_palette_visible() {
+            return None;
+        }
+        self.stamp_palette_rects()
+            .into_iter()
+            .position(|(x, y, w, h)| pos.0 >= x && pos.0 <= x + w && pos.1 >= y && pos.1 <= y + h)
+    }
+
+    // 🚀 选择印章：新印章使用它，选中的印章元素同时替换图像
+    fn select_stamp(&mut self, index: usize) {
+        let Some(new_stamp) = self.stamp_palette.get(index).cloned() else {
+            return;
+        };
+        self.current_stamp = index;
+        self.modify_selected_element("Replace", |element| match element {
+            DrawingElement::Stamp { stamp, .. } if *stamp != new_stamp => {
+                *stamp = new_stamp;
+                true
+            }
+            _ => false,
+        });
+        println!("🚀 当前印章: {}", self.stamp_palette[index].key());
+        self.needs_redraw = true;
+    }
+
+    // 🚀 印章工具下输入的表情符号：加入印章面板（已存在则直接选择）
+    fn add_emoji_stamp(&mut self, emoji: String) {
+        let stamp = Stamp::Emoji(emoji);
+        let index = match self.stamp_palette.iter().position(|entry| *entry == stamp) {
+            Some(index) => index,
+            None => {
+                self.stamp_palette.push(stamp);
+                self.stamp_palette.len() - 1
+            }
+        };
+        self.select_stamp(index);
+    }
+
+    // 🚀 渲染印章面板：背景、当前印章高亮和印章预览
+    fn render_stamp_palette(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let Some(bar_rect) = self.stamp_palette_bar() else {
+            return;
+        };
+        let rects = self.stamp_palette_rects();
+
+        let mut triangles = Vec::new();
+        self.push_rect_triangles(bar_rect, TEXT_PROPERTY_BAR_COLOR, &mut triangles);
+        for (index, rect) in rects.iter().enumerate() {
+            let color = if index == self.current_stamp {
+                TEXT_PROPERTY_ACTIVE_COLOR
+            } else {
+                TEXT_PROPERTY_BUTTON_COLOR
+            };
+            self.push_rect_triangles(*rect, color, &mut triangles);
+        }
+        self.draw_drawing_vertices(render_pass, &[], &triangles);
+
+        let inset = self.ui(TEXT_PROPERTY_MARGIN);
+        let raster_size = self.stamp_raster_size(self.ui(STAMP_BUTTON_SIZE));
+        let quads: Vec<_> = self
+            .stamp_palette
+            .iter()
+            .zip(&rects)
+            .filter_map(|(stamp, (x, y, w, h))| {
+                let key = Self::stamp_texture_key(stamp, raster_size);
+                let texture = self.stamp_textures.get(&key)?;
+                let rect = (x + inset, y + inset, w - inset * 2.0, h - inset * 2.0);
+                let center = (x + w / 2.0, y + h / 2.0);
+                Some((rect, center, 0.0, 1.0, &texture.bind_group))
+            })
+            .collect();
+        self.draw_textured_quads(render_pass, &quads);
+    }
+
+    // 🚀 历史记录面板：占用属性栏的位置，打开时属性栏和印章面板隐藏
+    // （界面文字渲染器每帧只能准备一组标签）
+    fn history_panel_visible(&self) -> bool {
+        self.show_toolbar && self.show_history_panel
+    }
+
+    // 🚀 历史记录面板的背景矩形和各行 (历史位置, 名称, 矩形)。
+    // 位置 0 是初始状态（最旧的步骤因超出限制被丢弃后是保留的最旧状态），
+    // 位置 n 是执行完第 n 步之后；步骤过多时只显示当前位置附近的行
+    fn history_panel_layout(&self) -> Option<HistoryPanelLayout> {
+        let first = if self.history.evicted() > 0 {
+            "Oldest kept state"
+        } else {
+            "Initial state"
+        };
+        let names: Vec<&str> = std::iter::once(first)
+            .chain(self.history.labels())
+            .collect();
+        let current = self.history.undo_len();
+        let start = names
+            .len()
+            .saturating_sub(HISTORY_PANEL_MAX_ROWS)
+            .min(current);
+        let count = (names.len() - start).min(HISTORY_PANEL_MAX_ROWS);
+
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let row_height = self.ui(HISTORY_ROW_HEIGHT);
+        let width = self.ui(HISTORY_PANEL_WIDTH);
+        let height = count as f32 * row_height + margin * 2.0;
+        let (x, y) = self.toolbar_panel_origin(height)?;
+
+        let rows = names[start..start + count]
+            .iter()
+            .enumerate()
+            .map(|(row, name)| {
+                let rect = (
+                    x + margin,
+                    y + margin + row as f32 * row_height,
+                    width - margin * 2.0,
+                    row_height,
+                );
+                (start + row, name.to_string(), rect)
+            })
+            .collect();
+        Some(((x, y, width, height), rows))
+    }
+
+    fn history_panel_at(&self, pos: (f32, f32)) -> Option<usize> {
+        if !self.history_panel_visible() {
+            return None;
+        }
+        let (_, rows) = self.history_panel_layout()?;
+        rows.into_iter()
+            .find(|(_, _, (x, y, w, h))| {
+                pos.0 >= *x && pos.0 <= x + w && pos.1 >= *y && pos.1 <= y + h
+            })
+            .map(|(position, _, _)| position)
+    }
+
+    // 🚀 渲染历史记录面板：当前位置高亮，已撤销的步骤显示为灰色
+    fn render_history_panel(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let Some((bar_rect, rows)) = self.history_panel_layout() else {
+            return;
+        };
+        let current = self.history.undo_len();
+
+        let mut triangles = Vec::new();
+        self.push_rect_triangles(bar_rect, TEXT_PROPERTY_BAR_COLOR, &mut triangles);
+        for (position, _, rect) in &rows {
+            if *position == current {
+                self.push_rect_triangles(*rect, TEXT_PROPERTY_ACTIVE_COLOR, &mut triangles);
+            }
+        }
+        self.draw_drawing_vertices(render_pass, &[], &triangles);
+
+        let font_size = self.ui(TEXT_PROPERTY_LABEL_SIZE);
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let style = TextStyle::default();
+        let buffers: Vec<_> = rows
+            .iter()
+            .map(|(position, name, (x, y, w, h))| {
+                let (_, label_height) = self.text_renderer.measure(name, font_size, &style);
+                let buffer = self.text_renderer.create_buffer(name, font_size, &style);
+                let [r, g, b, _] = TEXT_PROPERTY_LABEL_COLOR;
+                let color = if *position > current {
+                    HISTORY_REDO_LABEL_COLOR
+                } else {
+                    [r, g, b]
+                };
+                let top = y + (h - label_height) / 2.0;
+                (buffer, color, (x + margin, top), (*x, *y, *w, *h))
+            })
+            .collect();
+
+        let text_areas =
+            buffers.iter().map(
+                |(buffer, color, (left, top), (x, y, w, h))| glyphon::TextArea {
+                    buffer,
+                    left: *left,
+                    top: *top,
+                    scale: 1.0,
+                    bounds: glyphon::TextBounds {
+                        left: *x as i32,
+                        top: *y as i32,
+                        right: (x + w - margin) as i32,
+                        bottom: (y + h) as i32,
+                    },
+                    default_color: Self::to_glyphon_color(*color),
+                    custom_glyphs: &[],
+                },
+            );
+        if let Err(e) = self
+            .text_renderer
+            .prepare_ui(&self.device, &self.queue, text_areas)
+        {
+            eprintln!("Failed to prepare history panel labels: {:?}", e);
+        } else if let Err(e) = self.text_renderer.render_ui(render_pass) {
+            eprintln!("Failed to render history panel labels: {:?}", e);
+        }
+    }
+
+    // 🚀 跳转到历史中的某个位置：连续撤销或重做，最后统一刷新
+    fn jump_to_history(&mut self, position: usize) {
+        let mut affected = Vec::new();
+        let mut region = self.capture_region();
+        while self.history.undo_len() > position {
+            let Some(edits) = self.history.undo(&mut self.drawing_elements, &mut region) else {
+                break;
+            };
+            affected.extend(edits.iter().flat_map(Edit::elements).cloned());
+        }
+        while self.history.undo_len() < position {
+            let Some(edits) = self.history.redo(&mut self.drawing_elements, &mut region) else {
+                break;
+            };
+            affected.extend(edits.iter().flat_map(Edit::elements).cloned());
+        }
+        self.after_history_step(affected, region);
+        println!("🔄 跳转到历史位置: {}", self.history.undo_len());
+    }
+
+    // 🚀 属性栏背景和各按钮的位置
+    fn text_properties_layout(&self) -> Option<TextPropertiesLayout> {
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let height = self.ui(TEXT_PROPERTY_BUTTON_SIZE) + margin * 2.0;
+        let (toolbar_x, y) = self.toolbar_panel_origin(height)?;
+
+        let mut x = toolbar_x + margin;
+        let buttons: Vec<_> = TEXT_PROPERTIES
+            .iter()
+            .map(|property| {
+                let button_x = x;
+                x += self.text_property_width(*property) + margin;
+                (*property, button_x)
+            })
+            .collect();
+        let width = x - toolbar_x;
+        Some(((toolbar_x, y, width, height), buttons))
+    }
+
+    fn text_property_width(&self, property: TextProperty) -> f32 {
+        self.ui(match property {
+            TextProperty::Family => TEXT_PROPERTY_FAMILY_WIDTH,
+            TextProperty::LineSpacing => TEXT_PROPERTY_SPACING_WIDTH,
+            _ => TEXT_PROPERTY_BUTTON_SIZE,
+        })
+    }
+
+    // 🚀 属性栏按钮的矩形 (x, y, w, h)
+    fn text_property_rects(&self) -> Vec<(TextProperty, (f32, f32, f32, f32))> {
+        let Some(((_, bar_y, _, _), buttons)) = self.text_properties_layout() else {
+            return Vec::new();
+        };
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let size = self.ui(TEXT_PROPERTY_BUTTON_SIZE);
+        buttons
+            .into_iter()
+            .map(|(property, x)| {
+                let width = self.text_property_width(property);
+                (property, (x, bar_y + margin, width, size))
+            })
+            .collect()
+    }
+
+    fn text_property_at(&self, pos: (f32, f32)) -> Option<TextProperty> {
+        if !self.text_properties_visible() {
+            return None;
+        }
+        self.text_property_rects()
+            .into_iter()
+            .find(|(_, (x, y, w, h))| {
+                pos.0 >= *x && pos.0 <= x + w && pos.1 >= *y && pos.1 <= y + h
+            })
+            .map(|(property, _)| property)
+    }
+
+    // 🚀 点击属性栏按钮：修改新文本的样式，同时修改正在编辑或选中的文本
+    fn apply_text_property(&mut self, property: TextProperty) {
+        let mut style = self.active_text_style();
+        match property {
+            TextProperty::Family => style.cycle_family(&self.text_renderer.families()),
+            TextProperty::Bold => style.bold = !style.bold,
+            TextProperty::Italic => style.italic = !style.italic,
+            TextProperty::Underline => style.underline = !style.underline,
+            TextProperty::Strikethrough => style.strikethrough = !style.strikethrough,
+            TextProperty::Background => style.background = !style.background,
+            TextProperty::Outline => style.outline = !style.outline,
+            TextProperty::Align(align) => style.align = align,
+            TextProperty::LineSpacing => style.cycle_line_spacing(),
+        }
+        self.current_text_style = style.clone();
+        println!("🚀 文本样式: {:?}", style);
+
+        if self.text_input_active {
+            if let Some(DrawingElement::Text {
+                style: element_style,
+                ..
+            }) = &mut self.current_drawing
+            {
+                *element_style = style;
+            }
+            // 重新测量排版大小，并让输入法候选窗口跟随光标
+            self.update_current_text_element();
+            return;
+        }
+
+        let Some(DrawingElement::Text {
+            content, font_size, ..
+        }) = self.selected_text_element().cloned()
+        else {
+            return;
+        };
+        let size = self.text_renderer.measure(&content, font_size, &style);
+        self.modify_selected_element("Restyle", |element| {
+            let old_frame = Self::shape_rotation_frame(element);
+            if let DrawingElement::Text {
+                style: element_style,
+                size: element_size,
+                ..
+            } = element
+            {
+                if *element_style == style {
+                    return false;
+                }
+                *element_style = style;
+                *element_size = size;
+            }
+            // 🚀 旋转文本：保持文字起点在屏幕上不动
+            Self::reanchor_rotated_element(element, old_frame);
+            true
+        });
+    }
+
+    // 🚀 屏幕坐标矩形 -> 两个三角形（NDC）
+    fn push_rect_triangles(
+        &self,
+        (x, y, w, h): (f32, f32, f32, f32),
+        [r, g, b, a]: [f32; 4],
+        vertices: &mut Vec<f32>,
+    ) {
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+        for index in [0, 1, 2, 0, 2, 3] {
+            let (px, py) = corners[index];
+            vertices.extend_from_slice(&[
+                (px / screen_width) * 2.0 - 1.0,
+                1.0 - (py / screen_height) * 2.0,
+                r,
+                g,
+                b,
+                a,
+                1.0,
+            ]);
+        }
+    }
+
+    // 🚀 渲染文本属性栏：背景、按钮状态、对齐图标和文字标签
+    fn render_text_properties_bar(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let Some((bar_rect, _)) = self.text_properties_layout() else {
+            return;
+        };
+        let style = self.active_text_style();
+        let rects = self.text_property_rects();
+        let line = self.ui(1.5);
+
+        let mut triangles = Vec::new();
+        self.push_rect_triangles(bar_rect, TEXT_PROPERTY_BAR_COLOR, &mut triangles);
+
+        let mut labels = Vec::new();
+        for (property, rect @ (x, y, w, h)) in &rects {
+            let active = match property {
+                TextProperty::Bold => style.bold,
+                TextProperty::Italic => style.italic,
+                TextProperty::Underline => style.underline,
+                TextProperty::Strikethrough => style.strikethrough,
+                TextProperty::Background => style.background,
+                TextProperty::Outline => style.outline,
+                TextProperty::Align(align) => style.align == *align,
+                TextProperty::Family | TextProperty::LineSpacing => false,
+            };
+            let color = if active {
+                TEXT_PROPERTY_ACTIVE_COLOR
+            } else {
+                TEXT_PROPERTY_BUTTON_COLOR
+            };
+            self.push_rect_triangles(*rect, color, &mut triangles);
+
+            let mut label_style = TextStyle::default();
+            let label = match property {
+                TextProperty::Family => style.family_name().to_string(),
+                TextProperty::Bold => {
+                    label_style.bold = true;
+                    "B".to_string()
+                }
+                TextProperty::Italic => {
+                    label_style.italic = true;
+                    "I".to_string()
+                }
+                TextProperty::Underline => {
+                    label_style.underline = true;
+                    "U".to_string()
+                }
+                TextProperty::Strikethrough => {
+                    label_style.strikethrough = true;
+                    "S".to_string()
+                }
+                TextProperty::Background => {
+                    // 背景图标：字母下面一块半透明底色
+                    let inset = w * 0.15;
+                    let [r, g, b, _] = TEXT_PROPERTY_LABEL_COLOR;
+                    self.push_rect_triangles(
+                        (x + inset, y + inset, w - inset * 2.0, h - inset * 2.0),
+                        [r, g, b, 0.35],
+                        &mut triangles,
+                    );
+                    "A".to_string()
+                }
+                TextProperty::Outline => {
+                    label_style.outline = true;
+                    "A".to_string()
+                }
+                TextProperty::LineSpacing => format!("{:.1}×", style.line_spacing),
+                TextProperty::Align(align) => {
+                    // 对齐图标：三条长短不一的横线
+                    let padding = w * 0.2;
+                    for (row, fraction) in [1.0, 0.6, 0.85].into_iter().enumerate() {
+                        let full = w - padding * 2.0;
+                        let width = full * fraction;
+                        let left = match align {
+                            TextAlign::Left => x + padding,
+                            TextAlign::Center => x + padding + (full - width) / 2.0,
+                            TextAlign::Right => x + w - padding - width,
+                        };
+                        let top = y + h * (0.3 + 0.2 * row as f32) - line / 2.0;
+                        self.push_rect_triangles(
+                            (left, top, width, line),
+                            TEXT_PROPERTY_LABEL_COLOR,
+                            &mut triangles,
+                        );
+                    }
+                    continue;
+                }
+            };
+            labels.push((label, label_style, *rect));
+        }
+
+        // 文字标签居中，装饰线由几何图形绘制
+        let font_size = self.ui(TEXT_PROPERTY_LABEL_SIZE);
+        let mut buffers = Vec::new();
+        for (label, label_style, (x, y, w, h)) in labels {
+            let (label_width, label_height) =
+                self.text_renderer.measure(&label, font_size, &label_style);
+            let left = x + ((w - label_width) / 2.0).max(self.ui(TEXT_PROPERTY_MARGIN));
+            let top = y + (h - label_height) / 2.0;
+            let baseline = top + label_height * 0.75;
+            let [r, g, b, a] = TEXT_PROPERTY_LABEL_COLOR;
+            if label_style.underline {
+                let rect = (left, baseline + line, label_width, line);
+                self.push_rect_triangles(rect, [r, g, b, a], &mut triangles);
+            }
+            if label_style.strikethrough {
+                let rect = (left, top + label_height * 0.5, label_width, line);
+                self.push_rect_triangles(rect, [r, g, b, a], &mut triangles);
+            }
+            let buffer = self
+                .text_renderer
+                .create_buffer(&label, font_size, &label_style);
+            buffers.push((buffer, label_style, (left, top), (x, y, w, h)));
+        }
+
+        self.draw_drawing_vertices(render_pass, &[], &triangles);
+
+        let [r, g, b, _] = TEXT_PROPERTY_LABEL_COLOR;
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let text_areas =
+            buffers
+                .iter()
+                .flat_map(|(buffer, label_style, (left, top), (x, y, w, h))| {
+                    let area = glyphon::TextArea {
+                        buffer,
+                        left: *left,
+                        top: *top,
+                        scale: 1.0,
+                        bounds: glyphon::TextBounds {
+                            left: *x as i32,
+                            top: *y as i32,
+                            right: (x + w - margin) as i32,
+                            bottom: (y + h) as i32,
+                        },
+                        default_color: Self::to_glyphon_color([r, g, b]),
+                        custom_glyphs: &[],
+                    };
+                    Self::outlined_text_areas(area, [r, g, b], label_style, font_size)
+                });
+        if let Err(e) = self
+            .text_renderer
+            .prepare_ui(&self.device, &self.queue, text_areas)
+        {
+            eprintln!("Failed to prepare text properties labels: {:?}", e);
+        } else if let Err(e) = self.text_renderer.render_ui(render_pass) {
+            eprintln!("Failed to render text properties labels: {:?}", e);
+        }
+    }
+
     fn set_current_tool(&mut self, tool: Tool) {
+        // 🚀 切换工具时移除未保留的测量线
+        if tool != Tool::Measure && self.measurement.take().is_some() {
+            self.needs_redraw = true;
+        }
         self.current_tool = tool;
     }
 
+    // 🚀 设置当前颜色，有选中元素时同时修改该元素的颜色
+    fn set_current_color(&mut self, new_color: [f32; 3]) {
+        self.current_color = new_color;
+        self.modify_selected_element("Recolor", |element| {
+            let color = match element {
+                DrawingElement::Rectangle { color, .. }
+                | DrawingElement::Circle { color, .. }
+                | DrawingElement::Arrow { color, .. }
+                | DrawingElement::Measure { color, .. }
+                | DrawingElement::Pen { color, .. }
+                | DrawingElement::Text { color, .. } => color,
+                // 印章和图像图层使用图像本身的颜色
+                DrawingElement::Stamp { .. } | DrawingElement::Image { .. } => return false,
+            };
+            let changed = *color != new_color;
+            *color = new_color;
+            changed
+        });
+        println!("🎨 当前颜色: {:?}", new_color);
+    }
+
+    // 🚀 调整当前线宽，有选中元素时同时修改该元素的线宽（文本没有线宽）
+    fn adjust_current_thickness(&mut self, delta: f32) {
+        let new_thickness = (self.current_thickness + delta).clamp(MIN_THICKNESS, MAX_THICKNESS);
+        self.current_thickness = new_thickness;
+        self.modify_selected_element("Change width of", |element| match element {
+            DrawingElement::Rectangle { thickness, .. }
+            | DrawingElement::Circle { thickness, .. }
+            | DrawingElement::Arrow { thickness, .. }
+            | DrawingElement::Measure { thickness, .. } => {
+                let new_value = (*thickness + delta).clamp(MIN_THICKNESS, MAX_THICKNESS);
+                let changed = *thickness != new_value;
+                *thickness = new_value;
+                changed
+            }
+            DrawingElement::Pen {
+                thickness, widths, ..
+            } => {
+                let new_value = (*thickness + delta).clamp(MIN_THICKNESS, MAX_THICKNESS);
+                let changed = *thickness != new_value;
+                // 🚀 压感笔画按比例缩放每个点的宽度，保留压力变化
+                let ratio = new_value / *thickness;
+                for width in widths.iter_mut() {
+                    *width *= ratio;
+                }
+                *thickness = new_value;
+                changed
+            }
+            DrawingElement::Text { .. }
+            | DrawingElement::Stamp { .. }
+            | DrawingElement::Image { .. } => false,
+        });
+        println!("🖊 当前线宽: {}", new_thickness);
+    }
+
+    // 🚀 修改选中的元素：modify 返回 true 表示确实有改动，此时才记录撤销并刷新缓存和手柄
+    // action 是历史面板中的操作名，后面接元素名称（如 "Recolor arrow"）
+    fn modify_selected_element(
+        &mut self,
+        action: &str,
+        modify: impl FnOnce(&mut DrawingElement) -> bool,
+    ) {
+        let Some(index) = self.selected_element.as_ref().map(|s| s.index) else {
+            return;
+        };
+        if index >= self.drawing_elements.len() {
+            return;
+        }
+
+        let mut element = self.drawing_elements[index].clone();
+        if !modify(&mut element) {
+            return;
+        }
+
+        let old_element = std::mem::replace(&mut self.drawing_elements[index], element.clone());
+        self.invalidate_element_cache(&old_element);
+        self.invalidate_element_cache(&element);
+        self.record_edit(
+            &format!("{} {}", action, element.name()),
+            Edit::Modify {
+                index,
+                before: old_element,
+                after: element.clone(),
+            },
+        );
+
+        let new_handles = self.generate_handles_for_element(&element, index);
+        if let Some(ref mut selected) = self.selected_element {
+            selected.handles = new_handles;
+        }
+        self.needs_redraw = true;
+        self.render_cache_valid = false;
+    }
+
+    // 🚀 调整选中元素的层级：置于顶层（列表末尾）或底层（列表开头）
+    fn reorder_selected_element(&mut self, to_front: bool) {
+        let Some(from) = self.selected_element.as_ref().map(|s| s.index) else {
+            return;
+        };
+        if from >= self.drawing_elements.len() {
+            return;
+        }
+        let to = if to_front {
+            self.drawing_elements.len() - 1
+        } else {
+            0
+        };
+        if from == to {
+            return;
+        }
+
+        let element = self.drawing_elements.remove(from);
+        let label = if to_front {
+            format!("Bring {} to front", element.name())
+        } else {
+            format!("Send {} to back", element.name())
+        };
+        self.drawing_elements.insert(to, element);
+        self.record_edit(&label, Edit::Reorder { from, to });
+        self.select_element(to);
+        self.needs_redraw = true;
+        self.render_cache_valid = false;
+        println!("🚀 元素层级: {} -> {}", from, to);
+    }
+
     // 🚀 根据绘图元素类型更新当前工具状态
     fn update_tool_from_element(&mut self, element: &DrawingElement) {
         let tool = match element {
             DrawingElement::Rectangle { .. } => Tool::Rectangle,
             DrawingElement::Circle { .. } => Tool::Circle,
             DrawingElement::Arrow { .. } => Tool::Arrow,
+            DrawingElement::Measure { .. } => Tool::Measure,
             DrawingElement::Pen { .. } => Tool::Pen,
             DrawingElement::Text { .. } => Tool::Text,
+            DrawingElement::Stamp { .. } => Tool::Stamp,
+            // 🚀 图像图层没有对应的工具，保持当前工具
+            DrawingElement::Image { .. } => return,
         };
 
         // 更新当前工具
-        self.current_tool = tool;
+        self.set_current_tool(tool);
 
         // 更新工具栏按钮选择状态
         for button in &mut self.toolbar_buttons {
@@ -2768,7 +4985,7 @@ impl State {
         match tool {
             Tool::None => {
                 // 🚀 无工具选择：取消所有选择，进入空闲状态
-                self.current_tool = Tool::None;
+                self.set_current_tool(Tool::None);
                 self.deselect_element();
                 self.drawing_state = DrawingState::Idle;
                 false
@@ -2778,18 +4995,70 @@ impl State {
                 self.update_uniforms();
                 false
             }
-            Tool::Undo => {
-                // 🚀 只有在有撤销历史时才执行撤销
-                if !self.undo_stack.is_empty() {
-                    self.undo();
-                } else {
+            Tool::Stamp => {
+                // 🚀 允许输入法输入，提交的表情符号会加入印章面板
+                self.window.set_ime_allowed(true);
+                self.set_current_tool(tool);
+                self.update_uniforms();
+                false
+            }
+            Tool::Eraser => {
+                // 🚀 再次点击橡皮擦按钮切换擦除模式
+                if self.current_tool == Tool::Eraser {
+                    self.toggle_eraser_mode();
+                }
+                self.deselect_element();
+                self.set_current_tool(tool);
+                self.update_uniforms();
+                false
+            }
+            Tool::ColorPicker => {
+                self.deselect_element();
+                self.set_current_tool(tool);
+                self.update_uniforms();
+                false
+            }
+            Tool::Measure => {
+                // 🚀 再次点击测量按钮切换是否把测量线保留为标注
+                if self.current_tool == Tool::Measure {
+                    self.measure_keep = !self.measure_keep;
+                    println!("📏 测量线保留为标注: {}", self.measure_keep);
+                }
+                self.set_current_tool(tool);
+                self.update_uniforms();
+                false
+            }
+            Tool::Undo => {
+                // 🚀 只有在有撤销历史时才执行撤销
+                if self.history.can_undo() {
+                    self.undo();
+                } else {
                     println!("⚠️ 没有可撤销的操作");
                 }
                 self.update_uniforms();
                 false
             }
+            Tool::Redo => {
+                if self.history.can_redo() {
+                    self.redo();
+                } else {
+                    println!("⚠️ 没有可重做的操作");
+                }
+                self.update_uniforms();
+                false
+            }
+            Tool::History => {
+                // 🚀 切换历史记录面板
+                self.show_history_panel = !self.show_history_panel;
+                self.needs_redraw = true;
+                self.update_uniforms();
+                false
+            }
             Tool::Save => {
-                println!("保存截图");
+                match self.export_selection() {
+                    Ok(path) => println!("💾 截图已保存: {}", path.display()),
+                    Err(e) => eprintln!("保存截图失败: {}", e),
+                }
                 self.update_uniforms();
                 false
             }
@@ -2803,6 +5072,7 @@ impl State {
     }
 
     fn load_screenshot_from_data(&mut self, rgba: Vec<u8>, width: u32, height: u32) {
+        self.capture_size = Some((width, height));
         let texture = self.device.create_texture(&wgpu::TextureDescriptor {
             size: wgpu::Extent3d {
                 width,
@@ -2837,6 +5107,9 @@ impl State {
                 depth_or_array_layers: 1,
             },
         );
+        let rgba = Arc::new(rgba);
+        self.capture_pixels = Some(rgba.clone());
+        self.start_region_detection(rgba, width, height);
 
         // 使用wgpu 26的现代纹理视图配置
         let view = texture.create_view(&wgpu::TextureViewDescriptor {
@@ -3028,62 +5301,80 @@ impl State {
             .map(|i| i as f32)
             .unwrap_or(-1.0);
 
-        // 🚀 获取撤销按钮状态
-        let undo_button_enabled = !self.undo_stack.is_empty();
-
-        let uniform_data = [
-            self.current_box_coords
-                .map_or(-1.0, |(min_x, _, _, _)| min_x),
-            self.current_box_coords
-                .map_or(-1.0, |(_, min_y, _, _)| min_y),
-            self.current_box_coords
-                .map_or(-1.0, |(_, _, max_x, _)| max_x),
-            self.current_box_coords
-                .map_or(-1.0, |(_, _, _, max_y)| max_y),
-            self.size.width as f32,
-            self.size.height as f32,
-            self.border_width,
-            self.handle_size,
-            self.handle_border_width,
-            if self.show_toolbar { 1.0 } else { 0.0 },
-            TOOLBAR_HEIGHT,
-            hovered_index,
-            if self.toolbar_active { 1.0 } else { 0.0 },
-            selected_index,
-            TOOLBAR_BUTTON_SIZE,
-            TOOLBAR_BUTTON_MARGIN,
-            self.border_color[0],
-            self.border_color[1],
-            self.border_color[2],
-            1.0,
-            self.handle_color[0],
-            self.handle_color[1],
-            self.handle_color[2],
-            1.0,
-            self.toolbar_buttons.len() as f32,
+        // 🚀 获取撤销按钮状态和位置
+        let undo_button_enabled = self.history.can_undo();
+        let undo_button_index = self
+            .toolbar_buttons
+            .iter()
+            .position(|btn| btn.tool == Tool::Undo)
+            .map(|i| i as f32)
+            .unwrap_or(-1.0);
+        let redo_button_enabled = self.history.can_redo();
+        let redo_button_index = self
+            .toolbar_buttons
+            .iter()
+            .position(|btn| btn.tool == Tool::Redo)
+            .map(|i| i as f32)
+            .unwrap_or(-1.0);
+
+        let box_coords = self.current_box_coords.unwrap_or((-1.0, -1.0, -1.0, -1.0));
+        let uniforms = Uniforms {
+            box_min: [box_coords.0, box_coords.1],
+            box_max: [box_coords.2, box_coords.3],
+            screen_size: [self.size.width as f32, self.size.height as f32],
+            border_width: self.border_width,
+            handle_size: self.handle_size,
+            handle_border_width: self.handle_border_width,
+            show_toolbar: if self.show_toolbar { 1.0 } else { 0.0 },
+            toolbar_height: self.ui(TOOLBAR_HEIGHT),
+            hovered_button: hovered_index,
+            toolbar_active: if self.toolbar_active { 1.0 } else { 0.0 },
+            selected_button: selected_index,
+            toolbar_button_size: self.ui(TOOLBAR_BUTTON_SIZE),
+            toolbar_button_margin: self.ui(TOOLBAR_BUTTON_MARGIN),
+            border_color: [
+                self.border_color[0],
+                self.border_color[1],
+                self.border_color[2],
+                1.0,
+            ],
+            handle_color: [
+                self.handle_color[0],
+                self.handle_color[1],
+                self.handle_color[2],
+                1.0,
+            ],
+            toolbar_button_count: self.toolbar_buttons.len() as f32,
             // 🚀 背景缓存控制参数
-            if self.background_cache_valid {
+            background_cache_valid: if self.background_cache_valid {
                 1.0
             } else {
                 0.0
             },
-            if self.force_background_update {
+            force_background_update: if self.force_background_update {
                 1.0
             } else {
                 0.0
             },
             // 🚀 绘图元素手柄参数
-            if self.selected_element.is_some() {
+            show_handles: if self.selected_element.is_some() {
                 1.0
             } else {
                 0.0
-            }, // 是否显示手柄
+            },
             // 🚀 撤销按钮状态
-            if undo_button_enabled { 1.0 } else { 0.0 },
-        ];
+            undo_button_enabled: if undo_button_enabled { 1.0 } else { 0.0 },
+            undo_button_index,
+            // 🚀 HiDPI缩放系数
+            ui_scale: self.scale_factor,
+            // 🚀 重做按钮状态
+            redo_button_enabled: if redo_button_enabled { 1.0 } else { 0.0 },
+            redo_button_index,
+            _padding: [0.0; 3],
+        };
 
         self.queue
-            .write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&uniform_data));
+            .write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&uniforms));
     }
 
     fn update_box(&mut self, min_x: f32, min_y: f32, max_x: f32, max_y: f32) {
@@ -3099,6 +5390,712 @@ impl State {
         self.invalidate_background_cache();
     }
 
+    // 🚀 当前选区，未选择时为 None（重置后的选区坐标为 -1）
+    fn capture_region(&self) -> Region {
+        self.current_box_coords
+            .filter(|(min_x, min_y, max_x, max_y)| max_x > min_x && max_y > min_y)
+    }
+
+    // 🚀 撤销/重做恢复选区：没有选区时隐藏工具栏，回到框选状态
+    fn set_capture_region(&mut self, region: Region) {
+        self.size_entry = None;
+        match region {
+            Some((min_x, min_y, max_x, max_y)) => {
+                self.update_box(min_x, min_y, max_x, max_y);
+                if !self.show_toolbar {
+                    self.show_toolbar();
+                }
+            }
+            None => {
+                self.hide_toolbar();
+                self.update_box(-1.0, -1.0, -1.0, -1.0);
+            }
+        }
+        self.warn_outside_annotations();
+    }
+
+    // 🚀 拖动或缩放选区：连续的选区变化在鼠标按下时打开的历史分组中合并为一步
+    fn drag_capture_box(
+        &mut self,
+        label: &str,
+        (min_x, min_y, max_x, max_y): (f32, f32, f32, f32),
+    ) {
+        let before = self.capture_region();
+        self.update_box(min_x, min_y, max_x, max_y);
+        let after = self.capture_region();
+        if before != after {
+            self.history.record(label, Edit::Region { before, after });
+        }
+    }
+
+    // 🚀 记录一次已经完成的选区变化（框选新区域、R 键重置）
+    fn record_region_change(&mut self, label: &str, before: Region) {
+        let after = self.capture_region();
+        if before != after {
+            self.record_edit(label, Edit::Region { before, after });
+            self.warn_outside_annotations();
+        }
+    }
+
+    // 🚀 超出选区的标注（包围盒不完全在选区内）
+    fn annotations_outside_region(&self) -> Vec<usize> {
+        let Some((min_x, min_y, max_x, max_y)) = self.capture_region() else {
+            return Vec::new();
+        };
+        self.drawing_elements
+            .iter()
+            .enumerate()
+            .filter(|(_, element)| {
+                Self::element_bounds(element).is_some_and(|(left, top, right, bottom)| {
+                    left < min_x || top < min_y || right > max_x || bottom > max_y
+                })
+            })
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    // 🚀 选区变化后提示超出选区的标注（warn 策略）
+    fn warn_outside_annotations(&self) {
+        if self.config.capture.outside_annotations != OutsideAnnotations::Warn {
+            return;
+        }
+        let outside = self.annotations_outside_region();
+        if !outside.is_empty() {
+            println!(
+                "⚠️ {} 个标注超出选区，导出时会被裁剪: {:?}",
+                outside.len(),
+                outside
+            );
+        }
+    }
+
+    // 🚀 选区在截图像素中的 (x, y, 宽, 高)，与导出时的裁剪方式一致
+    fn capture_pixel_rect(&self) -> Option<(u32, u32, u32, u32)> {
+        let (min_x, min_y, max_x, max_y) = self.capture_region()?;
+        let (left, top) = self.window_to_capture((min_x, min_y));
+        let (right, bottom) = self.window_to_capture((max_x, max_y));
+        let (x, y) = (left.round().max(0.0) as u32, top.round().max(0.0) as u32);
+        Some((
+            x,
+            y,
+            (right.round().max(0.0) as u32).saturating_sub(x),
+            (bottom.round().max(0.0) as u32).saturating_sub(y),
+        ))
+    }
+
+    // 🚀 锁定的宽高比换算到窗口坐标（截图与窗口的横纵缩放比例可能不同）
+    fn window_aspect_ratio(&self) -> Option<f32> {
+        let (scale_x, scale_y) = self.capture_scale();
+        self.aspect_ratio
+            .ratio()
+            .map(|ratio| ratio * scale_y / scale_x)
+    }
+
+    // 🚀 拖动创建或缩放选区时限制在屏幕内并应用宽高比锁定
+    fn constrain_capture_box(
+        &self,
+        rect: capture_size::Rect,
+        sides: capture_size::Sides,
+    ) -> capture_size::Rect {
+        capture_size::fit_on_screen(
+            rect,
+            sides,
+            self.window_aspect_ratio(),
+            (self.size.width as f32, self.size.height as f32),
+        )
+    }
+
+    // 🚀 A 键或尺寸标签中的按钮：切换宽高比锁定，自定义比例取当前选区的比例
+    fn cycle_aspect_ratio(&mut self) {
+        let current = self
+            .capture_pixel_rect()
+            .map_or((0.0, 0.0), |(_, _, width, height)| {
+                (width as f32, height as f32)
+            });
+        self.aspect_ratio = self.aspect_ratio.next(current);
+        if let Some(entry) = self.size_entry.as_mut() {
+            entry.apply_ratio(self.aspect_ratio.ratio());
+        }
+        self.needs_redraw = true;
+        println!("🚀 宽高比锁定: {}", self.aspect_ratio.label());
+    }
+
+    // 🚀 固定尺寸预设：按预设的截图像素设置选区大小，保持选区中心（没有选区时以鼠标为中心），
+    // 与移动选区一样限制在屏幕内，之后可以直接拖动选区
+    fn cycle_capture_preset(&mut self, forward: bool) {
+        let count = self.config.capture.presets.len();
+        if count == 0 {
+            println!("⚠️ 配置中没有选区尺寸预设");
+            return;
+        }
+        let index = match (self.capture_preset, forward) {
+            (Some(index), true) => (index + 1) % count,
+            (Some(index), false) => (index + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+        self.capture_preset = Some(index);
+        let preset = self.config.capture.presets[index].clone();
+
+        let (scale_x, scale_y) = self.capture_scale();
+        let (screen_width, screen_height) = (self.size.width as f32, self.size.height as f32);
+        let width = (preset.width as f32 / scale_x).min(screen_width);
+        let height = (preset.height as f32 / scale_y).min(screen_height);
+        let (center_x, center_y) = match self.capture_region() {
+            Some((min_x, min_y, max_x, max_y)) => ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0),
+            None => self
+                .mouse_position
+                .unwrap_or((screen_width / 2.0, screen_height / 2.0)),
+        };
+        let rect = capture_size::clamp_to_screen(
+            (
+                center_x - width / 2.0,
+                center_y - height / 2.0,
+                center_x + width / 2.0,
+                center_y + height / 2.0,
+            ),
+            (screen_width, screen_height),
+        );
+
+        let before = self.capture_region();
+        self.set_capture_region(Some(rect));
+        self.record_region_change(&format!("Preset {}", preset.label()), before);
+        println!("🚀 选区预设: {}", preset.label());
+    }
+
+    // 🚀 选区尺寸标签：放在选区左上角上方，放不下或与工具栏重叠时放到选区内
+    fn size_label_layout(&mut self) -> Option<SizeLabelLayout> {
+        let (min_x, min_y, _, _) = self.capture_region()?;
+        let (_, _, width, height) = self.capture_pixel_rect()?;
+        let cells: Vec<(SizeLabelCell, String)> = match &self.size_entry {
+            None => {
+                let mut text = format!("{} × {}", width, height);
+                if let Some(preset) = self
+                    .capture_preset
+                    .and_then(|index| self.config.capture.presets.get(index))
+                    .filter(|preset| (preset.width, preset.height) == (width, height))
+                    .and_then(|preset| preset.name.as_deref())
+                {
+                    text.push_str(&format!("  {}", preset));
+                }
+                if self.aspect_ratio != AspectRatio::Free {
+                    text.push_str(&format!("  {}", self.aspect_ratio.label()));
+                }
+                vec![(SizeLabelCell::Size, text)]
+            }
+            Some(entry) => capture_size::FIELDS
+                .iter()
+                .map(|&field| {
+                    let text = format!("{} {}", field.label(), entry.value(field));
+                    (SizeLabelCell::Field(field), text)
+                })
+                .chain(std::iter::once((
+                    SizeLabelCell::Ratio,
+                    self.aspect_ratio.label(),
+                )))
+                .collect(),
+        };
+
+        let font_size = self.ui(TEXT_PROPERTY_LABEL_SIZE);
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let label_height = self.ui(SIZE_LABEL_HEIGHT);
+        let gap = self.ui(SIZE_LABEL_GAP);
+        let style = TextStyle::default();
+        let widths: Vec<f32> = cells
+            .iter()
+            .map(|(_, text)| self.text_renderer.measure(text, font_size, &style).0 + margin * 2.0)
+            .collect();
+        let total_width = widths.iter().sum::<f32>() + margin * (cells.len() + 1) as f32;
+        let total_height = label_height + margin * 2.0;
+
+        let left = min_x.min(self.size.width as f32 - total_width).max(0.0);
+        let mut top = min_y - gap - total_height;
+        let overlaps_toolbar = self.show_toolbar
+            && self.toolbar_buttons.iter().any(|button| {
+                let (bx, by, bw, bh) = button.rect;
+                bx < left + total_width
+                    && bx + bw > left
+                    && by < top + total_height
+                    && by + bh > top
+            });
+        if top < 0.0 || overlaps_toolbar {
+            top = min_y + gap;
+        }
+
+        let mut cell_x = left + margin;
+        let cells = cells
+            .into_iter()
+            .zip(widths)
+            .map(|((cell, text), width)| {
+                let rect = (cell_x, top + margin, width, label_height);
+                cell_x += width + margin;
+                (cell, text, rect)
+            })
+            .collect();
+        Some(((left, top, total_width, total_height), cells))
+    }
+
+    fn size_label_at(&mut self, pos: (f32, f32)) -> Option<SizeLabelCell> {
+        let (_, cells) = self.size_label_layout()?;
+        cells
+            .into_iter()
+            .find(|(_, _, (x, y, w, h))| {
+                pos.0 >= *x && pos.0 <= x + w && pos.1 >= *y && pos.1 <= y + h
+            })
+            .map(|(cell, _, _)| cell)
+    }
+
+    // 🚀 点击尺寸标签：打开数值输入、切换字段或切换宽高比
+    fn click_size_label(&mut self, cell: SizeLabelCell) {
+        match cell {
+            SizeLabelCell::Size => {
+                self.size_entry = self.capture_pixel_rect().map(SizeEntry::new);
+            }
+            SizeLabelCell::Field(field) => {
+                if let Some(entry) = self.size_entry.as_mut() {
+                    entry.select(field);
+                }
+            }
+            SizeLabelCell::Ratio => self.cycle_aspect_ratio(),
+        }
+        self.needs_redraw = true;
+    }
+
+    // 🚀 数值输入的按键：数字、退格、Tab 切换字段、Enter 应用、Esc 取消
+    fn handle_size_entry_key(&mut self, event: &winit::event::KeyEvent) {
+        use winit::keyboard::{KeyCode, PhysicalKey};
+
+        let shift = self.modifiers.state().shift_key();
+        let ratio = self.aspect_ratio.ratio();
+        let Some(entry) = self.size_entry.as_mut() else {
+            return;
+        };
+        match event.physical_key {
+            PhysicalKey::Code(KeyCode::Enter | KeyCode::NumpadEnter) => self.apply_size_entry(),
+            PhysicalKey::Code(KeyCode::Escape) => self.size_entry = None,
+            PhysicalKey::Code(KeyCode::Tab) => entry.cycle(!shift),
+            PhysicalKey::Code(KeyCode::Backspace) => entry.backspace(ratio),
+            _ => {
+                for digit in event.text.as_deref().unwrap_or_default().chars() {
+                    entry.input(digit, ratio);
+                }
+            }
+        }
+        self.needs_redraw = true;
+    }
+
+    // 🚀 应用输入的位置和尺寸（截图像素），限制在屏幕内并记录为一个撤销步骤
+    fn apply_size_entry(&mut self) {
+        let Some(entry) = self.size_entry.take() else {
+            return;
+        };
+        let Some((x, y, width, height)) = entry.rect() else {
+            println!("⚠️ 选区尺寸无效，忽略输入");
+            return;
+        };
+        let (scale_x, scale_y) = self.capture_scale();
+        let (screen_width, screen_height) = (self.size.width as f32, self.size.height as f32);
+        let width = (width as f32 / scale_x).clamp(MIN_BOX_SIZE, screen_width);
+        let height = (height as f32 / scale_y).clamp(MIN_BOX_SIZE, screen_height);
+        let min_x = (x as f32 / scale_x).min(screen_width - width);
+        let min_y = (y as f32 / scale_y).min(screen_height - height);
+
+        let before = self.capture_region();
+        self.update_box(min_x, min_y, min_x + width, min_y + height);
+        self.record_region_change("Set selection size", before);
+    }
+
+    // 🚀 选区尺寸标签（数值输入时高亮当前字段）
+    fn add_size_label(&mut self, triangles: &mut Vec<f32>, labels: &mut Vec<OverlayLabel>) {
+        let Some((label_rect, cells)) = self.size_label_layout() else {
+            return;
+        };
+        let active = self
+            .size_entry
+            .as_ref()
+            .map(|entry| SizeLabelCell::Field(entry.field));
+
+        self.push_rect_triangles(label_rect, TEXT_PROPERTY_BAR_COLOR, triangles);
+        for (cell, text, rect) in cells {
+            if Some(cell) == active {
+                self.push_rect_triangles(rect, TEXT_PROPERTY_ACTIVE_COLOR, triangles);
+            } else if cell != SizeLabelCell::Size {
+                self.push_rect_triangles(rect, TEXT_PROPERTY_BUTTON_COLOR, triangles);
+            }
+            labels.push((text, rect));
+        }
+    }
+
+    // 🚀 取色器：鼠标下像素的颜色块和 hex / RGB / HSL 读数，显示在鼠标右下方（放不下时翻到另一侧）
+    fn add_color_readout(&mut self, triangles: &mut Vec<f32>, labels: &mut Vec<OverlayLabel>) {
+        let Some((pos, color)) = self.color_under_cursor() else {
+            return;
+        };
+        let text = color_picker::describe(color);
+        let font_size = self.ui(TEXT_PROPERTY_LABEL_SIZE);
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let (text_width, text_height) =
+            self.text_renderer
+                .measure(&text, font_size, &TextStyle::default());
+        let swatch_width = self.ui(COLOR_SWATCH_WIDTH);
+        let width = swatch_width + text_width + margin * 5.0;
+        let height = text_height + margin * 2.0;
+
+        let offset = self.ui(COLOR_READOUT_OFFSET);
+        let mut x = pos.0 + offset;
+        let mut y = pos.1 + offset;
+        if x + width > self.size.width as f32 {
+            x = pos.0 - offset - width;
+        }
+        if y + height > self.size.height as f32 {
+            y = pos.1 - offset - height;
+        }
+
+        self.push_rect_triangles((x, y, width, height), TEXT_PROPERTY_BAR_COLOR, triangles);
+        let border = self.ui(1.0);
+        let swatch = (x + margin, y + margin, swatch_width, text_height);
+        self.push_rect_triangles(swatch, TEXT_PROPERTY_LABEL_COLOR, triangles);
+        let [r, g, b] = color_picker::to_annotation_color(color);
+        self.push_rect_triangles(
+            (
+                swatch.0 + border,
+                swatch.1 + border,
+                swatch.2 - border * 2.0,
+                swatch.3 - border * 2.0,
+            ),
+            [r, g, b, 1.0],
+            triangles,
+        );
+        labels.push((
+            text,
+            (
+                x + swatch_width + margin * 2.0,
+                y + margin,
+                text_width + margin * 2.0,
+                text_height,
+            ),
+        ));
+    }
+
+    // 🚀 测量读数：正在绘制或未保留的测量线的长度（截图像素），显示在终点外侧
+    fn add_measure_readout(&mut self, triangles: &mut Vec<f32>, labels: &mut Vec<OverlayLabel>) {
+        let Some(DrawingElement::Measure { start, end, .. }) = self
+            .current_drawing
+            .as_ref()
+            .or(self.measurement.as_ref())
+            .cloned()
+        else {
+            return;
+        };
+        let text = measure::readout(start, end, self.capture_scale());
+        let font_size = self.ui(TEXT_PROPERTY_LABEL_SIZE);
+        let margin = self.ui(TEXT_PROPERTY_MARGIN);
+        let (text_width, text_height) =
+            self.text_renderer
+                .measure(&text, font_size, &TextStyle::default());
+        let width = text_width + margin * 4.0;
+        let height = text_height + margin * 2.0;
+
+        // 沿测量方向放在终点之外，放不下时翻到另一侧
+        let offset = self.ui(MEASURE_READOUT_OFFSET);
+        let mut x = if end.0 >= start.0 {
+            end.0 + offset
+        } else {
+            end.0 - offset - width
+        };
+        let mut y = if end.1 >= start.1 {
+            end.1 + offset
+        } else {
+            end.1 - offset - height
+        };
+        if x + width > self.size.width as f32 {
+            x = end.0 - offset - width;
+        }
+        if x < 0.0 {
+            x = end.0 + offset;
+        }
+        if y + height > self.size.height as f32 {
+            y = end.1 - offset - height;
+        }
+        if y < 0.0 {
+            y = end.1 + offset;
+        }
+
+        self.push_rect_triangles((x, y, width, height), TEXT_PROPERTY_BAR_COLOR, triangles);
+        labels.push((
+            text,
+            (
+                x + margin,
+                y + margin,
+                text_width + margin * 2.0,
+                text_height,
+            ),
+        ));
+    }
+
+    // 🚀 选区上的标签（尺寸标签、取色读数、测量读数）：背景一起绘制，文字用同一个渲染器一次准备
+    fn render_overlay_labels(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let mut triangles = Vec::new();
+        let mut labels = Vec::new();
+        self.add_size_label(&mut triangles, &mut labels);
+        self.add_color_readout(&mut triangles, &mut labels);
+        self.add_measure_readout(&mut triangles, &mut labels);
+        if triangles.is_empty() {
+            return;
+        }
+        self.draw_drawing_vertices(render_pass, &[], &triangles);
+
+        let font_size = self.ui(TEXT_PROPERTY_LABEL_SIZE);
+        let style = TextStyle::default();
+        let buffers: Vec<_> = labels
+            .iter()
+            .map(|(text, (x, y, w, h))| {
+                let (label_width, label_height) =
+                    self.text_renderer.measure(text, font_size, &style);
+                let buffer = self.text_renderer.create_buffer(text, font_size, &style);
+                let left = x + (w - label_width) / 2.0;
+                let top = y + (h - label_height) / 2.0;
+                (buffer, (left, top), (*x, *y, *w, *h))
+            })
+            .collect();
+
+        let [r, g, b, _] = TEXT_PROPERTY_LABEL_COLOR;
+        let text_areas =
+            buffers
+                .iter()
+                .map(|(buffer, (left, top), (x, y, w, h))| glyphon::TextArea {
+                    buffer,
+                    left: *left,
+                    top: *top,
+                    scale: 1.0,
+                    bounds: Self::text_bounds((*x, *y), (*w, *h)),
+                    default_color: Self::to_glyphon_color([r, g, b]),
+                    custom_glyphs: &[],
+                });
+        if let Err(e) = self
+            .text_renderer
+            .prepare_overlay(&self.device, &self.queue, text_areas)
+        {
+            eprintln!("Failed to prepare overlay labels: {:?}", e);
+        } else if let Err(e) = self.text_renderer.render_overlay(render_pass) {
+            eprintln!("Failed to render overlay labels: {:?}", e);
+        }
+    }
+
+    // 🚀 取色器：窗口坐标换算为截图坐标后，从原始截图像素中取色
+    fn sample_color(&self, pos: (f32, f32)) -> Option<[u8; 3]> {
+        let pixels = self.capture_pixels.as_ref()?;
+        let (width, height) = self.capture_size?;
+        color_picker::pixel_at(pixels, width, height, self.window_to_capture(pos))
+    }
+
+    fn color_under_cursor(&self) -> Option<((f32, f32), [u8; 3])> {
+        if !self.toolbar_active || self.current_tool != Tool::ColorPicker {
+            return None;
+        }
+        let pos = self.mouse_position?;
+        if !self.is_point_in_screenshot_area(pos.0, pos.1) {
+            return None;
+        }
+        Some((pos, self.sample_color(pos)?))
+    }
+
+    // 🚀 单击取色：hex 复制到剪贴板，并设为当前标注颜色（同时修改选中的元素）
+    fn pick_color(&mut self, pos: (f32, f32)) {
+        let Some(color) = self.sample_color(pos) else {
+            return;
+        };
+        let hex = color_picker::hex(color);
+        if let Some(clipboard) = self.clipboard() {
+            match clipboard.set_text(hex.clone()) {
+                Ok(()) => println!("🎨 已复制颜色: {}", hex),
+                Err(e) => eprintln!("复制到剪贴板失败: {}", e),
+            }
+        }
+        self.set_current_color(color_picker::to_annotation_color(color));
+    }
+
+    // 🚀 clip 策略：把标注裁剪到选区内绘制，与导出结果一致
+    fn set_annotation_scissor(&self, render_pass: &mut wgpu::RenderPass, clip: bool) {
+        let (width, height) = (self.size.width, self.size.height);
+        match self.capture_region() {
+            Some((min_x, min_y, max_x, max_y))
+                if clip && self.config.capture.outside_annotations == OutsideAnnotations::Clip =>
+            {
+                let x = (min_x.max(0.0) as u32).min(width);
+                let y = (min_y.max(0.0) as u32).min(height);
+                let right = (max_x.ceil().max(0.0) as u32).min(width);
+                let bottom = (max_y.ceil().max(0.0) as u32).min(height);
+                render_pass.set_scissor_rect(x, y, right - x, bottom - y);
+            }
+            _ => render_pass.set_scissor_rect(0, 0, width, height),
+        }
+    }
+
+    // 🚀 warn 策略：用警告色框出超出选区的标注
+    fn add_outside_warning_triangles(&self, triangles: &mut Vec<f32>) {
+        if self.config.capture.outside_annotations != OutsideAnnotations::Warn {
+            return;
+        }
+        let line = self.ui(1.5);
+        for index in self.annotations_outside_region() {
+            let Some((left, top, right, bottom)) =
+                Self::element_bounds(&self.drawing_elements[index])
+            else {
+                continue;
+            };
+            let (width, height) = (right - left, bottom - top);
+            for rect in [
+                (left, top, width, line),
+                (left, bottom - line, width, line),
+                (left, top, line, height),
+                (right - line, top, line, height),
+            ] {
+                self.push_rect_triangles(rect, OUTSIDE_WARNING_COLOR, triangles);
+            }
+        }
+    }
+
+    // 🚀 吸附：拖动中的矩形对齐到其他元素和选区（移动选区时为窗口）的边缘和中心以及网格，
+    // 返回修正量并更新参考线。moving 为正在移动的元素，None 表示移动的是选区；
+    // 按住 Alt 临时切换吸附开关
+    fn snap_moving_rect(&mut self, rect: snap::Rect, moving: Option<usize>) -> (f32, f32) {
+        let config = &self.config.snap;
+        if config.enabled == self.modifiers.state().alt_key() {
+            self.snap_guides.clear();
+            return (0.0, 0.0);
+        }
+
+        let mut targets = snap::Targets::default();
+        for (index, element) in self.drawing_elements.iter().enumerate() {
+            if Some(index) != moving
+                && let Some(bounds) = Self::element_bounds(element)
+            {
+                targets.add_rect(bounds);
+            }
+        }
+        let frame = match moving {
+            Some(_) => self.capture_region(),
+            None => Some((0.0, 0.0, self.size.width as f32, self.size.height as f32)),
+        };
+        if let Some(frame) = frame {
+            targets.add_rect(frame);
+        }
+        // 元素的网格从选区左上角开始，选区的网格从窗口左上角开始
+        let grid = (config.grid > 0.0).then(|| snap::Grid {
+            size: self.ui(config.grid),
+            origin: match (moving, self.capture_region()) {
+                (Some(_), Some((min_x, min_y, _, _))) => (min_x, min_y),
+                _ => (0.0, 0.0),
+            },
+        });
+
+        let (offset, guides) = snap::snap_rect(rect, &targets, grid, self.ui(config.threshold));
+        self.snap_guides = guides;
+        offset
+    }
+
+    // 🚀 在后台线程中检测截图里的窗口和界面元素，不阻塞窗口显示
+    fn start_region_detection(&mut self, rgba: Arc<Vec<u8>>, width: u32, height: u32) {
+        let config = &self.config.detection;
+        if !config.enabled {
+            return;
+        }
+        let options = detect::Options {
+            tolerance: config.tolerance,
+            min_size: config.min_size,
+            ..detect::Options::default()
+        };
+        // 🚀 X11 上另外加入其他窗口的位置（跳过截图窗口自身）
+        let origin = self.capture_origin;
+        let own_window = self.x11_window_id();
+        self.detected_regions.clear();
+        self.region_detection = Some(std::thread::spawn(move || {
+            let mut regions = detect::detect_regions(&rgba, width, height, &options);
+            let windows = window_geometry::window_regions(origin, (width, height), own_window);
+            detect::merge_regions(&mut regions, windows);
+            regions
+        }));
+    }
+
+    fn x11_window_id(&self) -> Option<u32> {
+        use winit::raw_window_handle::{HasWindowHandle, RawWindowHandle};
+        match self.window.window_handle().ok()?.as_raw() {
+            RawWindowHandle::Xlib(handle) => u32::try_from(handle.window).ok(),
+            RawWindowHandle::Xcb(handle) => Some(handle.window.get()),
+            _ => None,
+        }
+    }
+
+    // 🚀 框选前高亮鼠标下检测到的最小区域，没有时高亮整个屏幕；返回高亮是否变化
+    fn update_hovered_region(&mut self, pos: (f32, f32)) -> bool {
+        if self
+            .region_detection
+            .as_ref()
+            .is_some_and(|detection| detection.is_finished())
+            && let Some(detection) = self.region_detection.take()
+        {
+            self.detected_regions = detection.join().unwrap_or_default();
+            println!("🚀 检测到 {} 个候选区域", self.detected_regions.len());
+        }
+
+        let hovered = if !self.config.detection.enabled || self.capture_region().is_some() {
+            None
+        } else {
+            let (scale_x, scale_y) = self.capture_scale();
+            Some(
+                match detect::region_at(&self.detected_regions, self.window_to_capture(pos)) {
+                    Some((left, top, right, bottom)) => (
+                        left as f32 / scale_x,
+                        top as f32 / scale_y,
+                        right as f32 / scale_x,
+                        bottom as f32 / scale_y,
+                    ),
+                    None => (0.0, 0.0, self.size.width as f32, self.size.height as f32),
+                },
+            )
+        };
+        let changed = hovered != self.hovered_region;
+        self.hovered_region = hovered;
+        changed
+    }
+
+    fn add_hovered_region_triangles(&self, triangles: &mut Vec<f32>) {
+        let Some((left, top, right, bottom)) = self.hovered_region else {
+            return;
+        };
+        if self.capture_region().is_some() {
+            return;
+        }
+        let line = self.ui(2.0);
+        let (width, height) = (right - left, bottom - top);
+        self.push_rect_triangles(
+            (left, top, width, height),
+            DETECTED_REGION_FILL_COLOR,
+            triangles,
+        );
+        for rect in [
+            (left, top, width, line),
+            (left, bottom - line, width, line),
+            (left, top, line, height),
+            (right - line, top, line, height),
+        ] {
+            self.push_rect_triangles(rect, DETECTED_REGION_BORDER_COLOR, triangles);
+        }
+    }
+
+    fn add_snap_guide_triangles(&self, triangles: &mut Vec<f32>) {
+        let line = self.ui(1.0);
+        let (width, height) = (self.size.width as f32, self.size.height as f32);
+        for guide in &self.snap_guides {
+            let rect = match *guide {
+                snap::Guide::Vertical(x) => (x - line / 2.0, 0.0, line, height),
+                snap::Guide::Horizontal(y) => (0.0, y - line / 2.0, width, line),
+            };
+            self.push_rect_triangles(rect, SNAP_GUIDE_COLOR, triangles);
+        }
+    }
+
     // 🚀 智能背景缓存渲染系统
     fn render(&mut self) {
         if self.size.width == 0 || self.size.height == 0 {
@@ -3115,6 +6112,10 @@ impl State {
             self.render_background_to_cache();
         }
 
+        // 🚀 旋转文本和印章需要在主渲染通道之前光栅化到离屏纹理
+        self.prepare_rotated_text_textures();
+        self.prepare_stamp_textures();
+
         let output = self.surface.get_current_texture().unwrap();
         let view = output
             .texture
@@ -3152,10 +6153,21 @@ impl State {
                 render_pass.draw(0..6, 0..1);
             }
 
+            // 🚀 图像图层在其他标注下面（clip 策略下标注裁剪到选区内）
+            self.set_annotation_scissor(&mut render_pass, true);
+            self.render_image_layers(&mut render_pass);
+
             // 渲染绘图元素
             self.render_drawings_batched(&mut render_pass);
 
-            // 🚀 渲染选中元素的手柄
+            // 🚀 渲染印章
+            self.render_stamps(&mut render_pass);
+
+            // 🚀 渲染旋转文本
+            self.render_rotated_text(&mut render_pass);
+            self.set_annotation_scissor(&mut render_pass, false);
+
+            // 🚀 渲染选中元素的手柄
             if self.selected_element.is_some() {
                 self.render_element_handles(&mut render_pass);
             }
@@ -3171,11 +6183,25 @@ impl State {
 
             // 🚀 在主渲染通道中渲染文本 - 检查状态后调用合适的函数
             let is_editing = self.text_input_active;
+            self.set_annotation_scissor(&mut render_pass, true);
             if is_editing {
                 self.render_all_text_with_editing(&mut render_pass);
             } else {
                 self.render_completed_text(&mut render_pass);
             }
+            self.set_annotation_scissor(&mut render_pass, false);
+
+            // 🚀 文本属性栏和印章面板绘制在标注文本之上
+            if self.text_properties_visible() {
+                self.render_text_properties_bar(&mut render_pass);
+            }
+            if self.stamp_palette_visible() {
+                self.render_stamp_palette(&mut render_pass);
+            }
+            if self.history_panel_visible() {
+                self.render_history_panel(&mut render_pass);
+            }
+            self.render_overlay_labels(&mut render_pass);
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
@@ -3199,20 +6225,41 @@ impl State {
         self.render_cache_valid = false;
     }
 
-    // 🚀 撤销系统：保存当前状态到撤销栈
-    fn save_state_for_undo(&mut self) {
-        // 限制撤销栈大小，避免内存过度使用
-        const MAX_UNDO_STEPS: usize = 50;
+    // 🚀 撤销系统：记录一次已经执行的操作
+    fn record_edit(&mut self, label: &str, edit: Edit<DrawingElement>) {
+        self.history.record(label, edit);
+        self.history_changed();
+    }
+
+    // 🚀 结束一次拖动或擦除手势，期间记录的操作合并为一个撤销步骤
+    fn end_history_group(&mut self) {
+        self.history.end_group();
+        self.history_changed();
+    }
+
+    fn history_changed(&mut self) {
+        self.release_unused_image_layers();
+        self.update_undo_redo_button_states();
+    }
 
-        if self.undo_stack.len() >= MAX_UNDO_STEPS {
-            self.undo_stack.remove(0); // 移除最旧的状态
+    // 🚀 撤销/重做后只失效受影响元素的缓存
+    fn after_history_step(&mut self, affected: Vec<DrawingElement>, region: Region) {
+        for element in &affected {
+            self.invalidate_element_cache(element);
+        }
+        if region != self.capture_region() {
+            self.set_capture_region(region);
         }
 
-        // 保存当前绘图元素状态
-        self.undo_stack.push(self.drawing_elements.clone());
+        // 取消当前选择
+        self.deselect_element();
+
+        // 标记需要重绘
+        self.needs_redraw = true;
+        self.render_cache_valid = false;
 
-        // 清空重做栈（新操作后不能重做之前的撤销）
-        self.redo_stack.clear();
+        // 🚀 强制请求重绘
+        self.window.request_redraw();
 
         // 🚀 更新工具栏按钮状态
         self.update_undo_redo_button_states();
@@ -3220,71 +6267,32 @@ impl State {
 
     // 🚀 撤销操作 (Ctrl+Z)
     fn undo(&mut self) {
-        if let Some(previous_state) = self.undo_stack.pop() {
-            // 将当前状态保存到重做栈
-            self.redo_stack.push(self.drawing_elements.clone());
-
-            // 恢复到之前的状态
-            self.drawing_elements = previous_state;
-
-            // 取消当前选择
-            self.deselect_element();
-
-            // 标记需要重绘
-            self.needs_redraw = true;
-            self.render_cache_valid = false;
-
-            // 🚀 清除所有缓存
-            self.cached_drawing_vertices.clear();
-            self.drawing_cache_valid.clear();
-
-            // 🚀 强制请求重绘
-            self.window.request_redraw();
-
-            // 🚀 更新工具栏按钮状态
-            self.update_undo_redo_button_states();
-
-            println!("🔄 撤销操作，剩余撤销步数: {}", self.undo_stack.len());
-        } else {
+        let mut region = self.capture_region();
+        let Some(edits) = self.history.undo(&mut self.drawing_elements, &mut region) else {
             println!("⚠️ 没有可撤销的操作");
-        }
+            return;
+        };
+        let affected = edits.iter().flat_map(Edit::elements).cloned().collect();
+        self.after_history_step(affected, region);
+        println!("🔄 撤销操作，剩余撤销步数: {}", self.history.undo_len());
     }
 
     // 🚀 重做操作 (Ctrl+Y 或 Ctrl+Shift+Z)
     fn redo(&mut self) {
-        if let Some(next_state) = self.redo_stack.pop() {
-            // 将当前状态保存到撤销栈
-            self.undo_stack.push(self.drawing_elements.clone());
-
-            // 恢复到重做状态
-            self.drawing_elements = next_state;
-
-            // 取消当前选择
-            self.deselect_element();
-
-            // 标记需要重绘
-            self.needs_redraw = true;
-            self.render_cache_valid = false;
-
-            // 🚀 清除所有缓存
-            self.cached_drawing_vertices.clear();
-            self.drawing_cache_valid.clear();
-
-            // 🚀 强制请求重绘
-            self.window.request_redraw();
-
-            // 🚀 更新工具栏按钮状态
-            self.update_undo_redo_button_states();
-
-            println!("🔄 重做操作，剩余重做步数: {}", self.redo_stack.len());
-        } else {
+        let mut region = self.capture_region();
+        let Some(edits) = self.history.redo(&mut self.drawing_elements, &mut region) else {
             println!("⚠️ 没有可重做的操作");
-        }
+            return;
+        };
+        let affected = edits.iter().flat_map(Edit::elements).cloned().collect();
+        self.after_history_step(affected, region);
+        println!("🔄 重做操作，剩余重做步数: {}", self.history.redo_len());
     }
 
     // 🚀 更新撤销/重做按钮的启用/禁用状态
     fn update_undo_redo_button_states(&mut self) {
-        let has_undo_history = !self.undo_stack.is_empty();
+        let has_undo_history = self.history.can_undo();
+        let has_redo_history = self.history.can_redo();
 
         // 🚀 更新每个按钮的状态
         for (index, button) in self.toolbar_buttons.iter_mut().enumerate() {
@@ -3297,6 +6305,10 @@ impl State {
                         index, has_undo_history, button.is_selected
                     );
                 }
+                Tool::Redo => {
+                    // 🚀 重做按钮：根据重做历史设置状态
+                    button.is_selected = has_redo_history;
+                }
                 _ => {
                     // 🚀 其他按钮：根据当前工具设置状态
                     button.is_selected = button.tool == self.current_tool;
@@ -3314,112 +6326,189 @@ impl State {
         self.update_uniforms();
     }
 
-    // 🚀 处理文本输入
+    // 🚀 处理文本输入（光标移动与删除都以字素簇为单位，见 text_editor.rs）
     fn handle_text_input(&mut self, event: &winit::event::KeyEvent) {
         use winit::keyboard::{KeyCode, PhysicalKey};
 
         println!("🚀 处理文本输入: {:?}", event.physical_key);
 
-        match event.physical_key {
-            PhysicalKey::Code(KeyCode::Enter) => {
-                // 🚀 修复：Ctrl+Enter完成输入，单独Enter添加换行
-                if self.modifiers.state().control_key() {
-                    // Ctrl+Enter：完成文本输入
+        let ctrl = self.modifiers.state().control_key();
+        let shift = self.modifiers.state().shift_key();
+        let PhysicalKey::Code(code) = event.physical_key else {
+            return;
+        };
+
+        // 🚀 光标移动：Ctrl 按单词/全文移动，Shift 扩展选区
+        let movement: Option<fn(&mut TextEditor) -> bool> = match code {
+            KeyCode::ArrowLeft if ctrl => Some(TextEditor::move_word_left),
+            KeyCode::ArrowRight if ctrl => Some(TextEditor::move_word_right),
+            KeyCode::ArrowLeft => Some(TextEditor::move_left),
+            KeyCode::ArrowRight => Some(TextEditor::move_right),
+            KeyCode::ArrowUp => Some(TextEditor::move_up),
+            KeyCode::ArrowDown => Some(TextEditor::move_down),
+            // Home/End 移到行首/行尾，配合 Ctrl 移到全文首/尾
+            KeyCode::Home if ctrl => Some(TextEditor::move_text_start),
+            KeyCode::End if ctrl => Some(TextEditor::move_text_end),
+            KeyCode::Home => Some(TextEditor::move_home),
+            KeyCode::End => Some(TextEditor::move_end),
+            _ => None,
+        };
+
+        let handled = if let Some(movement) = movement {
+            self.text_editor.move_with(shift, movement)
+        } else {
+            match code {
+                KeyCode::Enter | KeyCode::NumpadEnter if ctrl => {
+                    // 🚀 Ctrl+Enter：完成文本输入
                     self.finish_text_input();
                     println!("🚀 Ctrl+Enter：完成文本输入");
-                } else {
+                    return;
+                }
+                KeyCode::Escape => {
+                    // ESC键：取消文本输入
+                    self.cancel_text_input();
+                    return;
+                }
+                KeyCode::Enter | KeyCode::NumpadEnter => {
                     // 单独Enter：添加换行
-                    println!(
-                        "🚀 Enter前: 文本='{:?}', 光标位置={}, 文本长度={}",
-                        self.current_text_input,
-                        self.text_cursor_position,
-                        self.current_text_input.len()
-                    );
-
-                    self.current_text_input
-                        .insert(self.text_cursor_position, '\n');
-                    self.text_cursor_position += 1;
-
-                    println!(
-                        "🚀 Enter后: 文本='{:?}', 光标位置={}, 文本长度={}",
-                        self.current_text_input,
-                        self.text_cursor_position,
-                        self.current_text_input.len()
-                    );
-
-                    self.update_current_text_element();
-                    println!("🚀 Enter：添加换行符");
+                    self.text_editor.insert_str("\n");
+                    true
                 }
-            }
-            PhysicalKey::Code(KeyCode::Escape) => {
-                // ESC键：取消文本输入
-                self.cancel_text_input();
-            }
-            PhysicalKey::Code(KeyCode::Backspace) => {
-                // 退格键：删除字符
-                if !self.current_text_input.is_empty() && self.text_cursor_position > 0 {
-                    self.text_cursor_position -= 1;
-                    self.current_text_input.remove(self.text_cursor_position);
-                    self.update_current_text_element();
+                KeyCode::Backspace => self.text_editor.backspace(),
+                KeyCode::Delete => self.text_editor.delete(),
+                // 🚀 全选与剪贴板
+                KeyCode::KeyA if ctrl => {
+                    self.text_editor.select_all();
+                    true
                 }
-            }
-            PhysicalKey::Code(KeyCode::ArrowLeft) => {
-                // 左箭头：移动光标
-                if self.text_cursor_position > 0 {
-                    self.text_cursor_position -= 1;
-                    println!(
-                        "🚀 光标左移到位置: {} (文本长度: {})",
-                        self.text_cursor_position,
-                        self.current_text_input.len()
-                    );
-                    // 🚀 修复：光标移动后触发重绘和文本更新
-                    self.update_current_text_element(); // 强制更新文本元素
-                    self.needs_redraw = true;
-                    self.window.request_redraw();
-                    println!("🚀 触发重绘请求和文本更新");
-                } else {
-                    println!("🚀 光标已在最左边，无法继续左移");
+                KeyCode::KeyC if ctrl => {
+                    self.copy_selected_text();
+                    false
+                }
+                KeyCode::KeyX if ctrl => {
+                    self.copy_selected_text() && self.text_editor.delete_selection()
+                }
+                KeyCode::KeyV if ctrl => self.paste_text(),
+                _ => {
+                    // 其他键：不再使用key_to_char，而是依赖IME事件处理真实的文本输入
+                    println!("🚀 其他按键（将通过IME处理）: {:?}", event.physical_key);
+                    false
                 }
             }
-            PhysicalKey::Code(KeyCode::ArrowRight) => {
-                // 右箭头：移动光标
-                if self.text_cursor_position < self.current_text_input.len() {
-                    self.text_cursor_position += 1;
-                    println!("🚀 光标右移到位置: {}", self.text_cursor_position);
-                    // 🚀 修复：光标移动后触发重绘和文本更新
-                    self.update_current_text_element(); // 强制更新文本元素
-                    self.needs_redraw = true;
-                    self.window.request_redraw();
-                    println!("🚀 触发重绘请求和文本更新");
-                } else {
-                    println!("🚀 光标已在最右边，无法继续右移");
+        };
+
+        if handled {
+            println!(
+                "🚀 文本编辑: 文本='{:?}', 光标位置={}, 选区={:?}",
+                self.text_editor.text(),
+                self.text_editor.cursor(),
+                self.text_editor.selection()
+            );
+            // 🚀 光标移动也需要更新文本元素（显示内容包含光标）
+            self.update_current_text_element();
+        }
+
+        self.window.request_redraw();
+    }
+
+    // 🚀 系统剪贴板：首次使用时创建并一直保留（Linux 上剪贴板内容由该对象提供）
+    fn clipboard(&mut self) -> Option<&mut arboard::Clipboard> {
+        if self.clipboard.is_none() {
+            match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard = Some(clipboard),
+                Err(e) => {
+                    eprintln!("无法访问系统剪贴板: {}", e);
+                    return None;
                 }
             }
-            PhysicalKey::Code(KeyCode::ArrowUp) => {
-                // 🚀 新增：上箭头移动到上一行
-                self.move_cursor_up();
-                // 🚀 修复：光标移动后触发重绘和文本更新
-                self.update_current_text_element(); // 强制更新文本元素
-                self.needs_redraw = true;
-                self.window.request_redraw();
-                println!("🚀 上箭头：触发重绘请求和文本更新");
-            }
-            PhysicalKey::Code(KeyCode::ArrowDown) => {
-                // 🚀 新增：下箭头移动到下一行
-                self.move_cursor_down();
-                // 🚀 修复：光标移动后触发重绘和文本更新
-                self.update_current_text_element(); // 强制更新文本元素
-                self.needs_redraw = true;
-                self.window.request_redraw();
-                println!("🚀 下箭头：触发重绘请求和文本更新");
+        }
+        self.clipboard.as_mut()
+    }
+
+    // 🚀 复制选中的文本，没有选区或剪贴板不可用时返回 false
+    fn copy_selected_text(&mut self) -> bool {
+        let Some(text) = self.text_editor.selected_text().map(str::to_string) else {
+            return false;
+        };
+        let Some(clipboard) = self.clipboard() else {
+            return false;
+        };
+        match clipboard.set_text(text) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("复制到剪贴板失败: {}", e);
+                false
             }
-            _ => {
-                // 其他键：不再使用key_to_char，而是依赖IME事件处理真实的文本输入
-                println!("🚀 其他按键（将通过IME处理）: {:?}", event.physical_key);
+        }
+    }
+
+    // 🚀 粘贴剪贴板中的文本（替换选区），统一换行符
+    fn paste_text(&mut self) -> bool {
+        let Some(clipboard) = self.clipboard() else {
+            return false;
+        };
+        let text = match clipboard.get_text() {
+            Ok(text) => text.replace("\r\n", "\n").replace('\r', "\n"),
+            Err(e) => {
+                eprintln!("读取剪贴板失败: {}", e);
+                return false;
             }
+        };
+        if text.is_empty() {
+            return false;
         }
+        self.text_editor.insert_str(&text);
+        true
+    }
 
-        self.window.request_redraw();
+    // 🚀 鼠标位置对应的编辑文本偏移，以及该位置是否在文本框内
+    fn editing_text_offset_at(&mut self, pos: (f32, f32)) -> Option<(usize, bool)> {
+        let Some(
+            ref element @ DrawingElement::Text {
+                position,
+                font_size,
+                is_editing: true,
+                ref style,
+                ..
+            },
+        ) = self.current_drawing.clone()
+        else {
+            return None;
+        };
+        // 转换到文本的局部（未旋转）坐标系
+        let (center, rotation, _) = Self::shape_rotation_frame(element)?;
+        let local = Self::rotate_point(pos, center, -rotation);
+        let (x, y) = (local.0 - position.0, local.1 - position.1);
+
+        let display = self.text_editor.display(CURSOR_CHAR);
+        let (width, height) = self.text_renderer.measure(&display.text, font_size, style);
+        let inside = (-TEXT_BOX_PADDING..=width + TEXT_BOX_PADDING).contains(&x)
+            && (-TEXT_BOX_PADDING..=height + TEXT_BOX_PADDING).contains(&y);
+
+        let offset = self
+            .text_renderer
+            .hit(&display.text, font_size, style, (x, y))?;
+        Some((display.to_text_offset(offset), inside))
+    }
+
+    // 🚀 在正在编辑的文本内按下鼠标：放置光标（Shift 扩展选区）并开始拖动选择
+    fn begin_text_selection(&mut self, pos: (f32, f32)) -> bool {
+        let Some((offset, true)) = self.editing_text_offset_at(pos) else {
+            return false;
+        };
+        let shift = self.modifiers.state().shift_key();
+        self.text_editor.set_cursor(offset, shift);
+        self.text_selecting = true;
+        self.update_current_text_element();
+        true
+    }
+
+    // 🚀 拖动选择文本
+    fn update_text_selection(&mut self, pos: (f32, f32)) {
+        if let Some((offset, _)) = self.editing_text_offset_at(pos) {
+            self.text_editor.set_cursor(offset, true);
+            self.update_current_text_element();
+        }
     }
 
     // 🚀 处理IME文本输入事件
@@ -3432,19 +6521,25 @@ impl State {
             }
             Ime::Preedit(text, cursor_range) => {
                 println!("🚀 IME预编辑文本: '{}', 光标范围: {:?}", text, cursor_range);
-                // 预编辑文本通常用于显示正在输入但尚未确认的文本
-                // 在这个简单的实现中，我们暂时不处理预编辑状态
+                // 🚀 预编辑文本（尚未确认的候选）显示在光标处，带下划线
+                self.text_editor.set_preedit(text, *cursor_range);
+                self.update_current_text_element();
             }
             Ime::Commit(text) => {
                 println!("🚀 IME提交文本: '{}'", text);
+                // 部分平台会把 Ctrl 组合键作为控制字符提交，忽略它们
+                if text.chars().all(char::is_control) {
+                    return;
+                }
                 // 这是真正的文本输入，插入到当前光标位置
-                self.current_text_input
-                    .insert_str(self.text_cursor_position, text);
-                self.text_cursor_position += text.len();
+                self.text_editor.clear_preedit();
+                self.text_editor.insert_str(text);
                 self.update_current_text_element();
             }
             Ime::Disabled => {
                 println!("🚀 IME已禁用");
+                self.text_editor.clear_preedit();
+                self.update_current_text_element();
             }
         }
     }
@@ -3452,18 +6547,37 @@ impl State {
     // 🚀 更新当前文本元素的内容
     fn update_current_text_element(&mut self) {
         println!(
-            "🚀 尝试更新文本元素，current_text_input: '{}'",
-            self.current_text_input
+            "🚀 尝试更新文本元素，text_input: '{}'",
+            self.text_editor.text()
         );
 
-        if let Some(DrawingElement::Text { content, .. }) = &mut self.current_drawing {
-            let old_content = content.clone();
-            *content = self.current_text_input.clone();
-            println!("🚀 文本内容更新: '{}' -> '{}'", old_content, content);
+        if let Some(element @ DrawingElement::Text { .. }) = &mut self.current_drawing {
+            let old_frame = Self::shape_rotation_frame(element);
+            if let DrawingElement::Text {
+                content,
+                font_size,
+                size,
+                style,
+                ..
+            } = element
+            {
+                println!(
+                    "🚀 文本内容更新: '{}' -> '{}'",
+                    content,
+                    self.text_editor.text()
+                );
+                *content = self.text_editor.text().to_string();
+                *size = self.text_renderer.measure(content, *font_size, style);
+            }
+            // 🚀 旋转文本：保持文字起点在屏幕上不动
+            Self::reanchor_rotated_element(element, old_frame);
 
             // 标记需要重绘
             self.needs_redraw = true;
             self.render_cache_valid = false;
+
+            // 🚀 让输入法候选窗口跟随光标
+            self.update_ime_cursor_area();
         } else {
             println!("🚀 警告：current_drawing不是Text类型或为None");
         }
@@ -3472,19 +6586,23 @@ impl State {
     // 🚀 完成文本输入
     fn finish_text_input(&mut self) {
         println!(
-            "🚀 开始完成文本输入，current_text_input: '{}'",
-            self.current_text_input
+            "🚀 开始完成文本输入，text_input: '{}'",
+            self.text_editor.text()
         );
 
         // 🚀 确保文本内容被保存到当前绘图元素中
         if let Some(DrawingElement::Text {
             content,
             is_editing,
+            font_size,
+            size,
+            style,
             ..
         }) = &mut self.current_drawing
         {
             // 保存用户输入的文本内容
-            *content = self.current_text_input.clone();
+            *content = self.text_editor.text().to_string();
+            *size = self.text_renderer.measure(content, *font_size, style);
             *is_editing = false;
 
             println!("🚀 保存文本内容: '{}' (长度: {})", content, content.len());
@@ -3493,23 +6611,27 @@ impl State {
         }
 
         // 🚀 改进：只有在文本完全为空（去除空白字符后）时才取消保存
-        let trimmed_text = self.current_text_input.trim();
+        let trimmed_text = self.text_editor.text().trim();
         if !trimmed_text.is_empty() {
             println!(
                 "🚀 文本不为空，完成绘图并保存: '{}'",
-                self.current_text_input
+                self.text_editor.text()
             );
             self.finish_current_drawing();
         } else {
             println!("🚀 文本为空（去除空白字符后），取消绘图，不保存");
             self.current_drawing = None;
             self.drawing_state = DrawingState::Idle;
+            // 🚀 已有文本被清空：记为删除
+            if let Some((index, element)) = self.editing_text_origin.take() {
+                self.record_edit("Delete text", Edit::Remove { index, element });
+            }
         }
 
         // 退出文本输入模式
         self.text_input_active = false;
-        self.current_text_input.clear();
-        self.text_cursor_position = 0;
+        self.text_selecting = false;
+        self.text_editor.clear();
 
         // 🚀 禁用IME
         self.window.set_ime_allowed(false);
@@ -3522,10 +6644,16 @@ impl State {
         // 取消当前绘图
         self.current_drawing = None;
 
+        // 🚀 取消编辑已有文本：恢复原文本
+        if let Some((index, element)) = self.editing_text_origin.take() {
+            let index = index.min(self.drawing_elements.len());
+            self.drawing_elements.insert(index, element);
+        }
+
         // 退出文本输入模式
         self.text_input_active = false;
-        self.current_text_input.clear();
-        self.text_cursor_position = 0;
+        self.text_selecting = false;
+        self.text_editor.clear();
         self.drawing_state = DrawingState::Idle;
 
         // 🚀 禁用IME
@@ -3534,103 +6662,6 @@ impl State {
         println!("🚀 取消文本输入，IME已禁用");
     }
 
-    // 🚀 新增：上下箭头光标移动函数
-    fn move_cursor_up(&mut self) {
-        let lines: Vec<&str> = self.current_text_input.split('\n').collect();
-        if lines.len() <= 1 {
-            // 只有一行，移动到行首
-            self.text_cursor_position = 0;
-            println!("🚀 光标移动到行首: {}", self.text_cursor_position);
-            return;
-        }
-
-        // 找到当前光标所在的行和列
-        let mut current_pos = 0;
-        let mut current_line = 0;
-        let mut current_col = 0;
-
-        for (line_idx, line) in lines.iter().enumerate() {
-            let line_end = current_pos + line.len();
-            if self.text_cursor_position <= line_end {
-                current_line = line_idx;
-                current_col = self.text_cursor_position - current_pos;
-                break;
-            }
-            current_pos = line_end + 1; // +1 for the '\n' character
-        }
-
-        if current_line > 0 {
-            // 移动到上一行
-            let prev_line = lines[current_line - 1];
-            let prev_line_start = lines[..current_line - 1]
-                .iter()
-                .map(|l| l.len() + 1)
-                .sum::<usize>();
-
-            // 保持列位置，但不超过上一行的长度
-            let new_col = current_col.min(prev_line.len());
-            self.text_cursor_position = prev_line_start + new_col;
-            println!(
-                "🚀 光标上移到位置: {} (行: {}, 列: {})",
-                self.text_cursor_position,
-                current_line - 1,
-                new_col
-            );
-        } else {
-            // 已经在第一行，移动到行首
-            self.text_cursor_position = 0;
-            println!("🚀 光标移动到第一行行首: {}", self.text_cursor_position);
-        }
-    }
-
-    fn move_cursor_down(&mut self) {
-        let lines: Vec<&str> = self.current_text_input.split('\n').collect();
-        if lines.len() <= 1 {
-            // 只有一行，移动到行尾
-            self.text_cursor_position = self.current_text_input.len();
-            println!("🚀 光标移动到行尾: {}", self.text_cursor_position);
-            return;
-        }
-
-        // 找到当前光标所在的行和列
-        let mut current_pos = 0;
-        let mut current_line = 0;
-        let mut current_col = 0;
-
-        for (line_idx, line) in lines.iter().enumerate() {
-            let line_end = current_pos + line.len();
-            if self.text_cursor_position <= line_end {
-                current_line = line_idx;
-                current_col = self.text_cursor_position - current_pos;
-                break;
-            }
-            current_pos = line_end + 1; // +1 for the '\n' character
-        }
-
-        if current_line < lines.len() - 1 {
-            // 移动到下一行
-            let next_line = lines[current_line + 1];
-            let next_line_start = lines[..current_line + 1]
-                .iter()
-                .map(|l| l.len() + 1)
-                .sum::<usize>();
-
-            // 保持列位置，但不超过下一行的长度
-            let new_col = current_col.min(next_line.len());
-            self.text_cursor_position = next_line_start + new_col;
-            println!(
-                "🚀 光标下移到位置: {} (行: {}, 列: {})",
-                self.text_cursor_position,
-                current_line + 1,
-                new_col
-            );
-        } else {
-            // 已经在最后一行，移动到行尾
-            self.text_cursor_position = self.current_text_input.len();
-            println!("🚀 光标移动到最后一行行尾: {}", self.text_cursor_position);
-        }
-    }
-
     // 🚀 检测是否为双击
     fn is_double_click(&mut self, pos: (f32, f32)) -> bool {
         let now = std::time::Instant::now();
@@ -3659,12 +6690,14 @@ impl State {
                 position,
                 color,
                 font_size,
+                rotation,
+                size,
+                style,
                 ..
             } = element
             {
-                // 设置当前文本输入内容
-                self.current_text_input = content.clone();
-                self.text_cursor_position = content.len();
+                // 设置当前文本输入内容，光标放在末尾
+                self.text_editor.set_text(&content);
 
                 // 创建编辑中的文本元素
                 self.current_drawing = Some(DrawingElement::Text {
@@ -3673,18 +6706,25 @@ impl State {
                     color,
                     font_size,
                     is_editing: true,
-                    rotation: None, // 🚀 编辑时保持原有旋转
+                    rotation, // 🚀 编辑时保持原有旋转
+                    size,
+                    style,
                 });
 
                 // 激活文本输入模式
                 self.text_input_active = true;
                 self.drawing_state = DrawingState::Drawing;
 
+                // 🚀 编辑已有文本同样需要IME，候选窗口跟随光标
+                self.window.set_ime_allowed(true);
+                self.update_ime_cursor_area();
+
                 // 🚀 修复：清除选中状态，避免显示多个手柄
                 self.selected_element = None;
 
-                // 从绘图元素列表中移除原文本（编辑完成后会重新添加）
-                self.drawing_elements.remove(element_index);
+                // 从绘图元素列表中移除原文本（编辑完成后放回原位置，撤销时记为一次修改）
+                let original = self.drawing_elements.remove(element_index);
+                self.editing_text_origin = Some((element_index, original));
 
                 println!("🚀 开始编辑文本: '{}'", content);
             }
@@ -3749,6 +6789,9 @@ impl State {
                 self.add_handle_vertices(handle, &mut handle_vertices);
             }
 
+            // 🚀 旋转手柄与选区之间的连接线
+            self.add_rotate_handle_connector(&selected.handles, &mut handle_vertices);
+
             // 🚀 为选中的元素添加虚线边框
             if selected.index < self.drawing_elements.len() {
                 match &self.drawing_elements[selected.index] {
@@ -3756,43 +6799,44 @@ impl State {
                         center,
                         radius_x,
                         radius_y,
+                        rotation,
                         ..
                     } => {
                         self.add_dashed_circle_border(
                             *center,
                             *radius_x,
                             *radius_y,
+                            *rotation,
                             &mut handle_vertices,
                         );
                     }
-                    DrawingElement::Text {
+                    DrawingElement::Pen { points, .. } => {
+                        // 🚀 为选中的画笔添加包围盒虚线边框
+                        if let Some((left, top, right, bottom)) = Self::pen_bounds(points) {
+                            self.add_dashed_circle_border(
+                                ((left + right) / 2.0, (top + bottom) / 2.0),
+                                (right - left) / 2.0,
+                                (bottom - top) / 2.0,
+                                0.0,
+                                &mut handle_vertices,
+                            );
+                        }
+                    }
+                    element @ DrawingElement::Text {
                         position,
-                        content,
-                        font_size,
                         is_editing,
+                        size,
                         ..
                     } => {
-                        // 🚀 为选中的文本添加黑色虚线边框
-                        if !*is_editing {
-                            let lines: Vec<&str> = content.split('\n').collect();
-                            let line_count = lines.len() as f32;
-                            let max_line_width = lines
-                                .iter()
-                                .map(|line| {
-                                    // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                                    let char_count = line.chars().count() as f32;
-                                    let estimated_width = char_count * font_size * 0.7; // 增加字符宽度系数
-                                    estimated_width + font_size * 0.2 // 减少额外空间，避免过多padding
-                                })
-                                .fold(0.0, f32::max);
-
-                            let text_width = max_line_width.max(80.0); // 减少最小宽度，避免过多空白
-                            let text_height = font_size * 1.2 * line_count;
-
+                        // 🚀 为选中的文本添加黑色虚线边框（随文本旋转）
+                        if !*is_editing
+                            && let Some((center, rotation, _)) = Self::shape_rotation_frame(element)
+                        {
                             self.add_dashed_text_border(
                                 *position,
-                                text_width,
-                                text_height,
+                                size.0,
+                                size.1,
+                                (center, rotation),
                                 &mut handle_vertices,
                             );
                         }
@@ -3835,10 +6879,17 @@ impl State {
                 center,
                 radius_x,
                 radius_y,
+                rotation,
                 ..
             } = current_drawing
             {
-                self.add_dashed_circle_border(*center, *radius_x, *radius_y, &mut handle_vertices);
+                self.add_dashed_circle_border(
+                    *center,
+                    *radius_x,
+                    *radius_y,
+                    *rotation,
+                    &mut handle_vertices,
+                );
             }
 
             if !handle_vertices.is_empty() {
@@ -4036,28 +7087,66 @@ impl State {
         }
     }
 
+    // 🚀 旋转手柄连接线：从上边中点（箭头为中点）连到旋转手柄
+    fn add_rotate_handle_connector(&self, handles: &[Handle], vertices: &mut Vec<f32>) {
+        let Some(rotate) = handles.iter().find(|h| h.handle_type == HandleType::Rotate) else {
+            return;
+        };
+        let find = |handle_type: HandleType| {
+            handles
+                .iter()
+                .find(|h| h.handle_type == handle_type)
+                .map(|h| h.position)
+        };
+        let midpoint = |a: HandleType, b: HandleType| {
+            let (a, b) = (find(a)?, find(b)?);
+            Some(((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0))
+        };
+        // 矩形/椭圆连接上边中点，箭头连接中点，文本连接上边两角的中点
+        let Some(anchor) = find(HandleType::TopCenter)
+            .or_else(|| midpoint(HandleType::ArrowStart, HandleType::ArrowEnd))
+            .or_else(|| midpoint(HandleType::TopLeft, HandleType::TopRight))
+        else {
+            return;
+        };
+
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        let color = [0.7, 0.7, 0.7];
+        for (x, y) in [anchor, rotate.position] {
+            let ndc_x = (x / screen_width) * 2.0 - 1.0;
+            let ndc_y = 1.0 - (y / screen_height) * 2.0;
+            vertices.extend_from_slice(&[ndc_x, ndc_y, color[0], color[1], color[2], 1.0, 1.0]);
+        }
+    }
+
     // 🚀 添加虚线矩形边框（用于椭圆选择指示）
     fn add_dashed_circle_border(
         &self,
         center: (f32, f32),
         radius_x: f32,
         radius_y: f32,
+        rotation: f32,
         vertices: &mut Vec<f32>,
     ) {
         let screen_width = self.size.width as f32;
         let screen_height = self.size.height as f32;
 
-        // 计算包围椭圆的矩形边界
-        let left = center.0 - radius_x;
-        let right = center.0 + radius_x;
-        let top = center.1 - radius_y;
-        let bottom = center.1 + radius_y;
-
-        // 转换到NDC坐标
-        let x1 = (left / screen_width) * 2.0 - 1.0;
-        let y1 = 1.0 - (top / screen_height) * 2.0;
-        let x2 = (right / screen_width) * 2.0 - 1.0;
-        let y2 = 1.0 - (bottom / screen_height) * 2.0;
+        // 计算包围椭圆的矩形的四个角（左上、右上、右下、左下），随椭圆一起旋转
+        let corners = [
+            (center.0 - radius_x, center.1 - radius_y),
+            (center.0 + radius_x, center.1 - radius_y),
+            (center.0 + radius_x, center.1 + radius_y),
+            (center.0 - radius_x, center.1 + radius_y),
+        ]
+        .map(|corner| {
+            // 转换到NDC坐标
+            let (x, y) = Self::rotate_point(corner, center, rotation);
+            (
+                (x / screen_width) * 2.0 - 1.0,
+                1.0 - (y / screen_height) * 2.0,
+            )
+        });
 
         let color = [0.7, 0.7, 0.7]; // 灰色虚线
         let thickness = 2.0;
@@ -4065,60 +7154,24 @@ impl State {
         // 🚀 简化的虚线绘制 - 使用更多段数让虚线更细密
         let segments_per_side = 20; // 每边20段，让虚线更细密
 
-        // 上边虚线
-        for i in 0..segments_per_side {
-            if i % 2 == 0 {
-                // 只画偶数段，形成虚线效果
-                let t1 = i as f32 / segments_per_side as f32;
-                let t2 = (i + 1) as f32 / segments_per_side as f32;
-                let sx = x1 + (x2 - x1) * t1;
-                let ex = x1 + (x2 - x1) * t2;
-                vertices.extend_from_slice(&[
-                    sx, y1, color[0], color[1], color[2], 1.0, thickness, ex, y1, color[0],
-                    color[1], color[2], 1.0, thickness,
-                ]);
-            }
-        }
-
-        // 右边虚线
-        for i in 0..segments_per_side {
-            if i % 2 == 0 {
-                let t1 = i as f32 / segments_per_side as f32;
-                let t2 = (i + 1) as f32 / segments_per_side as f32;
-                let sy = y1 + (y2 - y1) * t1;
-                let ey = y1 + (y2 - y1) * t2;
-                vertices.extend_from_slice(&[
-                    x2, sy, color[0], color[1], color[2], 1.0, thickness, x2, ey, color[0],
-                    color[1], color[2], 1.0, thickness,
-                ]);
-            }
-        }
-
-        // 下边虚线
-        for i in 0..segments_per_side {
-            if i % 2 == 0 {
-                let t1 = i as f32 / segments_per_side as f32;
-                let t2 = (i + 1) as f32 / segments_per_side as f32;
-                let sx = x2 - (x2 - x1) * t1;
-                let ex = x2 - (x2 - x1) * t2;
-                vertices.extend_from_slice(&[
-                    sx, y2, color[0], color[1], color[2], 1.0, thickness, ex, y2, color[0],
-                    color[1], color[2], 1.0, thickness,
-                ]);
-            }
-        }
-
-        // 左边虚线
-        for i in 0..segments_per_side {
-            if i % 2 == 0 {
-                let t1 = i as f32 / segments_per_side as f32;
-                let t2 = (i + 1) as f32 / segments_per_side as f32;
-                let sy = y2 - (y2 - y1) * t1;
-                let ey = y2 - (y2 - y1) * t2;
-                vertices.extend_from_slice(&[
-                    x1, sy, color[0], color[1], color[2], 1.0, thickness, x1, ey, color[0],
-                    color[1], color[2], 1.0, thickness,
-                ]);
+        // 上、右、下、左四条边的虚线
+        for side in 0..4 {
+            let (x1, y1) = corners[side];
+            let (x2, y2) = corners[(side + 1) % 4];
+            for i in 0..segments_per_side {
+                if i % 2 == 0 {
+                    // 只画偶数段，形成虚线效果
+                    let t1 = i as f32 / segments_per_side as f32;
+                    let t2 = (i + 1) as f32 / segments_per_side as f32;
+                    let sx = x1 + (x2 - x1) * t1;
+                    let sy = y1 + (y2 - y1) * t1;
+                    let ex = x1 + (x2 - x1) * t2;
+                    let ey = y1 + (y2 - y1) * t2;
+                    vertices.extend_from_slice(&[
+                        sx, sy, color[0], color[1], color[2], 1.0, thickness, ex, ey, color[0],
+                        color[1], color[2], 1.0, thickness,
+                    ]);
+                }
             }
         }
     }
@@ -4127,25 +7180,13 @@ impl State {
     fn scale_text_element(
         position: &mut (f32, f32),
         font_size: &mut f32,
-        content: &str,
+        (current_width, current_height): (f32, f32),
         mouse_pos: (f32, f32),
         handle_type: HandleType,
     ) {
-        // 计算当前文本的边界
-        let lines: Vec<&str> = content.split('\n').collect();
-        let line_count = lines.len() as f32;
-        let max_line_width = lines
-            .iter()
-            .map(|line| {
-                // 🚀 改进：为文本宽度计算增加更多空间，特别是对于中文字符
-                let char_count = line.chars().count() as f32;
-                let estimated_width = char_count * *font_size * 0.7; // 增加字符宽度系数
-                estimated_width + *font_size * 0.2 // 减少额外空间，避免过多padding
-            })
-            .fold(0.0, f32::max);
-
-        let current_width = max_line_width.max(100.0);
-        let current_height = *font_size * 1.2 * line_count;
+        // 当前文本的边界（排版测量结果），过窄时按最小宽度处理，避免缩放比例过于敏感
+        let current_width = current_width.max(*font_size);
+        let current_height = current_height.max(*font_size);
 
         // 计算文本中心点
         let center_x = position.0 + current_width / 2.0;
@@ -4246,23 +7287,22 @@ impl State {
         position: (f32, f32),
         width: f32,
         height: f32,
+        (center, rotation): ((f32, f32), f32),
         vertices: &mut Vec<f32>,
     ) {
         let screen_width = self.size.width as f32;
         let screen_height = self.size.height as f32;
 
         // 🚀 添加padding到文本边界
-        let padding = 4.0; // 减少padding，避免过多空白
+        let padding = TEXT_BOX_PADDING;
         let left = position.0 - padding;
         let right = position.0 + width + padding;
         let top = position.1 - padding;
         let bottom = position.1 + height + padding;
 
-        // 转换到NDC坐标
-        let x1 = (left / screen_width) * 2.0 - 1.0;
-        let y1 = 1.0 - (top / screen_height) * 2.0;
-        let x2 = (right / screen_width) * 2.0 - 1.0;
-        let y2 = 1.0 - (bottom / screen_height) * 2.0;
+        // 四个角（左上、右上、右下、左下），绕文本的旋转中心旋转
+        let corners = [(left, top), (right, top), (right, bottom), (left, bottom)]
+            .map(|corner| Self::rotate_point(corner, center, rotation));
 
         let color = [0.0, 0.0, 0.0]; // 黑色虚线
         let thickness = 2.0;
@@ -4272,89 +7312,1212 @@ impl State {
         let gap_length = 5.0; // 间隔长度（像素）
         let pattern_length = dash_length + gap_length;
 
-        // 上边虚线
-        let mut current_pos = 0.0;
-        while current_pos < width {
-            let end_pos = (current_pos + dash_length).min(width);
-            let t1 = current_pos / width;
-            let t2 = end_pos / width;
-            let sx = x1 + (x2 - x1) * t1;
-            let ex = x1 + (x2 - x1) * t2;
-            vertices.extend_from_slice(&[
-                sx, y1, color[0], color[1], color[2], 1.0, thickness, ex, y1, color[0], color[1],
-                color[2], 1.0, thickness,
-            ]);
-            current_pos += pattern_length;
+        // 上、右、下、左四条边的虚线
+        for side in 0..4 {
+            let (x1, y1) = corners[side];
+            let (x2, y2) = corners[(side + 1) % 4];
+            let side_length = ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt();
+            if side_length <= 0.0 {
+                continue;
+            }
+
+            let mut current_pos = 0.0;
+            while current_pos < side_length {
+                let end_pos = (current_pos + dash_length).min(side_length);
+                let t1 = current_pos / side_length;
+                let t2 = end_pos / side_length;
+                // 转换到NDC坐标
+                let to_ndc = |t: f32| {
+                    (
+                        ((x1 + (x2 - x1) * t) / screen_width) * 2.0 - 1.0,
+                        1.0 - ((y1 + (y2 - y1) * t) / screen_height) * 2.0,
+                    )
+                };
+                let (sx, sy) = to_ndc(t1);
+                let (ex, ey) = to_ndc(t2);
+                vertices.extend_from_slice(&[
+                    sx, sy, color[0], color[1], color[2], 1.0, thickness, ex, ey, color[0],
+                    color[1], color[2], 1.0, thickness,
+                ]);
+                current_pos += pattern_length;
+            }
+        }
+    }
+
+    // 🚀 正在编辑的文本内容（在光标位置插入光标字符）
+    fn editing_display_text(&self) -> String {
+        self.text_editor.display_with_cursor(CURSOR_CHAR)
+    }
+
+    // 🚀 文本的背景框、下划线和删除线：字形本身不带这些装饰，作为图形绘制在文字下方
+    fn add_text_decorations(&mut self, element: &DrawingElement, vertices: &mut Vec<f32>) {
+        let DrawingElement::Text {
+            position,
+            content,
+            color,
+            font_size,
+            is_editing,
+            style,
+            ..
+        } = element
+        else {
+            return;
+        };
+        if style.background {
+            self.add_text_background(element, vertices);
+        }
+        if !style.underline && !style.strikethrough {
+            return;
+        }
+        let text = if *is_editing {
+            self.editing_display_text()
+        } else {
+            content.clone()
+        };
+        let Some((center, rotation, _)) = Self::shape_rotation_frame(element) else {
+            return;
+        };
+
+        // 相对基线的偏移：下划线在基线下方，删除线约在 x 高度的一半
+        let offsets: Vec<f32> = [
+            (style.underline, font_size * 0.12),
+            (style.strikethrough, -font_size * 0.3),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, offset)| enabled.then_some(offset))
+        .collect();
+        let thickness = (font_size / 14.0).max(1.0);
+
+        for span in self
+            .text_renderer
+            .spans(&text, *font_size, style, 0..text.len())
+        {
+            // 空行没有字形，不画装饰线
+            if span.right - span.left < 1.0 {
+                continue;
+            }
+            for offset in &offsets {
+                let y = position.1 + span.baseline + offset;
+                let points = [(position.0 + span.left, y), (position.0 + span.right, y)]
+                    .map(|point| Self::rotate_point(point, center, rotation));
+                self.add_stroke_triangles(&points, &[], *color, thickness, vertices);
+            }
         }
+    }
+
+    // 🚀 文本背景框：排版区域四周加内边距的圆角矩形，颜色取文字颜色的对比色
+    fn add_text_background(&self, element: &DrawingElement, vertices: &mut Vec<f32>) {
+        const CORNER_SEGMENTS: usize = 6;
+        let DrawingElement::Text {
+            position,
+            size,
+            color,
+            font_size,
+            ..
+        } = element
+        else {
+            return;
+        };
+        let Some((center, rotation, _)) = Self::shape_rotation_frame(element) else {
+            return;
+        };
+
+        let padding = font_size * BACKGROUND_PADDING;
+        let (left, top) = (position.0 - padding, position.1 - padding);
+        let (right, bottom) = (position.0 + size.0 + padding, position.1 + size.1 + padding);
+        let radius = (font_size * BACKGROUND_RADIUS)
+            .min((right - left) / 2.0)
+            .min((bottom - top) / 2.0);
+
+        // 四个圆角依次沿顺时针方向取点（屏幕坐标 y 轴向下）
+        let corners = [
+            ((right - radius, top + radius), -std::f32::consts::FRAC_PI_2),
+            ((right - radius, bottom - radius), 0.0),
+            (
+                (left + radius, bottom - radius),
+                std::f32::consts::FRAC_PI_2,
+            ),
+            ((left + radius, top + radius), std::f32::consts::PI),
+        ];
+        let outline: Vec<(f32, f32)> = corners
+            .iter()
+            .flat_map(|&((cx, cy), start)| {
+                (0..=CORNER_SEGMENTS).map(move |step| {
+                    let angle =
+                        start + std::f32::consts::FRAC_PI_2 * step as f32 / CORNER_SEGMENTS as f32;
+                    (cx + radius * angle.cos(), cy + radius * angle.sin())
+                })
+            })
+            .map(|point| Self::rotate_point(point, center, rotation))
+            .collect();
+
+        // 以中心为扇形顶点三角化
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        let [r, g, b] = contrast_color(*color);
+        let fan_center = Self::rotate_point(
+            ((left + right) / 2.0, (top + bottom) / 2.0),
+            center,
+            rotation,
+        );
+        for (index, &point) in outline.iter().enumerate() {
+            let next = outline[(index + 1) % outline.len()];
+            for (x, y) in [fan_center, point, next] {
+                vertices.extend_from_slice(&[
+                    (x / screen_width) * 2.0 - 1.0,
+                    1.0 - (y / screen_height) * 2.0,
+                    r,
+                    g,
+                    b,
+                    BACKGROUND_ALPHA,
+                    1.0,
+                ]);
+            }
+        }
+    }
+
+    // 🚀 选中文本的高亮背景（在文字之前绘制，位于字形下方）
+    fn add_text_selection_highlight(&mut self, vertices: &mut Vec<f32>) {
+        let Some(
+            ref element @ DrawingElement::Text {
+                position,
+                font_size,
+                ref style,
+                ..
+            },
+        ) = self.current_drawing.clone()
+        else {
+            return;
+        };
+        let display = self.text_editor.display(CURSOR_CHAR);
+        let Some(selection) = display.selection else {
+            return;
+        };
+        let Some((center, rotation, _)) = Self::shape_rotation_frame(element) else {
+            return;
+        };
+
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        let [r, g, b, a] = TEXT_SELECTION_COLOR;
+        for span in self
+            .text_renderer
+            .spans(&display.text, font_size, style, selection)
+        {
+            let (left, right) = (position.0 + span.left, position.0 + span.right);
+            let (top, bottom) = (position.1 + span.top, position.1 + span.bottom);
+            let corners = [(left, top), (right, top), (right, bottom), (left, bottom)]
+                .map(|corner| Self::rotate_point(corner, center, rotation));
+            for index in [0, 1, 2, 0, 2, 3] {
+                let (x, y) = corners[index];
+                vertices.extend_from_slice(&[
+                    (x / screen_width) * 2.0 - 1.0,
+                    1.0 - (y / screen_height) * 2.0,
+                    r,
+                    g,
+                    b,
+                    a,
+                    1.0,
+                ]);
+            }
+        }
+    }
+
+    // 🚀 输入法预编辑文本的下划线：整段细线，输入法选中的片段加粗
+    fn add_preedit_underline(&mut self, vertices: &mut Vec<f32>) {
+        let Some(
+            ref element @ DrawingElement::Text {
+                position,
+                color,
+                font_size,
+                ref style,
+                ..
+            },
+        ) = self.current_drawing.clone()
+        else {
+            return;
+        };
+        let display = self.text_editor.display(CURSOR_CHAR);
+        let Some(preedit) = display.preedit else {
+            return;
+        };
+        let Some((center, rotation, _)) = Self::shape_rotation_frame(element) else {
+            return;
+        };
+
+        let underlines = [
+            (Some(preedit), self.ui(1.5)),
+            (display.preedit_highlight, self.ui(3.0)),
+        ];
+        for (range, thickness) in underlines {
+            let Some(range) = range else {
+                continue;
+            };
+            for span in self
+                .text_renderer
+                .spans(&display.text, font_size, style, range)
+            {
+                let y = position.1 + (span.baseline + span.bottom) / 2.0;
+                let points = [(position.0 + span.left, y), (position.0 + span.right, y)]
+                    .map(|point| Self::rotate_point(point, center, rotation));
+                self.add_stroke_triangles(&points, &[], color, thickness, vertices);
+            }
+        }
+    }
+
+    // 🚀 把输入法候选窗口的位置设到光标处（窗口物理像素）
+    fn update_ime_cursor_area(&mut self) {
+        let Some(
+            ref element @ DrawingElement::Text {
+                position,
+                font_size,
+                is_editing: true,
+                ref style,
+                ..
+            },
+        ) = self.current_drawing.clone()
+        else {
+            return;
+        };
+        let display = self.text_editor.display(CURSOR_CHAR);
+        // 光标隐藏时使用预编辑文本的起点
+        let Some(anchor) = display.caret.or(display.preedit) else {
+            return;
+        };
+        let Some(span) = self
+            .text_renderer
+            .spans(&display.text, font_size, style, anchor.start..anchor.start)
+            .into_iter()
+            .next()
+        else {
+            return;
+        };
+        let Some((center, rotation, _)) = Self::shape_rotation_frame(element) else {
+            return;
+        };
+
+        let (x, y) = Self::rotate_point(
+            (position.0 + span.left, position.1 + span.top),
+            center,
+            rotation,
+        );
+        let height = span.bottom - span.top;
+        self.window.set_ime_cursor_area(
+            winit::dpi::PhysicalPosition::new(x as f64, y as f64),
+            winit::dpi::PhysicalSize::new(1.0, height as f64),
+        );
+    }
+
+    // 🚀 收集需要以旋转四边形绘制的文本（已完成的 + 正在编辑的）
+    fn collect_rotated_texts(&self) -> Vec<RotatedText> {
+        let editing = self
+            .current_drawing
+            .iter()
+            .filter(|_| self.text_input_active);
+        let mut items = Vec::new();
+        for element in self.drawing_elements.iter().chain(editing) {
+            let DrawingElement::Text {
+                position,
+                content,
+                color,
+                font_size,
+                is_editing,
+                style,
+                ..
+            } = element
+            else {
+                continue;
+            };
+            if !Self::is_rotated_text(element) {
+                continue;
+            }
+            let Some((center, rotation, _)) = Self::shape_rotation_frame(element) else {
+                continue;
+            };
+
+            let text = if *is_editing {
+                self.editing_display_text()
+            } else {
+                content.clone()
+            };
+            if text.is_empty() {
+                continue;
+            }
+            items.push(RotatedText {
+                key: format!(
+                    "{:?}_{}_{}_{}_{}_{:?}",
+                    text, font_size, color[0], color[1], color[2], style
+                ),
+                text,
+                font_size: *font_size,
+                style: style.clone(),
+                color: *color,
+                position: *position,
+                center,
+                rotation,
+                centered: false,
+            });
+        }
+
+        // 🚀 保留为标注的测量线：尺寸文字沿测量线方向绘制在线的上方
+        let scale = self.capture_scale();
+        let font_size = self.ui(MEASURE_LABEL_SIZE);
+        let style = TextStyle {
+            outline: true,
+            ..TextStyle::default()
+        };
+        for element in &self.drawing_elements {
+            let DrawingElement::Measure {
+                start,
+                end,
+                color,
+                thickness,
+            } = element
+            else {
+                continue;
+            };
+            let text = measure::label(*start, *end, scale);
+            let distance = font_size / 2.0 + thickness / 2.0 + self.ui(MEASURE_LABEL_GAP);
+            let center = measure::label_center(*start, *end, distance);
+            items.push(RotatedText {
+                key: format!(
+                    "{:?}_{}_{}_{}_{}_{:?}",
+                    text, font_size, color[0], color[1], color[2], style
+                ),
+                text,
+                font_size,
+                style: style.clone(),
+                color: *color,
+                position: center,
+                center,
+                rotation: measure::label_rotation(*start, *end),
+                centered: true,
+            });
+        }
+        items
+    }
+
+    // 🚀 旋转文本：把新出现的文本光栅化到离屏纹理，并释放不再使用的纹理
+    fn prepare_rotated_text_textures(&mut self) {
+        let items = self.collect_rotated_texts();
+        self.rotated_text_textures
+            .retain(|key, _| items.iter().any(|item| &item.key == key));
+
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        for item in items {
+            if self.rotated_text_textures.contains_key(&item.key) {
+                continue;
+            }
+
+            // 纹理四周留出 padding，避免字形的抗锯齿边缘被裁掉
+            let (text_width, text_height) =
+                self.text_renderer
+                    .measure(&item.text, item.font_size, &item.style);
+            let size = (
+                ((text_width + TEXT_BOX_PADDING * 2.0).ceil() as u32).clamp(1, max_dimension),
+                ((text_height + TEXT_BOX_PADDING * 2.0).ceil() as u32).clamp(1, max_dimension),
+            );
+            let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Rotated Text Texture"),
+                size: wgpu::Extent3d {
+                    width: size.0,
+                    height: size.1,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.surface_format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let buffer = self
+                .text_renderer
+                .create_buffer(&item.text, item.font_size, &item.style);
+            let text_area = glyphon::TextArea {
+                buffer: &buffer,
+                left: TEXT_BOX_PADDING,
+                top: TEXT_BOX_PADDING,
+                scale: 1.0,
+                bounds: glyphon::TextBounds {
+                    left: 0,
+                    top: 0,
+                    right: size.0 as i32,
+                    bottom: size.1 as i32,
+                },
+                default_color: Self::to_glyphon_color(item.color),
+                custom_glyphs: &[],
+            };
+            let text_areas =
+                Self::outlined_text_areas(text_area, item.color, &item.style, item.font_size);
+            if let Err(e) = self.text_renderer.render_to_texture(
+                &self.device,
+                &self.queue,
+                text_areas,
+                &view,
+                size,
+                wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            ) {
+                eprintln!("Failed to rasterize rotated text: {:?}", e);
+                continue;
+            }
+
+            let bind_group = self.create_quad_bind_group(&view);
+            self.rotated_text_textures.insert(
+                item.key,
+                TextTexture {
+                    _texture: texture,
+                    bind_group,
+                    size,
+                },
+            );
+        }
+    }
+
+    // 🚀 印章纹理的光栅化边长：按截图分辨率取整到2的幂，拖动缩放时不必每帧重新光栅化
+    fn stamp_raster_size(&self, side: f32) -> u32 {
+        let (scale, _) = self.capture_scale();
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        ((side * scale.max(1.0)).max(1.0).ceil() as u32)
+            .next_power_of_two()
+            .min(max_dimension)
+    }
+
+    fn stamp_texture_key(stamp: &Stamp, raster_size: u32) -> String {
+        format!("{}@{}", stamp.key(), raster_size)
+    }
+
+    // 🚀 需要绘制的印章纹理：画布上的印章（含正在拖出的）和显示中的印章面板
+    fn needed_stamp_textures(&self) -> Vec<(String, Stamp, u32)> {
+        let mut needed = Vec::new();
+        for element in self.drawing_elements.iter().chain(&self.current_drawing) {
+            if let DrawingElement::Stamp { size, stamp, .. } = element {
+                let raster_size = self.stamp_raster_size(*size);
+                let key = Self::stamp_texture_key(stamp, raster_size);
+                needed.push((key, stamp.clone(), raster_size));
+            }
+        }
+        if self.stamp_palette_visible() {
+            let raster_size = self.stamp_raster_size(self.ui(STAMP_BUTTON_SIZE));
+            for stamp in &self.stamp_palette {
+                let key = Self::stamp_texture_key(stamp, raster_size);
+                needed.push((key, stamp.clone(), raster_size));
+            }
+        }
+        needed
+    }
+
+    // 🚀 印章：光栅化新出现的印章，并释放不再使用的纹理
+    fn prepare_stamp_textures(&mut self) {
+        let needed = self.needed_stamp_textures();
+        self.stamp_textures
+            .retain(|key, _| needed.iter().any(|(needed_key, _, _)| needed_key == key));
+
+        for (key, stamp, raster_size) in needed {
+            if self.stamp_textures.contains_key(&key) {
+                continue;
+            }
+            let texture = match &stamp {
+                Stamp::Svg { data, .. } => self.load_svg_texture(data, raster_size),
+                Stamp::Emoji(emoji) => match self.rasterize_emoji(emoji, raster_size) {
+                    Some(texture) => texture,
+                    None => continue,
+                },
+            };
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self.create_quad_bind_group(&view);
+            self.stamp_textures.insert(
+                key,
+                TextTexture {
+                    _texture: texture,
+                    bind_group,
+                    size: (raster_size, raster_size),
+                },
+            );
+        }
+    }
+
+    // 🚀 表情符号印章：通过 glyphon 的彩色字形图集绘制到离屏纹理，居中并留出边距
+    fn rasterize_emoji(&mut self, emoji: &str, raster_size: u32) -> Option<wgpu::Texture> {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Emoji Stamp Texture"),
+            size: wgpu::Extent3d {
+                width: raster_size,
+                height: raster_size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let style = TextStyle::default();
+        let font_size = raster_size as f32 * 0.75;
+        let (width, height) = self.text_renderer.measure(emoji, font_size, &style);
+        let buffer = self.text_renderer.create_buffer(emoji, font_size, &style);
+        let text_area = glyphon::TextArea {
+            buffer: &buffer,
+            left: (raster_size as f32 - width) / 2.0,
+            top: (raster_size as f32 - height) / 2.0,
+            scale: 1.0,
+            bounds: glyphon::TextBounds {
+                left: 0,
+                top: 0,
+                right: raster_size as i32,
+                bottom: raster_size as i32,
+            },
+            default_color: Self::to_glyphon_color(STAMP_EMOJI_COLOR),
+            custom_glyphs: &[],
+        };
+        if let Err(e) = self.text_renderer.render_to_texture(
+            &self.device,
+            &self.queue,
+            [text_area],
+            &view,
+            (raster_size, raster_size),
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+        ) {
+            eprintln!("Failed to rasterize emoji stamp: {:?}", e);
+            return None;
+        }
+        Some(texture)
+    }
+
+    // 🚀 以旋转四边形绘制印章（已完成的 + 正在拖出的）
+    fn render_stamps(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let quads: Vec<_> = self
+            .drawing_elements
+            .iter()
+            .chain(&self.current_drawing)
+            .filter_map(|element| {
+                let DrawingElement::Stamp {
+                    center,
+                    size,
+                    rotation,
+                    stamp,
+                } = element
+                else {
+                    return None;
+                };
+                let key = Self::stamp_texture_key(stamp, self.stamp_raster_size(*size));
+                let texture = self.stamp_textures.get(&key)?;
+                let half = size / 2.0;
+                let rect = (center.0 - half, center.1 - half, *size, *size);
+                Some((rect, *center, *rotation, 1.0, &texture.bind_group))
+            })
+            .collect();
+        self.draw_textured_quads(render_pass, &quads);
+    }
+
+    // 🚀 图像图层：上传为纹理，作为新元素放在选区中央并选中。
+    // 一个图像像素对应一个截图像素，放不下时等比缩小
+    fn add_image_layer(&mut self, image: LayerImage) {
+        let Some((min_x, min_y, max_x, max_y)) = self.current_box_coords else {
+            println!("⚠️ 没有选区，无法添加图像");
+            return;
+        };
+        if max_x <= min_x || max_y <= min_y {
+            println!("⚠️ 没有选区，无法添加图像");
+            return;
+        }
+
+        let texture_size = wgpu::Extent3d {
+            width: image.width,
+            height: image.height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Layer Texture"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        self.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image.rgba,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * image.width),
+                rows_per_image: Some(image.height),
+            },
+            texture_size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = self.create_quad_bind_group(&view);
+        let image_id = self.next_image_id;
+        self.next_image_id += 1;
+        self.image_layers.insert(
+            image_id,
+            TextTexture {
+                _texture: texture,
+                bind_group,
+                size: (image.width, image.height),
+            },
+        );
+
+        let (width, height) = image_layer::fit_size(
+            (image.width, image.height),
+            self.capture_scale(),
+            (
+                (max_x - min_x) * IMAGE_LAYER_MAX_FILL,
+                (max_y - min_y) * IMAGE_LAYER_MAX_FILL,
+            ),
+        );
+        let center = ((min_x + max_x) / 2.0, (min_y + max_y) / 2.0);
+        let element = DrawingElement::Image {
+            start: (center.0 - width / 2.0, center.1 - height / 2.0),
+            end: (center.0 + width / 2.0, center.1 + height / 2.0),
+            rotation: 0.0,
+            opacity: 1.0,
+            image_id,
+        };
+
+        let new_index = self.drawing_elements.len();
+        self.drawing_elements.push(element.clone());
+        self.record_edit(
+            "Add image",
+            Edit::Add {
+                index: new_index,
+                element,
+            },
+        );
+        self.select_element(new_index);
+        self.invalidate_drawing_cache();
+        self.needs_redraw = true;
+        self.render_cache_valid = false;
+        println!(
+            "🚀 添加图像图层 {}: {}x{} 像素",
+            image_id, image.width, image.height
+        );
+    }
+
+    // 🚀 Ctrl+V：剪贴板中有图像时粘贴为图像图层
+    fn paste_image(&mut self) {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let Some(clipboard) = self.clipboard() else {
+            return;
+        };
+        let data = match clipboard.get_image() {
+            Ok(data) => data,
+            Err(e) => {
+                println!("⚠️ 剪贴板中没有图像: {}", e);
+                return;
+            }
+        };
+        match LayerImage::from_clipboard(data, max_dimension) {
+            Ok(image) => self.add_image_layer(image),
+            Err(e) => eprintln!("读取剪贴板图像失败: {}", e),
+        }
+    }
+
+    // 🚀 拖放到窗口上的图像文件
+    fn open_image_file(&mut self, path: &std::path::Path) {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        match LayerImage::load(path, max_dimension) {
+            Ok(image) => self.add_image_layer(image),
+            Err(e) => eprintln!("无法打开图像 {}: {}", path.display(), e),
+        }
+    }
+
+    // 🚀 调整选中图像图层的不透明度，没有选中图像时返回 false
+    fn adjust_image_opacity(&mut self, delta: f32) -> bool {
+        let is_image = self
+            .selected_element
+            .as_ref()
+            .and_then(|selected| self.drawing_elements.get(selected.index))
+            .is_some_and(|element| matches!(element, DrawingElement::Image { .. }));
+        if !is_image {
+            return false;
+        }
+        self.modify_selected_element("Change opacity of", |element| {
+            let DrawingElement::Image { opacity, .. } = element else {
+                return false;
+            };
+            let new_value = (*opacity + delta).clamp(MIN_IMAGE_OPACITY, 1.0);
+            let changed = *opacity != new_value;
+            *opacity = new_value;
+            println!("🖼 图像不透明度: {:.0}%", new_value * 100.0);
+            changed
+        });
+        true
+    }
+
+    // 🚀 释放画布和撤销/重做历史中都不再引用的图像纹理
+    fn release_unused_image_layers(&mut self) {
+        let mut referenced = std::collections::HashSet::new();
+        for element in self.drawing_elements.iter().chain(self.history.elements()) {
+            if let DrawingElement::Image { image_id, .. } = element {
+                referenced.insert(*image_id);
+            }
+        }
+        self.image_layers
+            .retain(|image_id, _| referenced.contains(image_id));
+    }
+
+    // 🚀 以旋转四边形绘制图像图层
+    fn render_image_layers(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let quads: Vec<_> = self
+            .drawing_elements
+            .iter()
+            .filter_map(|element| {
+                let DrawingElement::Image {
+                    start,
+                    end,
+                    rotation,
+                    opacity,
+                    image_id,
+                } = element
+                else {
+                    return None;
+                };
+                let texture = self.image_layers.get(image_id)?;
+                let rect = (
+                    start.0.min(end.0),
+                    start.1.min(end.1),
+                    (end.0 - start.0).abs(),
+                    (end.1 - start.1).abs(),
+                );
+                let center = ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0);
+                Some((rect, center, *rotation, *opacity, &texture.bind_group))
+            })
+            .collect();
+        self.draw_textured_quads(render_pass, &quads);
+    }
+
+    // 🚀 离屏纹理的绑定组（与主绑定组布局相同，供纹理四边形管线使用）
+    fn create_quad_bind_group(&self, view: &wgpu::TextureView) -> wgpu::BindGroup {
+        let sampler = self.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Textured Quad Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let bind_group_layout = &self.render_pipeline.get_bind_group_layout(0);
+        self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("Textured Quad Bind Group"),
+        })
+    }
+
+    // 🚀 以旋转四边形绘制旋转文本的离屏纹理
+    fn render_rotated_text(&mut self, render_pass: &mut wgpu::RenderPass) {
+        let items = self.collect_rotated_texts();
+        let quads: Vec<_> = items
+            .iter()
+            .filter_map(|item| {
+                let text_texture = self.rotated_text_textures.get(&item.key)?;
+                let (width, height) = (text_texture.size.0 as f32, text_texture.size.1 as f32);
+                let rect = if item.centered {
+                    (
+                        item.position.0 - width / 2.0,
+                        item.position.1 - height / 2.0,
+                        width,
+                        height,
+                    )
+                } else {
+                    (
+                        item.position.0 - TEXT_BOX_PADDING,
+                        item.position.1 - TEXT_BOX_PADDING,
+                        width,
+                        height,
+                    )
+                };
+                Some((
+                    rect,
+                    item.center,
+                    item.rotation,
+                    1.0,
+                    &text_texture.bind_group,
+                ))
+            })
+            .collect();
+        self.draw_textured_quads(render_pass, &quads);
+    }
+
+    // 🚀 绘制纹理四边形：每项为 (未旋转的矩形 x/y/w/h, 旋转中心, 旋转角度, 不透明度, 纹理绑定组)
+    fn draw_textured_quads(&self, render_pass: &mut wgpu::RenderPass, quads: &[TexturedQuad<'_>]) {
+        if quads.is_empty() {
+            return;
+        }
+
+        let screen_width = self.size.width as f32;
+        let screen_height = self.size.height as f32;
+        let mut vertices: Vec<f32> = Vec::new();
+        for ((x, y, w, h), center, rotation, opacity, _) in quads {
+            let corners = [
+                ((*x, *y), (0.0, 0.0)),
+                ((x + w, *y), (1.0, 0.0)),
+                ((x + w, y + h), (1.0, 1.0)),
+                ((*x, y + h), (0.0, 1.0)),
+            ];
+            // 两个三角形组成四边形
+            for index in [0, 1, 2, 0, 2, 3] {
+                let (corner, (u, v)) = corners[index];
+                let (px, py) = Self::rotate_point(corner, *center, *rotation);
+                vertices.extend_from_slice(&[
+                    (px / screen_width) * 2.0 - 1.0,
+                    1.0 - (py / screen_height) * 2.0,
+                    u,
+                    v,
+                    *opacity,
+                ]);
+            }
+        }
+        let bind_groups = quads.iter().map(|(_, _, _, _, bind_group)| *bind_group);
+
+        let vertex_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Textured Quad Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        render_pass.set_pipeline(&self.textured_quad_pipeline);
+        render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        for (i, bind_group) in bind_groups.enumerate() {
+            let first = (i * 6) as u32;
+            render_pass.set_bind_group(0, bind_group, &[]);
+            render_pass.draw(first..first + 6, 0..1);
+        }
+
+        // 恢复主绑定组，后续绘制依赖它
+        if let Some(bind_group) = &self.bind_group {
+            render_pass.set_bind_group(0, bind_group, &[]);
+        }
+    }
+
+    // 🚀 导出选区：按截图原始分辨率离屏渲染截图和标注（不含工具栏、手柄等界面元素），
+    // 裁剪到选区后保存为PNG
+    fn export_selection(&mut self) -> Result<std::path::PathBuf, String> {
+        let (min_x, min_y, max_x, max_y) = self.current_box_coords.ok_or("没有选区")?;
+        let screenshot_bind_group = self.bind_group.clone().ok_or("没有截图")?;
+        let (width, height) = self
+            .capture_size
+            .unwrap_or((self.size.width, self.size.height));
+        let extent = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Export Texture"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface_format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.prepare_stamp_textures();
 
-        // 下边虚线
-        current_pos = 0.0;
-        while current_pos < width {
-            let end_pos = (current_pos + dash_length).min(width);
-            let t1 = current_pos / width;
-            let t2 = end_pos / width;
-            let sx = x1 + (x2 - x1) * t1;
-            let ex = x1 + (x2 - x1) * t2;
-            vertices.extend_from_slice(&[
-                sx, y2, color[0], color[1], color[2], 1.0, thickness, ex, y2, color[0], color[1],
-                color[2], 1.0, thickness,
-            ]);
-            current_pos += pattern_length;
+        // 1. 原始截图 + 图形标注（顶点为NDC坐标，与分辨率无关）
+        let mut line_vertices = Vec::new();
+        let mut triangle_vertices = Vec::new();
+        for element in &self.drawing_elements.clone() {
+            self.add_element_vertices(element, &mut line_vertices);
+            self.add_element_triangles(element, &mut triangle_vertices);
+            self.add_text_decorations(element, &mut triangle_vertices);
         }
+        let quad_vertices: [[f32; 5]; 6] = [
+            [-1.0, 1.0, 0.0, 0.0, 1.0],
+            [1.0, 1.0, 1.0, 0.0, 1.0],
+            [1.0, -1.0, 1.0, 1.0, 1.0],
+            [-1.0, 1.0, 0.0, 0.0, 1.0],
+            [1.0, -1.0, 1.0, 1.0, 1.0],
+            [-1.0, -1.0, 0.0, 1.0, 1.0],
+        ];
+        let quad_buffer = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Export Screenshot Quad"),
+                contents: bytemuck::cast_slice(&quad_vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
 
-        // 左边虚线
-        current_pos = 0.0;
-        while current_pos < height {
-            let end_pos = (current_pos + dash_length).min(height);
-            let t1 = current_pos / height;
-            let t2 = end_pos / height;
-            let sy = y1 + (y2 - y1) * t1;
-            let ey = y1 + (y2 - y1) * t2;
-            vertices.extend_from_slice(&[
-                x1, sy, color[0], color[1], color[2], 1.0, thickness, x1, ey, color[0], color[1],
-                color[2], 1.0, thickness,
-            ]);
-            current_pos += pattern_length;
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Export Encoder"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Export Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.textured_quad_pipeline);
+            render_pass.set_bind_group(0, &screenshot_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, quad_buffer.slice(..));
+            render_pass.draw(0..6, 0..1);
+
+            self.render_image_layers(&mut render_pass);
+            self.draw_drawing_vertices(&mut render_pass, &line_vertices, &triangle_vertices);
+            self.render_stamps(&mut render_pass);
+            self.render_rotated_text(&mut render_pass);
         }
+        self.queue.submit(std::iter::once(encoder.finish()));
 
-        // 右边虚线
-        current_pos = 0.0;
-        while current_pos < height {
-            let end_pos = (current_pos + dash_length).min(height);
-            let t1 = current_pos / height;
-            let t2 = end_pos / height;
-            let sy = y1 + (y2 - y1) * t1;
-            let ey = y1 + (y2 - y1) * t2;
-            vertices.extend_from_slice(&[
-                x2, sy, color[0], color[1], color[2], 1.0, thickness, x2, ey, color[0], color[1],
-                color[2], 1.0, thickness,
-            ]);
-            current_pos += pattern_length;
+        // 2. 文本：按截图分辨率缩放后绘制
+        let (scale, _) = self.capture_scale();
+        let texts: Vec<_> = self
+            .drawing_elements
+            .clone()
+            .iter()
+            .filter(|element| !Self::is_rotated_text(element))
+            .filter_map(|element| match element {
+                DrawingElement::Text {
+                    position,
+                    content,
+                    color,
+                    font_size,
+                    size,
+                    style,
+                    ..
+                } if !content.is_empty() => {
+                    let buffer = self.text_renderer.create_buffer(content, *font_size, style);
+                    Some((*position, *size, *color, *font_size, style.clone(), buffer))
+                }
+                _ => None,
+            })
+            .collect();
+        if !texts.is_empty() {
+            let text_areas =
+                texts
+                    .iter()
+                    .flat_map(|(position, size, color, font_size, style, buffer)| {
+                        // 裁剪边界与屏幕上一致，留出描边和抗锯齿边缘的空间
+                        let bounds = Self::text_bounds(*position, *size);
+                        let area = glyphon::TextArea {
+                            buffer,
+                            left: position.0 * scale,
+                            top: position.1 * scale,
+                            scale,
+                            bounds: glyphon::TextBounds {
+                                left: (bounds.left as f32 * scale).floor() as i32,
+                                top: (bounds.top as f32 * scale).floor() as i32,
+                                right: (bounds.right as f32 * scale).ceil() as i32,
+                                bottom: (bounds.bottom as f32 * scale).ceil() as i32,
+                            },
+                            default_color: Self::to_glyphon_color(*color),
+                            custom_glyphs: &[],
+                        };
+                        Self::outlined_text_areas(area, *color, style, *font_size)
+                    });
+            self.text_renderer
+                .render_to_texture(
+                    &self.device,
+                    &self.queue,
+                    text_areas,
+                    &view,
+                    (width, height),
+                    wgpu::LoadOp::Load,
+                )
+                .map_err(|e| e.to_string())?;
         }
+
+        // 3. 把选区换算到截图像素，读回并保存
+        let (left, top) = self.window_to_capture((min_x, min_y));
+        let (right, bottom) = self.window_to_capture((max_x, max_y));
+        let crop_x = (left.round().max(0.0) as u32).min(width - 1);
+        let crop_y = (top.round().max(0.0) as u32).min(height - 1);
+        let crop_width = (right.round().max(0.0) as u32)
+            .min(width)
+            .saturating_sub(crop_x)
+            .max(1);
+        let crop_height = (bottom.round().max(0.0) as u32)
+            .min(height)
+            .saturating_sub(crop_y)
+            .max(1);
+
+        let padded_bytes_per_row = export::padded_bytes_per_row(crop_width);
+        let readback_buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Export Readback Buffer"),
+            size: (padded_bytes_per_row * crop_height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Export Readback Encoder"),
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: crop_x,
+                    y: crop_y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &readback_buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(crop_height),
+                },
+            },
+            wgpu::Extent3d {
+                width: crop_width,
+                height: crop_height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback_buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::Wait)
+            .map_err(|e| e.to_string())?;
+        receiver
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?;
+
+        let bgra = matches!(
+            self.surface_format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let rgba = export::unpad_rows(
+            &slice.get_mapped_range(),
+            crop_width,
+            crop_height,
+            padded_bytes_per_row,
+            bgra,
+        );
+        readback_buffer.unmap();
+
+        let path = export::output_path(self.config.export.directory.as_deref());
+        export::save_png(&path, crop_width, crop_height, rgba)?;
+        Ok(path)
     }
 
     // 🚀 缓存优化的绘图渲染：使用智能缓存减少重复计算
     fn render_drawings_batched(&mut self, render_pass: &mut wgpu::RenderPass) {
         // 🚀 收集所有绘图元素的顶点（使用缓存）
         let mut line_vertices = Vec::new();
+        let mut triangle_vertices = Vec::new();
 
         // 🚀 添加已完成的绘图元素（使用缓存）
         for element in &self.drawing_elements.clone() {
             self.add_element_vertices(element, &mut line_vertices);
+            self.add_element_triangles(element, &mut triangle_vertices);
+            self.add_text_decorations(element, &mut triangle_vertices);
         }
 
         // 🚀 添加当前正在绘制的元素（动态元素，可能需要实时计算）
         if let Some(ref current) = self.current_drawing.clone() {
             self.add_element_vertices(current, &mut line_vertices);
+            self.add_element_triangles(current, &mut triangle_vertices);
+            self.add_text_decorations(current, &mut triangle_vertices);
 
-            // 🚀 为正在编辑的文本添加边框
+            // 🚀 为正在编辑的文本添加边框和输入法预编辑下划线
             if let DrawingElement::Text { is_editing, .. } = current {
                 if *is_editing {
                     self.render_text_border(&mut line_vertices);
+                    self.add_text_selection_highlight(&mut triangle_vertices);
+                    self.add_preedit_underline(&mut triangle_vertices);
                 }
             }
         }
 
+        // 🚀 未保留为标注的测量线
+        if let Some(measurement) = self.measurement.clone() {
+            self.add_element_vertices(&measurement, &mut line_vertices);
+        }
+
+        // 🚀 橡皮擦工具激活时在鼠标位置显示橡皮擦范围
+        if self.toolbar_active
+            && self.current_tool == Tool::Eraser
+            && let Some((x, y)) = self.mouse_position
+            && self.is_point_in_screenshot_area(x, y)
+        {
+            self.add_eraser_cursor_vertices((x, y), &mut line_vertices);
+        }
+
+        // 🚀 超出选区的标注显示警告框，拖动时显示吸附参考线，框选前高亮检测到的区域
+        self.add_outside_warning_triangles(&mut triangle_vertices);
+        self.add_snap_guide_triangles(&mut triangle_vertices);
+        self.add_hovered_region_triangles(&mut triangle_vertices);
+
+        self.draw_drawing_vertices(render_pass, &line_vertices, &triangle_vertices);
+    }
+
+    // 🚀 提交绘图顶点：先画有宽度的三角形，再画线段
+    fn draw_drawing_vertices(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+        line_vertices: &[f32],
+        triangle_vertices: &[f32],
+    ) {
+        // 🚀 先渲染有宽度的笔画三角形
+        if !triangle_vertices.is_empty() {
+            let triangle_buffer =
+                self.device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("Drawing Triangle Buffer"),
+                        contents: bytemuck::cast_slice(triangle_vertices),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    });
+
+            render_pass.set_pipeline(&self.drawing_triangle_pipeline);
+            render_pass.set_vertex_buffer(0, triangle_buffer.slice(..));
+            render_pass.draw(0..(triangle_vertices.len() / 7) as u32, 0..1);
+        }
+
         // 如果没有顶点数据，直接返回
         if line_vertices.is_empty() {
             return;
@@ -4365,7 +8528,7 @@ impl State {
             .device
             .create_buffer_init(&wgpu::util::BufferInitDescriptor {
                 label: Some("Drawing Buffer"),
-                contents: bytemuck::cast_slice(&line_vertices),
+                contents: bytemuck::cast_slice(line_vertices),
                 usage: wgpu::BufferUsages::VERTEX,
             });
 
@@ -4416,16 +8579,29 @@ impl State {
         let mut instance_data = Vec::new();
         let mut bind_groups = Vec::new();
 
-        for (i, button) in self.toolbar_buttons.iter().enumerate() {
+        for button in self.toolbar_buttons.iter() {
             if let Some(icon_bind_group) = self.get_icon_bind_group(button.tool) {
                 let (btn_x, btn_y, btn_w, btn_h) = button.rect;
 
                 // 计算实例变换矩阵
-                let padding = if i == 3 || i == 4 || i == 5 || i == 6 {
-                    3.0
-                } else {
-                    2.0
-                };
+                let padding = self.ui(
+                    if matches!(
+                        button.tool,
+                        Tool::Pen
+                            | Tool::Text
+                            | Tool::Eraser
+                            | Tool::ColorPicker
+                            | Tool::Measure
+                            | Tool::Undo
+                            | Tool::Redo
+                            | Tool::History
+                            | Tool::Save
+                    ) {
+                        3.0
+                    } else {
+                        2.0
+                    },
+                );
                 let icon_vertices = self
                     .create_icon_quad_vertices_with_padding(btn_x, btn_y, btn_w, btn_h, padding);
 
@@ -4477,6 +8653,57 @@ impl State {
         self.text_renderer
             .resize(new_size.width, new_size.height, &self.queue);
     }
+
+    // 🚀 HiDPI：逻辑UI单位 -> 窗口物理像素
+    fn ui(&self, logical: f32) -> f32 {
+        logical * self.scale_factor
+    }
+
+    // 🚀 窗口移动到不同缩放比例的显示器（或系统缩放改变）时重新计算界面尺寸
+    fn set_scale_factor(&mut self, scale_factor: f32) {
+        if (scale_factor - self.scale_factor).abs() < f32::EPSILON {
+            return;
+        }
+        // 用户调整过的橡皮擦半径按比例保留
+        self.eraser_radius *= scale_factor / self.scale_factor;
+        self.scale_factor = scale_factor;
+        self.border_width = self.ui(DEFAULT_BORDER_WIDTH);
+        self.handle_size = self.ui(DEFAULT_HANDLE_SIZE);
+        self.handle_border_width = self.ui(DEFAULT_HANDLE_BORDER_WIDTH);
+
+        // 图标按新尺寸重新光栅化，工具栏和手柄重新布局
+        self.initialize_svg_icons();
+        self.update_toolbar_layout();
+        if let Some(selected) = &self.selected_element
+            && let Some(element) = self.drawing_elements.get(selected.index)
+        {
+            let handles = self.generate_handles_for_element(element, selected.index);
+            if let Some(selected) = self.selected_element.as_mut() {
+                selected.handles = handles;
+            }
+        }
+        self.update_uniforms();
+        self.invalidate_background_cache();
+        self.needs_redraw = true;
+        println!("🚀 缩放系数变为: {}", scale_factor);
+    }
+
+    // 🚀 窗口物理像素 -> 截图像素的缩放比例（截图分辨率可能与窗口大小不同）
+    fn capture_scale(&self) -> (f32, f32) {
+        match self.capture_size {
+            Some((width, height)) if self.size.width > 0 && self.size.height > 0 => (
+                width as f32 / self.size.width as f32,
+                height as f32 / self.size.height as f32,
+            ),
+            _ => (1.0, 1.0),
+        }
+    }
+
+    // 🚀 窗口坐标 -> 截图坐标
+    fn window_to_capture(&self, pos: (f32, f32)) -> (f32, f32) {
+        let (scale_x, scale_y) = self.capture_scale();
+        (pos.0 * scale_x, pos.1 * scale_y)
+    }
 }
 
 struct App {
@@ -4484,11 +8711,15 @@ struct App {
     mouse_pressed: bool,
     box_start: (f32, f32),
     first_drag_move: bool,
+    box_snap: (f32, f32), // 🚀 移动选区时当前的吸附修正量
     box_created: bool,
     current_box: Option<(f32, f32, f32, f32)>,
     drag_mode: DragMode,
     needs_redraw: bool,
     mouse_press_position: Option<(f32, f32)>,
+    // 🚀 触控/手写笔输入：只跟踪一个触点，并转换为鼠标事件处理
+    active_touch: Option<u64>,
+    dispatching_touch: bool,
 }
 
 #[derive(PartialEq)]
@@ -4518,11 +8749,14 @@ impl Default for App {
             mouse_pressed: false,
             box_start: (0.0, 0.0),
             first_drag_move: false,
+            box_snap: (0.0, 0.0),
             box_created: false,
             current_box: None,
             drag_mode: DragMode::None,
             needs_redraw: false,
             mouse_press_position: None,
+            active_touch: None,
+            dispatching_touch: false,
         }
     }
 }
@@ -4579,14 +8813,69 @@ fn is_mouse_in_box_body_static(
     }
 }
 
+impl App {
+    // 🚀 触控/手写笔事件：记录压力后转换为左键按下/移动/释放，复用鼠标的交互逻辑
+    fn handle_touch(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        window_id: WindowId,
+        touch: winit::event::Touch,
+    ) {
+        use winit::event::{ElementState, MouseButton, TouchPhase};
+
+        // 只跟踪第一个触点，忽略多指触控的其他手指
+        if let Some(active) = self.active_touch
+            && active != touch.id
+        {
+            return;
+        }
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        state.pen_pressure = touch.force.map(|force| force.normalized() as f32);
+
+        let cursor_moved = WindowEvent::CursorMoved {
+            device_id: touch.device_id,
+            position: touch.location,
+        };
+        let mouse_input = |button_state| WindowEvent::MouseInput {
+            device_id: touch.device_id,
+            state: button_state,
+            button: MouseButton::Left,
+        };
+
+        self.dispatching_touch = true;
+        match touch.phase {
+            TouchPhase::Started => {
+                self.active_touch = Some(touch.id);
+                self.window_event(event_loop, window_id, cursor_moved);
+                self.window_event(event_loop, window_id, mouse_input(ElementState::Pressed));
+            }
+            TouchPhase::Moved => {
+                self.window_event(event_loop, window_id, cursor_moved);
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.window_event(event_loop, window_id, cursor_moved);
+                self.window_event(event_loop, window_id, mouse_input(ElementState::Released));
+                self.active_touch = None;
+                if let Some(state) = self.state.as_mut() {
+                    state.pen_pressure = None;
+                }
+            }
+        }
+        self.dispatching_touch = false;
+    }
+}
+
 impl ApplicationHandler for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let screenshot_data = if let Ok(screens) = screenshots::Screen::all() {
             if let Some(screen) = screens.first() {
+                let origin = (screen.display_info.x, screen.display_info.y);
                 screen.capture().ok().map(|img| {
                     let dimensions = (img.width(), img.height());
                     let rgba = img.into_vec();
-                    (rgba, dimensions.0, dimensions.1)
+                    (rgba, dimensions.0, dimensions.1, origin)
                 })
             } else {
                 None
@@ -4610,7 +8899,8 @@ impl ApplicationHandler for App {
 
         let mut state = pollster::block_on(State::new(window.clone()));
 
-        if let Some((rgba, width, height)) = screenshot_data {
+        if let Some((rgba, width, height, origin)) = screenshot_data {
+            state.capture_origin = origin;
             state.load_screenshot_from_data(rgba, width, height);
         }
 
@@ -4619,15 +8909,41 @@ impl ApplicationHandler for App {
         window.request_redraw();
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
         // 🔧 GPU优化：使用Wait模式降低CPU和GPU负载，只在有事件时处理
         event_loop.set_control_flow(ControlFlow::Wait);
 
+        if let WindowEvent::Touch(touch) = event {
+            self.handle_touch(event_loop, id, touch);
+            return;
+        }
+        // 🚀 触控进行中时忽略系统模拟的鼠标事件，避免重复处理
+        if self.active_touch.is_some()
+            && !self.dispatching_touch
+            && matches!(
+                event,
+                WindowEvent::MouseInput { .. } | WindowEvent::CursorMoved { .. }
+            )
+        {
+            return;
+        }
+
         if let Some(state) = self.state.as_mut() {
+            // 🚀 撤销/重做可能改变了选区，以 State 中的选区为准
+            let region = state.capture_region();
+            if region != self.current_box && self.drag_mode != DragMode::Creating {
+                self.current_box = region;
+                self.box_created = region.is_some();
+                self.drag_mode = DragMode::None;
+            }
+
             match event {
                 WindowEvent::CloseRequested => event_loop.exit(),
                 WindowEvent::RedrawRequested => state.render(),
                 WindowEvent::Resized(size) => state.resize(size),
+                WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                    state.set_scale_factor(scale_factor as f32);
+                }
 
                 WindowEvent::MouseInput {
                     state: button_state,
@@ -4639,6 +8955,60 @@ impl ApplicationHandler for App {
                     if button == MouseButton::Left {
                         match button_state {
                             ElementState::Pressed => {
+                                // 🚀 点击选区尺寸标签：打开数值输入或切换字段、宽高比
+                                if !state.text_input_active
+                                    && let Some(mouse_pos) = state.mouse_position
+                                    && let Some(cell) = state.size_label_at(mouse_pos)
+                                {
+                                    state.click_size_label(cell);
+                                    state.window.request_redraw();
+                                    return;
+                                }
+                                // 点击其他地方应用输入的数值
+                                if state.size_entry.is_some() {
+                                    state.apply_size_entry();
+                                    state.window.request_redraw();
+                                }
+
+                                // 🚀 点击文本属性栏（编辑文本时不会结束编辑）
+                                if let Some(mouse_pos) = state.mouse_position
+                                    && let Some(property) = state.text_property_at(mouse_pos)
+                                {
+                                    state.apply_text_property(property);
+                                    state.window.request_redraw();
+                                    return;
+                                }
+
+                                // 🚀 点击历史记录面板跳转到该步骤
+                                if let Some(mouse_pos) = state.mouse_position
+                                    && let Some(position) = state.history_panel_at(mouse_pos)
+                                {
+                                    if state.text_input_active {
+                                        state.finish_text_input();
+                                    }
+                                    state.jump_to_history(position);
+                                    state.window.request_redraw();
+                                    return;
+                                }
+
+                                // 🚀 点击印章面板选择印章
+                                if let Some(mouse_pos) = state.mouse_position
+                                    && let Some(index) = state.stamp_palette_at(mouse_pos)
+                                {
+                                    state.select_stamp(index);
+                                    state.window.request_redraw();
+                                    return;
+                                }
+
+                                // 🚀 点击正在编辑的文本：放置光标并开始拖动选择
+                                if state.text_input_active
+                                    && let Some(mouse_pos) = state.mouse_position
+                                    && state.begin_text_selection(mouse_pos)
+                                {
+                                    state.window.request_redraw();
+                                    return;
+                                }
+
                                 // 🚀 如果正在文本输入模式，点击其他地方完成文本输入
                                 if state.text_input_active {
                                     println!("🚀 文本输入模式下点击其他地方，完成文本输入");
@@ -4649,19 +9019,50 @@ impl ApplicationHandler for App {
 
                                 // 🚀 优先检查绘图元素交互（无论工具栏是否激活）
                                 if let Some(mouse_pos) = state.mouse_position {
+                                    // 🚀 取色器：单击取色，不选择元素
+                                    if state.toolbar_active
+                                        && state.current_tool == Tool::ColorPicker
+                                        && state
+                                            .is_point_in_screenshot_area(mouse_pos.0, mouse_pos.1)
+                                    {
+                                        state.pick_color(mouse_pos);
+                                        state.window.request_redraw();
+                                        return;
+                                    }
+
+                                    // 🚀 橡皮擦工具：按下即开始擦除，不选择元素
+                                    if state.toolbar_active
+                                        && state.current_tool == Tool::Eraser
+                                        && state
+                                            .is_point_in_screenshot_area(mouse_pos.0, mouse_pos.1)
+                                    {
+                                        state.start_erasing(mouse_pos);
+                                        state.window.request_redraw();
+                                        return;
+                                    }
+
                                     // 首先检查是否点击了手柄
                                     if let Some(ref selected) = state.selected_element.clone() {
                                         for handle in &selected.handles {
                                             if state.hit_test_handle(mouse_pos, handle) {
-                                                // 🚀 开始拖拽手柄前保存状态
-                                                state.save_state_for_undo();
-
                                                 // 🚀 更新工具栏状态以反映当前拖拽的元素类型
                                                 if selected.index < state.drawing_elements.len() {
                                                     let element = state.drawing_elements
                                                         [selected.index]
                                                         .clone();
                                                     state.update_tool_from_element(&element);
+
+                                                    // 🚀 整个拖拽手势记录为一个撤销步骤
+                                                    let action = match handle.handle_type {
+                                                        HandleType::Rotate => "Rotate",
+                                                        HandleType::Move => "Move",
+                                                        _ => "Resize",
+                                                    };
+                                                    state.history.begin_group(&format!(
+                                                        "{} {}",
+                                                        action,
+                                                        element.name()
+                                                    ));
                                                 }
 
                                                 state.dragging_handle = Some(handle.clone());
@@ -4671,6 +9072,7 @@ impl ApplicationHandler for App {
                                                     {
                                                         sel.is_moving = true;
                                                         sel.move_offset = mouse_pos;
+                                                        sel.snap_correction = (0.0, 0.0);
                                                     }
                                                 }
                                                 state.window.request_redraw();
@@ -4707,13 +9109,16 @@ impl ApplicationHandler for App {
 
                                         state.select_element(i);
 
-                                        // 🚀 开始移动元素前保存状态
-                                        state.save_state_for_undo();
+                                        // 🚀 整个移动手势记录为一个撤销步骤（没有移动时不记录）
+                                        let label =
+                                            format!("Move {}", state.drawing_elements[i].name());
+                                        state.history.begin_group(&label);
 
                                         // 🚀 点击元素内部开始拖动
                                         if let Some(ref mut selected) = state.selected_element {
                                             selected.is_moving = true;
                                             selected.move_offset = mouse_pos;
+                                            selected.snap_correction = (0.0, 0.0);
                                         }
                                         state.window.request_redraw();
                                         clicked_element = true;
@@ -4750,6 +9155,24 @@ impl ApplicationHandler for App {
                                 }
                             }
                             ElementState::Released => {
+                                if !state.snap_guides.is_empty() {
+                                    state.snap_guides.clear();
+                                    state.needs_redraw = true;
+                                }
+
+                                // 🚀 结束拖动选择文本
+                                if state.text_selecting {
+                                    state.text_selecting = false;
+                                    return;
+                                }
+
+                                // 🚀 结束擦除手势
+                                if state.erasing {
+                                    state.finish_erasing();
+                                    state.window.request_redraw();
+                                    return;
+                                }
+
                                 // 完成绘图（但不包括文本输入）
                                 if state.drawing_state == DrawingState::Drawing {
                                     // 🚀 对于文本工具，不要在鼠标释放时完成绘图
@@ -4770,6 +9193,7 @@ impl ApplicationHandler for App {
                                 // 🚀 停止拖拽手柄
                                 if state.dragging_handle.is_some() {
                                     state.dragging_handle = None;
+                                    state.end_history_group();
                                     state.window.request_redraw();
                                     return;
                                 }
@@ -4778,6 +9202,7 @@ impl ApplicationHandler for App {
                                 if let Some(ref mut selected) = state.selected_element {
                                     if selected.is_moving {
                                         selected.is_moving = false;
+                                        state.end_history_group();
                                         state.window.request_redraw();
                                         return;
                                     }
@@ -4786,6 +9211,15 @@ impl ApplicationHandler for App {
                                 self.first_drag_move = false;
                                 self.mouse_press_position = None;
 
+                                // 🚀 结束选区拖动或缩放，整个手势记录为一个撤销步骤
+                                if matches!(
+                                    self.drag_mode,
+                                    DragMode::Resizing(_) | DragMode::Moving
+                                ) {
+                                    state.end_history_group();
+                                    state.warn_outside_annotations();
+                                }
+
                                 if let Some(mouse_pos) = state.mouse_position {
                                     let toolbar_tool =
                                         state.get_toolbar_button_at(mouse_pos.0, mouse_pos.1);
@@ -4808,6 +9242,15 @@ impl ApplicationHandler for App {
 
                                 match self.drag_mode {
                                     DragMode::Creating => {
+                                        // 🚀 单击（没有拖出选区）时采用鼠标下自动检测到的区域
+                                        if self.current_box.is_none() {
+                                            self.current_box = state.hovered_region.take().filter(
+                                                |(min_x, min_y, max_x, max_y)| {
+                                                    max_x - min_x >= MIN_BOX_SIZE
+                                                        && max_y - min_y >= MIN_BOX_SIZE
+                                                },
+                                            );
+                                        }
                                         if let Some((min_x, min_y, max_x, max_y)) = self.current_box
                                         {
                                             if max_x - min_x >= MIN_BOX_SIZE
@@ -4816,6 +9259,7 @@ impl ApplicationHandler for App {
                                                 self.box_created = true;
                                                 state.show_toolbar();
                                                 state.update_box(min_x, min_y, max_x, max_y);
+                                                state.record_region_change("Select region", None);
                                                 state.window.request_redraw();
                                             }
                                         }
@@ -4839,6 +9283,12 @@ impl ApplicationHandler for App {
                     if old_hovered != state.hovered_button {
                         state.window.request_redraw();
                     }
+                    // 🚀 拖动选择文本
+                    if state.text_selecting {
+                        state.update_text_selection((position.x as f32, position.y as f32));
+                        state.window.request_redraw();
+                        return;
+                    }
                     // 🚀 处理手柄拖拽
                     if state.dragging_handle.is_some() {
                         state.handle_drag((position.x as f32, position.y as f32));
@@ -4855,29 +9305,58 @@ impl ApplicationHandler for App {
 
                     if should_move {
                         let mouse_pos = (position.x as f32, position.y as f32);
-                        let (offset, selected_index) =
+                        let (delta, old_correction, selected_index) =
                             if let Some(ref selected) = state.selected_element {
-                                let offset = (
+                                let delta = (
                                     mouse_pos.0 - selected.move_offset.0,
                                     mouse_pos.1 - selected.move_offset.1,
                                 );
-                                (offset, selected.index)
+                                (delta, selected.snap_correction, selected.index)
                             } else {
                                 return;
                             };
 
                         if selected_index < state.drawing_elements.len() {
+                            // 🚀 吸附：先去掉上一次的修正量得到跟随鼠标的位置，再重新吸附
+                            let correction = match State::element_bounds(
+                                &state.drawing_elements[selected_index],
+                            ) {
+                                Some((left, top, right, bottom)) => {
+                                    let dx = delta.0 - old_correction.0;
+                                    let dy = delta.1 - old_correction.1;
+                                    state.snap_moving_rect(
+                                        (left + dx, top + dy, right + dx, bottom + dy),
+                                        Some(selected_index),
+                                    )
+                                }
+                                None => (0.0, 0.0),
+                            };
+                            let offset = (
+                                delta.0 - old_correction.0 + correction.0,
+                                delta.1 - old_correction.1 + correction.1,
+                            );
+
                             let element = &mut state.drawing_elements[selected_index];
+                            let before = element.clone();
                             State::move_element_static(element, offset);
 
                             // 更新手柄位置
                             let element_clone = element.clone();
+                            state.history.record(
+                                "",
+                                Edit::Modify {
+                                    index: selected_index,
+                                    before,
+                                    after: element_clone.clone(),
+                                },
+                            );
                             let new_handles =
                                 state.generate_handles_for_element(&element_clone, selected_index);
 
                             if let Some(ref mut selected) = state.selected_element {
                                 selected.handles = new_handles;
                                 selected.move_offset = mouse_pos;
+                                selected.snap_correction = correction;
                             }
 
                             state.needs_redraw = true;
@@ -4909,6 +9388,22 @@ impl ApplicationHandler for App {
                     // 🚀 更新鼠标指针状态
                     state.update_cursor((position.x as f32, position.y as f32));
 
+                    // 🚀 取色器：悬停时刷新取色读数
+                    if state.toolbar_active && state.current_tool == Tool::ColorPicker {
+                        state.window.request_redraw();
+                    }
+
+                    // 🚀 橡皮擦：拖动时擦除，悬停时刷新橡皮擦光标
+                    if state.toolbar_active && state.current_tool == Tool::Eraser {
+                        if state.erasing {
+                            state.erase_at((position.x as f32, position.y as f32));
+                        }
+                        state.window.request_redraw();
+                        if state.erasing {
+                            return;
+                        }
+                    }
+
                     // 更新绘图
                     if state.drawing_state == DrawingState::Drawing {
                         state.update_drawing(position.x as f32, position.y as f32);
@@ -4932,6 +9427,9 @@ impl ApplicationHandler for App {
 
                             if let Some(handle) = handle {
                                 self.drag_mode = DragMode::Resizing(handle);
+                                if !state.toolbar_active {
+                                    state.history.begin_group("Resize selection");
+                                }
                             } else if is_mouse_in_box_body_static(
                                 mouse_pos.0,
                                 mouse_pos.1,
@@ -4939,6 +9437,9 @@ impl ApplicationHandler for App {
                                 handle_size,
                             ) {
                                 self.drag_mode = DragMode::Moving;
+                                if !state.toolbar_active {
+                                    state.history.begin_group("Move selection");
+                                }
                             } else {
                                 self.drag_mode = DragMode::None;
                                 self.mouse_pressed = false;
@@ -4946,6 +9447,14 @@ impl ApplicationHandler for App {
                         }
                     }
 
+                    // 🚀 框选前高亮鼠标下自动检测到的区域
+                    if !self.box_created
+                        && !self.mouse_pressed
+                        && state.update_hovered_region((position.x as f32, position.y as f32))
+                    {
+                        state.window.request_redraw();
+                    }
+
                     // 更新鼠标指针样式
                     if !self.mouse_pressed {
                         let mouse_x = position.x as f32;
@@ -5033,13 +9542,27 @@ impl ApplicationHandler for App {
                                 self.first_drag_move = false;
                             } else {
                                 let current_pos = (position.x as f32, position.y as f32);
-                                let min_x = self.box_start.0.min(current_pos.0);
-                                let min_y = self.box_start.1.min(current_pos.1);
-                                let max_x = self.box_start.0.max(current_pos.0);
-                                let max_y = self.box_start.1.max(current_pos.1);
+                                let left = current_pos.0 < self.box_start.0;
+                                let top = current_pos.1 < self.box_start.1;
+                                // 🚀 限制在屏幕内；宽高比锁定时起点固定，只移动跟随鼠标的两条边
+                                let (min_x, min_y, max_x, max_y) = state.constrain_capture_box(
+                                    (
+                                        self.box_start.0.min(current_pos.0),
+                                        self.box_start.1.min(current_pos.1),
+                                        self.box_start.0.max(current_pos.0),
+                                        self.box_start.1.max(current_pos.1),
+                                    ),
+                                    capture_size::Sides {
+                                        left,
+                                        top,
+                                        right: !left,
+                                        bottom: !top,
+                                    },
+                                );
 
                                 if max_x - min_x >= MIN_BOX_SIZE && max_y - min_y >= MIN_BOX_SIZE {
                                     self.current_box = Some((min_x, min_y, max_x, max_y));
+                                    state.hovered_region = None;
                                     state.update_box(min_x, min_y, max_x, max_y);
                                     self.needs_redraw = true;
                                 }
@@ -5051,55 +9574,44 @@ impl ApplicationHandler for App {
                             {
                                 if self.first_drag_move {
                                     self.box_start = (position.x as f32, position.y as f32);
+                                    self.box_snap = (0.0, 0.0);
                                     self.first_drag_move = false;
                                 } else {
                                     let current_pos = (position.x as f32, position.y as f32);
-                                    let offset_x = current_pos.0 - self.box_start.0;
-                                    let offset_y = current_pos.1 - self.box_start.1;
-
-                                    let new_min_x = box_min_x + offset_x;
-                                    let new_min_y = box_min_y + offset_y;
-                                    let new_max_x = box_max_x + offset_x;
-                                    let new_max_y = box_max_y + offset_y;
-
-                                    let screen_width = state.size.width as f32;
-                                    let screen_height = state.size.height as f32;
-
-                                    let clamped_min_x = new_min_x.max(0.0);
-                                    let clamped_min_y = new_min_y.max(0.0);
-                                    let clamped_max_x = new_max_x.min(screen_width);
-                                    let clamped_max_y = new_max_y.min(screen_height);
-
-                                    let box_width = box_max_x - box_min_x;
-                                    let box_height = box_max_y - box_min_y;
-
-                                    let (final_min_x, final_max_x) =
-                                        if clamped_max_x == screen_width {
-                                            (screen_width - box_width, screen_width)
-                                        } else if clamped_min_x == 0.0 {
-                                            (0.0, box_width)
-                                        } else {
-                                            (clamped_min_x, clamped_max_x)
-                                        };
-
-                                    let (final_min_y, final_max_y) =
-                                        if clamped_max_y == screen_height {
-                                            (screen_height - box_height, screen_height)
-                                        } else if clamped_min_y == 0.0 {
-                                            (0.0, box_height)
-                                        } else {
-                                            (clamped_min_y, clamped_max_y)
-                                        };
+                                    // 🚀 去掉上一次的吸附修正量，得到跟随鼠标的位置后重新吸附
+                                    let offset_x =
+                                        current_pos.0 - self.box_start.0 - self.box_snap.0;
+                                    let offset_y =
+                                        current_pos.1 - self.box_start.1 - self.box_snap.1;
+                                    let snap = state.snap_moving_rect(
+                                        (
+                                            box_min_x + offset_x,
+                                            box_min_y + offset_y,
+                                            box_max_x + offset_x,
+                                            box_max_y + offset_y,
+                                        ),
+                                        None,
+                                    );
+                                    self.box_snap = snap;
+
+                                    let (final_min_x, final_min_y, final_max_x, final_max_y) =
+                                        capture_size::clamp_to_screen(
+                                            (
+                                                box_min_x + offset_x + snap.0,
+                                                box_min_y + offset_y + snap.1,
+                                                box_max_x + offset_x + snap.0,
+                                                box_max_y + offset_y + snap.1,
+                                            ),
+                                            (state.size.width as f32, state.size.height as f32),
+                                        );
 
                                     self.current_box =
                                         Some((final_min_x, final_min_y, final_max_x, final_max_y));
                                     self.box_start = current_pos;
 
-                                    state.update_box(
-                                        final_min_x,
-                                        final_min_y,
-                                        final_max_x,
-                                        final_max_y,
+                                    state.drag_capture_box(
+                                        "Move selection",
+                                        (final_min_x, final_min_y, final_max_x, final_max_y),
                                     );
 
                                     state.window.request_redraw();
@@ -5175,16 +9687,42 @@ impl ApplicationHandler for App {
                                     }
                                 }
 
-                                let screen_width = state.size.width as f32;
-                                let screen_height = state.size.height as f32;
-
-                                min_x = min_x.max(0.0);
-                                min_y = min_y.max(0.0);
-                                max_x = max_x.min(screen_width);
-                                max_y = max_y.min(screen_height);
+                                // 🚀 限制在屏幕内；宽高比锁定时只移动正在拖动的边
+                                (min_x, min_y, max_x, max_y) = state.constrain_capture_box(
+                                    (min_x, min_y, max_x, max_y),
+                                    capture_size::Sides {
+                                        left: matches!(
+                                            handle,
+                                            ResizeHandle::TopLeft
+                                                | ResizeHandle::MiddleLeft
+                                                | ResizeHandle::BottomLeft
+                                        ),
+                                        top: matches!(
+                                            handle,
+                                            ResizeHandle::TopLeft
+                                                | ResizeHandle::TopCenter
+                                                | ResizeHandle::TopRight
+                                        ),
+                                        right: matches!(
+                                            handle,
+                                            ResizeHandle::TopRight
+                                                | ResizeHandle::MiddleRight
+                                                | ResizeHandle::BottomRight
+                                        ),
+                                        bottom: matches!(
+                                            handle,
+                                            ResizeHandle::BottomLeft
+                                                | ResizeHandle::BottomCenter
+                                                | ResizeHandle::BottomRight
+                                        ),
+                                    },
+                                );
 
                                 self.current_box = Some((min_x, min_y, max_x, max_y));
-                                state.update_box(min_x, min_y, max_x, max_y);
+                                state.drag_capture_box(
+                                    "Resize selection",
+                                    (min_x, min_y, max_x, max_y),
+                                );
 
                                 state.window.request_redraw();
                             }
@@ -5203,12 +9741,25 @@ impl ApplicationHandler for App {
                     state.modifiers = modifiers;
                 }
 
+                WindowEvent::DroppedFile(path) => {
+                    // 🚀 拖放图像文件：作为图像图层放在选区中央
+                    state.open_image_file(&path);
+                    state.window.request_redraw();
+                }
+
                 WindowEvent::Ime(ime) => {
                     // 🚀 处理IME文本输入事件
                     if state.text_input_active {
                         println!("🚀 收到IME事件: {:?}", ime);
                         state.handle_ime_event(&ime);
                         state.window.request_redraw();
+                    } else if state.current_tool == Tool::Stamp
+                        && let winit::event::Ime::Commit(text) = &ime
+                        && let Some(emoji) = stamp::emoji_from_input(text)
+                    {
+                        // 🚀 印章工具：输入法（或系统表情面板）提交的表情符号作为印章
+                        state.add_emoji_stamp(emoji);
+                        state.window.request_redraw();
                     }
                 }
 
@@ -5224,11 +9775,28 @@ impl ApplicationHandler for App {
                             state.window.request_redraw(); // 确保重绘
                             return;
                         }
+                        // 🚀 选区尺寸的数值输入
+                        if state.size_entry.is_some() {
+                            state.handle_size_entry_key(&event);
+                            state.window.request_redraw();
+                            return;
+                        }
 
                         // 检查修饰键状态
                         let ctrl_pressed = state.modifiers.state().control_key();
                         let shift_pressed = state.modifiers.state().shift_key();
 
+                        // 🚀 印章工具：直接输入的表情符号作为印章
+                        if state.current_tool == Tool::Stamp
+                            && !ctrl_pressed
+                            && let Some(emoji) =
+                                event.text.as_deref().and_then(stamp::emoji_from_input)
+                        {
+                            state.add_emoji_stamp(emoji);
+                            state.window.request_redraw();
+                            return;
+                        }
+
                         match event.physical_key {
                             PhysicalKey::Code(KeyCode::KeyZ) if ctrl_pressed && !shift_pressed => {
                                 // Ctrl+Z: 撤销
@@ -5242,12 +9810,88 @@ impl ApplicationHandler for App {
                                 // Ctrl+Shift+Z: 重做（备选快捷键）
                                 state.redo();
                             }
+                            PhysicalKey::Code(
+                                code @ (KeyCode::Digit1
+                                | KeyCode::Digit2
+                                | KeyCode::Digit3
+                                | KeyCode::Digit4
+                                | KeyCode::Digit5
+                                | KeyCode::Digit6
+                                | KeyCode::Digit7
+                                | KeyCode::Digit8),
+                            ) => {
+                                // 1-8: 选择调色板颜色（同时修改选中的元素）
+                                let palette_index = match code {
+                                    KeyCode::Digit1 => 0,
+                                    KeyCode::Digit2 => 1,
+                                    KeyCode::Digit3 => 2,
+                                    KeyCode::Digit4 => 3,
+                                    KeyCode::Digit5 => 4,
+                                    KeyCode::Digit6 => 5,
+                                    KeyCode::Digit7 => 6,
+                                    _ => 7,
+                                };
+                                state.set_current_color(COLOR_PALETTE[palette_index]);
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::KeyV) if ctrl_pressed => {
+                                // Ctrl+V: 粘贴剪贴板中的图像
+                                state.paste_image();
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::BracketRight) if ctrl_pressed => {
+                                // Ctrl+]: 选中元素置于顶层
+                                state.reorder_selected_element(true);
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::BracketLeft) if ctrl_pressed => {
+                                // Ctrl+[: 选中元素置于底层
+                                state.reorder_selected_element(false);
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::BracketLeft) => {
+                                // [: 减小线宽（橡皮擦工具下减小橡皮擦半径，选中图像时降低不透明度）
+                                if state.current_tool == Tool::Eraser {
+                                    state.adjust_eraser_radius(-ERASER_RADIUS_STEP);
+                                } else if !state.adjust_image_opacity(-IMAGE_OPACITY_STEP) {
+                                    state.adjust_current_thickness(-THICKNESS_STEP);
+                                }
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::BracketRight) => {
+                                // ]: 增大线宽（橡皮擦工具下增大橡皮擦半径，选中图像时提高不透明度）
+                                if state.current_tool == Tool::Eraser {
+                                    state.adjust_eraser_radius(ERASER_RADIUS_STEP);
+                                } else if !state.adjust_image_opacity(IMAGE_OPACITY_STEP) {
+                                    state.adjust_current_thickness(THICKNESS_STEP);
+                                }
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::KeyE)
+                                if state.current_tool == Tool::Eraser =>
+                            {
+                                // E: 橡皮擦工具下切换擦除模式（对象擦除 / 笔画擦除）
+                                state.toggle_eraser_mode();
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::KeyA) if !ctrl_pressed => {
+                                // A: 切换选区宽高比锁定（自由、1:1、4:3、16:9、当前比例）
+                                state.cycle_aspect_ratio();
+                                state.window.request_redraw();
+                            }
+                            PhysicalKey::Code(KeyCode::KeyP) if !ctrl_pressed => {
+                                // P / Shift+P: 切换到下一个 / 上一个固定尺寸预设
+                                state.cycle_capture_preset(!shift_pressed);
+                                state.window.request_redraw();
+                            }
                             PhysicalKey::Code(KeyCode::KeyR) => {
+                                let before = state.capture_region();
                                 self.box_created = false;
                                 self.current_box = None;
                                 self.drag_mode = DragMode::None;
                                 state.hide_toolbar();
                                 state.update_box(-1.0, -1.0, -1.0, -1.0);
+                                state.record_region_change("Reset selection", before);
                                 state.window.request_redraw();
                             }
                             PhysicalKey::Code(KeyCode::Escape) => event_loop.exit(),
@@ -5267,3 +9911,30 @@ fn main() {
     let mut app = App::default();
     event_loop.run_app(&mut app).unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotating_a_stamp_keeps_its_size() {
+        let mut element = DrawingElement::Stamp {
+            center: (100.0, 100.0),
+            size: 40.0,
+            rotation: 0.0,
+            stamp: Stamp::Emoji("⭐".to_string()),
+        };
+        // 旋转手柄在印章上方，离中心远于印章半边长
+        State::resize_stamp_element(&mut element, HandleType::Rotate, (100.0, 40.0));
+        assert!(matches!(element, DrawingElement::Stamp { size: 40.0, .. }));
+
+        State::resize_stamp_element(&mut element, HandleType::BottomRight, (130.0, 125.0));
+        assert!(matches!(element, DrawingElement::Stamp { size: 60.0, .. }));
+    }
+
+    #[test]
+    fn uniforms_match_the_shader_layout() {
+        // shader.wgsl 中的 Uniforms：33 个 f32，结构体大小按 16 字节对齐后为 144 字节
+        assert_eq!(std::mem::size_of::<Uniforms>(), 144);
+    }
+}
@@ -0,0 +1,132 @@
+//! Measure tool: distances between two points in screenshot pixels and the
+//! placement of the dimension label along the measured line.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+/// Snap `end` onto the horizontal or vertical line through `start`,
+/// whichever is closer to the pointer.
+pub fn constrain(start: (f32, f32), end: (f32, f32)) -> (f32, f32) {
+    if (end.0 - start.0).abs() >= (end.1 - start.1).abs() {
+        (end.0, start.1)
+    } else {
+        (start.0, end.1)
+    }
+}
+
+/// Horizontal and vertical extent in screenshot pixels; `scale` maps window
+/// pixels to screenshot pixels on each axis.
+pub fn extent(start: (f32, f32), end: (f32, f32), scale: (f32, f32)) -> (f32, f32) {
+    (
+        (end.0 - start.0).abs() * scale.0,
+        (end.1 - start.1).abs() * scale.1,
+    )
+}
+
+/// Length of the line in screenshot pixels.
+pub fn length(start: (f32, f32), end: (f32, f32), scale: (f32, f32)) -> f32 {
+    let (dx, dy) = extent(start, end, scale);
+    dx.hypot(dy)
+}
+
+/// The dimension label, e.g. "240 px".
+pub fn label(start: (f32, f32), end: (f32, f32), scale: (f32, f32)) -> String {
+    format!("{} px", length(start, end, scale).round() as u32)
+}
+
+/// The live readout: the length, plus the horizontal and vertical extent
+/// for lines that are neither horizontal nor vertical.
+pub fn readout(start: (f32, f32), end: (f32, f32), scale: (f32, f32)) -> String {
+    let (dx, dy) = extent(start, end, scale);
+    let (dx, dy) = (dx.round() as u32, dy.round() as u32);
+    let label = label(start, end, scale);
+    if dx == 0 || dy == 0 {
+        label
+    } else {
+        format!("{}\n{} × {}", label, dx, dy)
+    }
+}
+
+/// Angle of the label so that it runs along the line without ever being
+/// upside down.
+pub fn label_rotation(start: (f32, f32), end: (f32, f32)) -> f32 {
+    let angle = (end.1 - start.1).atan2(end.0 - start.0);
+    if angle > FRAC_PI_2 {
+        angle - PI
+    } else if angle <= -FRAC_PI_2 {
+        angle + PI
+    } else {
+        angle
+    }
+}
+
+/// Center of the label: `distance` above the middle of the line, "above"
+/// being relative to the label's rotation.
+pub fn label_center(start: (f32, f32), end: (f32, f32), distance: f32) -> (f32, f32) {
+    let (sin, cos) = label_rotation(start, end).sin_cos();
+    (
+        (start.0 + end.0) / 2.0 + sin * distance,
+        (start.1 + end.1) / 2.0 - cos * distance,
+    )
+}
+
+/// The two end ticks, perpendicular to the line and `half_length` to either
+/// side of it.
+pub fn end_ticks(
+    start: (f32, f32),
+    end: (f32, f32),
+    half_length: f32,
+) -> [((f32, f32), (f32, f32)); 2] {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = dx.hypot(dy);
+    let (nx, ny) = if length > 0.0 {
+        (-dy / length * half_length, dx / length * half_length)
+    } else {
+        (0.0, half_length)
+    };
+    [start, end].map(|(x, y)| ((x - nx, y - ny), (x + nx, y + ny)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 1e-4 && (a.1 - b.1).abs() < 1e-4
+    }
+
+    #[test]
+    fn constrain_follows_the_dominant_axis() {
+        assert_eq!(constrain((10.0, 10.0), (50.0, 22.0)), (50.0, 10.0));
+        assert_eq!(constrain((10.0, 10.0), (4.0, -30.0)), (10.0, -30.0));
+    }
+
+    #[test]
+    fn lengths_are_in_screenshot_pixels() {
+        // 窗口缩小一半显示截图：窗口中的 30×40 对应截图中的 60×80
+        let scale = (2.0, 2.0);
+        assert_eq!(length((0.0, 0.0), (30.0, 40.0), scale), 100.0);
+        assert_eq!(label((0.0, 0.0), (30.0, 40.0), scale), "100 px");
+        assert_eq!(readout((0.0, 0.0), (30.0, 40.0), scale), "100 px\n60 × 80");
+        assert_eq!(readout((5.0, 5.0), (5.0, 125.4), (1.0, 1.0)), "120 px");
+    }
+
+    #[test]
+    fn label_stays_upright_above_the_line() {
+        // 从右往左画的水平线：文字不倒置，位于线的上方
+        assert_eq!(label_rotation((100.0, 0.0), (0.0, 0.0)), 0.0);
+        assert!(close(
+            label_center((100.0, 50.0), (0.0, 50.0), 10.0),
+            (50.0, 40.0)
+        ));
+        // 竖线：无论方向，文字都从上往下读，位于线的右侧
+        assert_eq!(label_rotation((0.0, 100.0), (0.0, 0.0)), FRAC_PI_2);
+        assert!(close(
+            label_center((0.0, 100.0), (0.0, 0.0), 10.0),
+            (10.0, 50.0)
+        ));
+
+        let [start_tick, end_tick] = end_ticks((0.0, 0.0), (100.0, 0.0), 5.0);
+        assert!(close(start_tick.0, (0.0, -5.0)) && close(start_tick.1, (0.0, 5.0)));
+        assert!(close(end_tick.0, (100.0, -5.0)) && close(end_tick.1, (100.0, 5.0)));
+    }
+}
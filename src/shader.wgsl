@@ -22,6 +22,12 @@ struct Uniforms {
     show_handles: f32,           // 4 bytes (索引27) - 是否显示手柄
     // 🚀 撤销按钮状态
     undo_button_enabled: f32,    // 4 bytes (索引28) - 撤销按钮是否启用
+    undo_button_index: f32,      // 4 bytes (索引29) - 撤销按钮在工具栏中的索引
+    // 🚀 HiDPI
+    ui_scale: f32,               // 4 bytes (索引30) - 逻辑UI单位到物理像素的缩放系数
+    // 🚀 重做按钮状态
+    redo_button_enabled: f32,    // 4 bytes (索引31) - 重做按钮是否启用
+    redo_button_index: f32,      // 4 bytes (索引32) - 重做按钮在工具栏中的索引
 }
 
 @group(0) @binding(0)
@@ -42,6 +48,11 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
 }
+struct TexturedVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+    @location(1) opacity: f32,
+}
 struct DrawingVertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
@@ -74,43 +85,21 @@ fn vs_icon(vertex: VertexInput) -> VertexOutput {
     out.tex_coords = vertex.position.zw;
     return out;
 }
-// 🔧 GPU优化：使用compute shader优化的绘图片段着色器
+// 🚀 纹理四边形（旋转文本、印章、图像图层）：位置和纹理坐标之外还有不透明度
+@vertex
+fn vs_textured(@location(0) position: vec4<f32>, @location(1) opacity: f32) -> TexturedVertexOutput {
+    var out: TexturedVertexOutput;
+    out.clip_position = vec4<f32>(position.xy, 0.0, 1.0);
+    out.tex_coords = position.zw;
+    out.opacity = opacity;
+    return out;
+}
+// 绘图片段着色器：笔画的简化和平滑在CPU端完成（见 stroke.rs），这里直接输出顶点颜色
 @fragment
 fn fs_drawing(in: DrawingVertexOutput) -> @location(0) vec4<f32> {
-    // 直接返回颜色，compute shader已经处理了复杂计算
     return in.color;
 }
 
-// 🔧 GPU优化：添加compute shader支持的存储缓冲区结构
-struct PenPointData {
-    position: vec2<f32>,
-    color: vec4<f32>,
-    thickness: f32,
-    _padding: f32, // 对齐到16字节
-}
-
-// 🔧 GPU优化：画笔点存储缓冲区（参考您的模式）
-@group(1) @binding(0)
-var<storage, read_write> pen_points: array<PenPointData>;
-
-// 🔧 GPU优化：画笔处理的compute shader
-@compute @workgroup_size(64, 1, 1)
-fn cs_process_pen_points(@builtin(global_invocation_id) global_id: vec3<u32>) {
-    let index = global_id.x;
-
-    // 边界检查
-    if index >= arrayLength(&pen_points) {
-        return;
-    }
-
-    // 简单的点处理 - 可以在这里添加更复杂的优化
-    // 例如：距离过滤、平滑处理等
-    let point = pen_points[index];
-
-    // 这里可以添加GPU并行的点处理逻辑
-    // 目前保持简单，直接传递数据
-    pen_points[index] = point;
-}
 // 🚀 背景缓存系统 - 智能缓存管理
 var<private> cached_background_state: vec4<f32> = vec4<f32>(-1.0); // x: box_hash, y: toolbar_state, z: cache_valid, w: reserved
 var<private> cached_background_color: vec4<f32> = vec4<f32>(0.0);  // 缓存的背景颜色
@@ -254,13 +243,13 @@ fn calculate_toolbar_layout() -> vec4<f32> {
     // 重新计算并缓存
     let toolbar_width = uniforms.toolbar_button_count * uniforms.toolbar_button_size + (uniforms.toolbar_button_count - 1.0) * uniforms.toolbar_button_margin;
 
-    var toolbar_y = uniforms.box_max.y + 5.0;
+    var toolbar_y = uniforms.box_max.y + 5.0 * uniforms.ui_scale;
     let toolbar_bottom = toolbar_y + uniforms.toolbar_height;
 
     if toolbar_bottom > uniforms.screen_size.y {
-        toolbar_y = uniforms.box_min.y - uniforms.toolbar_height - 10.0;
+        toolbar_y = uniforms.box_min.y - uniforms.toolbar_height - 10.0 * uniforms.ui_scale;
         if toolbar_y < 0.0 {
-            toolbar_y = 10.0;
+            toolbar_y = 10.0 * uniforms.ui_scale;
         }
     }
 
@@ -330,14 +319,15 @@ fn fs_icon(in: VertexOutput) -> @location(0) vec4<f32> {
     let is_selected = abs(uniforms.selected_button - button_index) < 0.5;
     let is_hovered = abs(uniforms.hovered_button - button_index) < 0.5;
 
-    // 🚀 检查是否是撤销按钮（索引5）
-    let is_undo_button = abs(button_index - 5.0) < 0.5;
+    // 🚀 检查是否是撤销/重做按钮
+    let is_undo_button = abs(button_index - uniforms.undo_button_index) < 0.5;
+    let is_redo_button = abs(button_index - uniforms.redo_button_index) < 0.5;
 
-    if is_undo_button {
-        // 🚀 使用专门的uniform来判断撤销按钮状态
-        let undo_enabled = uniforms.undo_button_enabled > 0.5;
+    if is_undo_button || is_redo_button {
+        // 🚀 使用专门的uniform来判断撤销/重做按钮状态
+        let history_enabled = select(uniforms.redo_button_enabled, uniforms.undo_button_enabled, is_undo_button) > 0.5;
 
-        if undo_enabled {
+        if history_enabled {
             // 🚀 启用状态：正常显示
             if is_hovered {
                 // 悬停时稍微增加亮度
@@ -393,6 +383,12 @@ fn fs_icon(in: VertexOutput) -> @location(0) vec4<f32> {
     return icon_color;
 }
 
+// 🚀 带纹理的四边形，纹理内容为预乘alpha，整体乘以不透明度即可
+@fragment
+fn fs_textured(in: TexturedVertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_texture, s_sampler, in.tex_coords) * in.opacity;
+}
+
 // 🚀 背景缓存着色器 - 专门用于渲染和缓存背景
 @fragment
 fn fs_background_cache(in: VertexOutput) -> @location(0) vec4<f32> {
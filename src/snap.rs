@@ -0,0 +1,143 @@
+//! Snapping of dragged rectangles to alignment targets (edges and centers
+//! of other rectangles) and to a grid, with the guide lines to draw.
+
+/// A rectangle as (left, top, right, bottom).
+pub type Rect = (f32, f32, f32, f32);
+
+/// Vertical (x) and horizontal (y) lines a dragged rectangle can snap to.
+#[derive(Debug, Default)]
+pub struct Targets {
+    pub xs: Vec<f32>,
+    pub ys: Vec<f32>,
+}
+
+impl Targets {
+    /// Add the edges and center of a rectangle.
+    pub fn add_rect(&mut self, (left, top, right, bottom): Rect) {
+        self.xs.extend([left, (left + right) / 2.0, right]);
+        self.ys.extend([top, (top + bottom) / 2.0, bottom]);
+    }
+}
+
+/// Grid lines every `size` pixels, starting at `origin`.
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pub size: f32,
+    pub origin: (f32, f32),
+}
+
+/// An alignment guide line shown while dragging.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Guide {
+    Vertical(f32),
+    Horizontal(f32),
+}
+
+/// Offset that moves `rect` onto the nearest target or grid line on each
+/// axis (only when within `threshold`), and the target lines it then
+/// touches. Targets win over the grid at equal distance; grid snaps draw
+/// no guides.
+pub fn snap_rect(
+    rect: Rect,
+    targets: &Targets,
+    grid: Option<Grid>,
+    threshold: f32,
+) -> ((f32, f32), Vec<Guide>) {
+    let (left, top, right, bottom) = rect;
+    let xs = [left, (left + right) / 2.0, right];
+    let ys = [top, (top + bottom) / 2.0, bottom];
+    let dx = snap_axis(
+        &xs,
+        &targets.xs,
+        grid.map(|g| (g.size, g.origin.0)),
+        threshold,
+    );
+    let dy = snap_axis(
+        &ys,
+        &targets.ys,
+        grid.map(|g| (g.size, g.origin.1)),
+        threshold,
+    );
+
+    let mut guides = Vec::new();
+    for (points, lines, delta, guide) in [
+        (xs, &targets.xs, dx, Guide::Vertical as fn(f32) -> Guide),
+        (ys, &targets.ys, dy, Guide::Horizontal),
+    ] {
+        for line in lines {
+            if points.iter().any(|p| (p + delta - line).abs() < 0.5)
+                && !guides.contains(&guide(*line))
+            {
+                guides.push(guide(*line));
+            }
+        }
+    }
+    ((dx, dy), guides)
+}
+
+// 一个方向上的吸附修正量：取距离最近且不超过阈值的目标线或网格线
+fn snap_axis(points: &[f32], lines: &[f32], grid: Option<(f32, f32)>, threshold: f32) -> f32 {
+    let mut best: Option<f32> = None;
+    let mut consider = |delta: f32| {
+        if delta.abs() <= threshold && best.is_none_or(|b| delta.abs() < b.abs()) {
+            best = Some(delta);
+        }
+    };
+    for point in points {
+        for line in lines {
+            consider(line - point);
+        }
+    }
+    if let Some((size, origin)) = grid.filter(|(size, _)| *size > 0.0) {
+        for point in points {
+            consider(origin + ((point - origin) / size).round() * size - point);
+        }
+    }
+    best.unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(rects: &[Rect]) -> Targets {
+        let mut targets = Targets::default();
+        for rect in rects {
+            targets.add_rect(*rect);
+        }
+        targets
+    }
+
+    #[test]
+    fn snaps_edges_within_threshold_only() {
+        let targets = targets(&[(100.0, 100.0, 200.0, 200.0)]);
+        // 左边缘距离目标右边缘 3 像素，上边缘离所有目标都超过阈值
+        let (offset, guides) = snap_rect((203.0, 20.0, 253.0, 50.0), &targets, None, 5.0);
+        assert_eq!(offset, (-3.0, 0.0));
+        assert_eq!(guides, vec![Guide::Vertical(200.0)]);
+    }
+
+    #[test]
+    fn centers_align_with_centers() {
+        let targets = targets(&[(0.0, 0.0, 100.0, 100.0)]);
+        let (offset, guides) = snap_rect((38.0, 200.0, 58.0, 220.0), &targets, None, 5.0);
+        assert_eq!(offset, (2.0, 0.0));
+        assert_eq!(guides, vec![Guide::Vertical(50.0)]);
+    }
+
+    #[test]
+    fn grid_snaps_without_guides_and_targets_win_ties() {
+        let grid = Some(Grid {
+            size: 10.0,
+            origin: (5.0, 0.0),
+        });
+        let (offset, guides) = snap_rect((17.0, 21.0, 37.0, 41.0), &Targets::default(), grid, 4.0);
+        assert_eq!(offset, (-2.0, -1.0));
+        assert!(guides.is_empty());
+
+        let targets = targets(&[(19.0, 0.0, 19.0, 0.0)]);
+        let (offset, guides) = snap_rect((17.0, 21.0, 37.0, 41.0), &targets, grid, 4.0);
+        assert_eq!(offset.0, 2.0);
+        assert!(guides.contains(&Guide::Vertical(19.0)));
+    }
+}
@@ -0,0 +1,178 @@
+//! Stamp palette for the Stamp tool: built-in SVG icons, SVG files from the
+//! configured stamp directory and emoji typed while the tool is active.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+// 内置印章：对勾、叉号、警告、问号、点赞
+const CHECK_SVG: &str = concat!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">",
+    "<circle cx=\"12\" cy=\"12\" r=\"11\" fill=\"#22c55e\"/>",
+    "<path d=\"M7 12.5l3.2 3.2L17 9\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"2.4\" ",
+    "stroke-linecap=\"round\" stroke-linejoin=\"round\"/></svg>"
+);
+
+const CROSS_SVG: &str = concat!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">",
+    "<circle cx=\"12\" cy=\"12\" r=\"11\" fill=\"#ef4444\"/>",
+    "<path d=\"M8 8l8 8M16 8l-8 8\" fill=\"none\" stroke=\"#ffffff\" stroke-width=\"2.4\" ",
+    "stroke-linecap=\"round\"/></svg>"
+);
+
+const WARNING_SVG: &str = concat!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">",
+    "<path d=\"M12 2.5L22.5 21h-21z\" fill=\"#f59e0b\" stroke=\"#f59e0b\" stroke-width=\"1.5\" ",
+    "stroke-linejoin=\"round\"/>",
+    "<path d=\"M12 9v5\" stroke=\"#ffffff\" stroke-width=\"2.4\" stroke-linecap=\"round\"/>",
+    "<circle cx=\"12\" cy=\"17.5\" r=\"1.3\" fill=\"#ffffff\"/></svg>"
+);
+
+const QUESTION_SVG: &str = concat!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\">",
+    "<circle cx=\"12\" cy=\"12\" r=\"11\" fill=\"#3b82f6\"/>",
+    "<path d=\"M9.2 9.2a2.9 2.9 0 1 1 4.3 2.5c-.9.5-1.5 1.1-1.5 2.1v.6\" fill=\"none\" ",
+    "stroke=\"#ffffff\" stroke-width=\"2.2\" stroke-linecap=\"round\"/>",
+    "<circle cx=\"12\" cy=\"17.6\" r=\"1.3\" fill=\"#ffffff\"/></svg>"
+);
+
+const THUMBS_UP_SVG: &str = concat!(
+    "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"24\" height=\"24\" viewBox=\"0 0 24 24\" ",
+    "fill=\"#fbbf24\" stroke=\"#92400e\" stroke-width=\"1.5\" stroke-linejoin=\"round\">",
+    "<path d=\"M7 10v12\"/>",
+    "<path d=\"M15 5.88 14 10h5.83a2 2 0 0 1 1.92 2.56l-2.33 8A2 2 0 0 1 17.5 22H4a2 2 0 0 1-2-2",
+    "v-8a2 2 0 0 1 2-2h2.76a2 2 0 0 0 1.79-1.11L12 2a3.13 3.13 0 0 1 3 3.88Z\"/></svg>"
+);
+
+const BUILTIN_STAMPS: [(&str, &str); 5] = [
+    ("check", CHECK_SVG),
+    ("cross", CROSS_SVG),
+    ("warning", WARNING_SVG),
+    ("question", QUESTION_SVG),
+    ("thumbs-up", THUMBS_UP_SVG),
+];
+
+/// One entry of the stamp palette, stored in every element placed from it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stamp {
+    /// SVG icon, rasterized with resvg. Elements share the SVG source.
+    Svg { name: String, data: Arc<str> },
+    /// A single grapheme drawn through glyphon's color atlas.
+    Emoji(String),
+}
+
+impl Stamp {
+    /// Cache key of the stamp image (without the raster size).
+    pub fn key(&self) -> String {
+        match self {
+            Stamp::Svg { name, .. } => format!("svg:{name}"),
+            Stamp::Emoji(emoji) => format!("emoji:{emoji}"),
+        }
+    }
+}
+
+/// Built-in stamps followed by the `*.svg` files of `directory`, sorted by
+/// file name. Files that are not valid SVG are skipped.
+pub fn load_palette(directory: Option<&Path>) -> Vec<Stamp> {
+    let mut palette: Vec<Stamp> = BUILTIN_STAMPS
+        .iter()
+        .map(|(name, data)| Stamp::Svg {
+            name: name.to_string(),
+            data: Arc::from(*data),
+        })
+        .collect();
+
+    let Some(directory) = directory else {
+        return palette;
+    };
+    let entries = match std::fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("无法读取印章目录 {}: {}", directory.display(), e);
+            return palette;
+        }
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+        })
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("无法读取印章 {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        // 提前解析一次，避免光栅化时才发现文件无效
+        if let Err(e) = usvg::Tree::from_str(&data, &usvg::Options::default()) {
+            eprintln!("印章 {} 不是有效的SVG: {}", path.display(), e);
+            continue;
+        }
+        // 使用完整文件名，避免与内置印章重名
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        println!("🚀 加载印章: {}", name);
+        palette.push(Stamp::Svg {
+            name,
+            data: Arc::from(data),
+        });
+    }
+    palette
+}
+
+/// The emoji to stamp for text typed while the Stamp tool is active: the
+/// first grapheme, ignoring whitespace. Plain ASCII is not an emoji (those
+/// keys are shortcuts), so it yields `None`.
+pub fn emoji_from_input(text: &str) -> Option<String> {
+    let grapheme = text.trim().graphemes(true).next()?;
+    if grapheme.is_ascii() || grapheme.chars().any(char::is_control) {
+        return None;
+    }
+    Some(grapheme.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_stamps_are_valid_svg() {
+        for (name, data) in BUILTIN_STAMPS {
+            assert!(
+                usvg::Tree::from_str(data, &usvg::Options::default()).is_ok(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn palette_without_directory_has_builtins() {
+        let palette = load_palette(None);
+        assert_eq!(palette.len(), BUILTIN_STAMPS.len());
+        assert_eq!(palette[0].key(), "svg:check");
+    }
+
+    #[test]
+    fn emoji_input_takes_first_grapheme() {
+        assert_eq!(emoji_from_input("👍"), Some("👍".to_string()));
+        assert_eq!(emoji_from_input(" 👍🏽x"), Some("👍🏽".to_string()));
+        assert_eq!(emoji_from_input("👨‍👩‍👧"), Some("👨‍👩‍👧".to_string()));
+    }
+
+    #[test]
+    fn ascii_and_control_input_is_not_an_emoji() {
+        assert_eq!(emoji_from_input("a"), None);
+        assert_eq!(emoji_from_input("1"), None);
+        assert_eq!(emoji_from_input("\r"), None);
+        assert_eq!(emoji_from_input(""), None);
+    }
+}
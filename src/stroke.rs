@@ -0,0 +1,529 @@
+//! 画笔笔画处理：Ramer–Douglas–Peucker 简化 + Catmull-Rom 平滑
+
+/// 平滑后相邻采样点的目标间距（像素），用于决定每段曲线的细分数量
+const SMOOTH_SAMPLE_SPACING: f32 = 3.0;
+/// 每段曲线最多细分的数量，防止长直线段产生过多点
+const MAX_SEGMENT_SUBDIVISIONS: usize = 16;
+/// 控制点预平滑的最大迭代次数（strength = 1.0 时）
+const MAX_RELAX_ITERATIONS: usize = 3;
+
+/// A polyline together with its per-point widths (empty for constant width).
+pub type StrokePiece = (Vec<(f32, f32)>, Vec<f32>);
+
+/// Distance from `p` to the segment `a`-`b`.
+pub fn point_segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq > f32::EPSILON {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (a.0 + dx * t, a.1 + dy * t);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+/// Simplify a polyline with the Ramer–Douglas–Peucker algorithm and return
+/// the indices of the points to keep.
+///
+/// Points closer than `tolerance` pixels to the simplified line are dropped;
+/// the first and last points are always kept.
+pub fn simplify_indices(points: &[(f32, f32)], tolerance: f32) -> Vec<usize> {
+    if points.len() < 3 || tolerance <= 0.0 {
+        return (0..points.len()).collect();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // 使用显式栈代替递归，避免超长笔画导致栈溢出
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        if last <= first + 1 {
+            continue;
+        }
+
+        let mut max_dist = 0.0;
+        let mut max_index = first;
+        for (i, &p) in points.iter().enumerate().take(last).skip(first + 1) {
+            let dist = point_segment_distance(p, points[first], points[last]);
+            if dist > max_dist {
+                max_dist = dist;
+                max_index = i;
+            }
+        }
+
+        if max_dist > tolerance {
+            keep[max_index] = true;
+            stack.push((first, max_index));
+            stack.push((max_index, last));
+        }
+    }
+
+    keep.iter()
+        .enumerate()
+        .filter_map(|(i, &k)| k.then_some(i))
+        .collect()
+}
+
+/// Smooth a polyline with a Catmull-Rom spline.
+///
+/// `strength` ranges from 0.0 (no smoothing, points returned unchanged) to
+/// 1.0 (strongest). It controls how much the control points are relaxed
+/// towards their neighbours before the spline is sampled. Endpoints are
+/// always preserved.
+///
+/// `widths` holds optional per-point stroke widths (empty for constant
+/// width strokes); they are relaxed and resampled along with the points.
+pub fn smooth(points: &[(f32, f32)], widths: &[f32], strength: f32) -> StrokePiece {
+    let strength = strength.clamp(0.0, 1.0);
+    let has_widths = widths.len() == points.len();
+    if points.len() < 3 || strength <= 0.0 {
+        let widths = if has_widths {
+            widths.to_vec()
+        } else {
+            Vec::new()
+        };
+        return (points.to_vec(), widths);
+    }
+
+    // 🚀 先对控制点做松弛，去掉鼠标抖动造成的锯齿（压感宽度同样处理，去掉压力抖动）
+    let control = relax(points, widths, strength);
+
+    // 🚀 再用 Catmull-Rom 样条穿过控制点采样出平滑曲线，宽度沿线段线性插值
+    let n = control.len();
+    let mut result = Vec::with_capacity(n * 4);
+    let mut result_widths = Vec::with_capacity(if has_widths { n * 4 } else { 0 });
+    result.push((control[0][0], control[0][1]));
+    if has_widths {
+        result_widths.push(control[0][2]);
+    }
+    for i in 0..n - 1 {
+        sample_segment(
+            &control,
+            i,
+            &mut result,
+            has_widths.then_some(&mut result_widths),
+        );
+    }
+    (result, result_widths)
+}
+
+/// Incremental [`smooth`] for a stroke that is still being drawn.
+///
+/// Appending a point only moves the last few control points, so the samples
+/// of the segments before them are kept and only the tail is re-smoothed.
+#[derive(Debug, Default)]
+pub struct LiveSmoother {
+    /// 不会再变化的线段数量，以及它们在输出中占用的采样点数量
+    frozen_segments: usize,
+    frozen_len: usize,
+    with_widths: bool,
+}
+
+impl LiveSmoother {
+    /// Bring `smoothed` and `smoothed_widths`, the output of the previous
+    /// calls, up to date with `points` and `widths` after points were
+    /// appended. The result equals `smooth(points, widths, strength)`.
+    pub fn update(
+        &mut self,
+        points: &[(f32, f32)],
+        widths: &[f32],
+        strength: f32,
+        smoothed: &mut Vec<(f32, f32)>,
+        smoothed_widths: &mut Vec<f32>,
+    ) {
+        let strength = strength.clamp(0.0, 1.0);
+        let has_widths = widths.len() == points.len();
+        // 压感数据中途消失时，已固定的部分也需要重新生成
+        if points.len() < 3 || strength <= 0.0 || has_widths != self.with_widths {
+            *self = Self {
+                with_widths: has_widths,
+                ..Self::default()
+            };
+        }
+        if points.len() < 3 || strength <= 0.0 {
+            (*smoothed, *smoothed_widths) = smooth(points, widths, strength);
+            return;
+        }
+
+        // 🚀 松弛最多影响前后 MAX_RELAX_ITERATIONS 个控制点，样条线段还会用到前一个控制点，
+        // 所以只需从已固定部分之前几个点开始重新平滑
+        let start = self
+            .frozen_segments
+            .saturating_sub(MAX_RELAX_ITERATIONS + 1);
+        let window_widths = if has_widths { &widths[start..] } else { &[] };
+        let control = relax(&points[start..], window_widths, strength);
+        // 线段 i 用到控制点 i-1..=i+2，末端点继续追加时它们都不会再移动
+        let stable = points
+            .len()
+            .saturating_sub(MAX_RELAX_ITERATIONS + 3)
+            .max(self.frozen_segments);
+
+        smoothed.truncate(self.frozen_len);
+        smoothed_widths.truncate(if has_widths { self.frozen_len } else { 0 });
+        if self.frozen_segments == 0 {
+            smoothed.push((control[0][0], control[0][1]));
+            if has_widths {
+                smoothed_widths.push(control[0][2]);
+            }
+        }
+        for i in self.frozen_segments..points.len() - 1 {
+            sample_segment(
+                &control,
+                i - start,
+                smoothed,
+                has_widths.then_some(&mut *smoothed_widths),
+            );
+            if i + 1 == stable {
+                self.frozen_segments = stable;
+                self.frozen_len = smoothed.len();
+            }
+        }
+    }
+}
+
+/// Relax the control points, and their widths when present, towards their
+/// neighbours to remove jitter; the endpoints stay in place.
+fn relax(points: &[(f32, f32)], widths: &[f32], strength: f32) -> Vec<[f32; 3]> {
+    let has_widths = widths.len() == points.len();
+    let mut control: Vec<[f32; 3]> = points
+        .iter()
+        .enumerate()
+        .map(|(i, &(x, y))| [x, y, if has_widths { widths[i] } else { 0.0 }])
+        .collect();
+    if control.len() < 3 {
+        return control;
+    }
+    let iterations = ((strength * MAX_RELAX_ITERATIONS as f32).ceil() as usize).max(1);
+    let weight = 0.5 * strength;
+    for _ in 0..iterations {
+        let prev = control.clone();
+        for i in 1..prev.len() - 1 {
+            for axis in 0..3 {
+                let avg = (prev[i - 1][axis] + prev[i + 1][axis]) * 0.5;
+                control[i][axis] += (avg - prev[i][axis]) * weight;
+            }
+        }
+    }
+    control
+}
+
+/// Append the Catmull-Rom samples of the segment from `control[i]` to
+/// `control[i + 1]`, without its start point, and their widths to `widths`.
+fn sample_segment(
+    control: &[[f32; 3]],
+    i: usize,
+    out: &mut Vec<(f32, f32)>,
+    mut widths: Option<&mut Vec<f32>>,
+) {
+    let n = control.len();
+    let p0 = control[i.saturating_sub(1)];
+    let p1 = control[i];
+    let p2 = control[i + 1];
+    let p3 = control[(i + 2).min(n - 1)];
+
+    let seg_len = ((p2[0] - p1[0]).powi(2) + (p2[1] - p1[1]).powi(2)).sqrt();
+    let steps =
+        ((seg_len / SMOOTH_SAMPLE_SPACING).ceil() as usize).clamp(1, MAX_SEGMENT_SUBDIVISIONS);
+    for step in 1..=steps {
+        let t = step as f32 / steps as f32;
+        out.push(catmull_rom(
+            (p0[0], p0[1]),
+            (p1[0], p1[1]),
+            (p2[0], p2[1]),
+            (p3[0], p3[1]),
+            t,
+        ));
+        if let Some(widths) = widths.as_deref_mut() {
+            widths.push(p1[2] + (p2[2] - p1[2]) * t);
+        }
+    }
+}
+
+/// Simplify then smooth a raw pen stroke, keeping per-point widths in sync.
+pub fn process(
+    points: &[(f32, f32)],
+    widths: &[f32],
+    tolerance: f32,
+    strength: f32,
+) -> StrokePiece {
+    let kept = simplify_indices(points, tolerance);
+    let simplified: Vec<(f32, f32)> = kept.iter().map(|&i| points[i]).collect();
+    let simplified_widths: Vec<f32> = if widths.len() == points.len() {
+        kept.iter().map(|&i| widths[i]).collect()
+    } else {
+        Vec::new()
+    };
+    smooth(&simplified, &simplified_widths, strength)
+}
+
+fn catmull_rom(
+    p0: (f32, f32),
+    p1: (f32, f32),
+    p2: (f32, f32),
+    p3: (f32, f32),
+    t: f32,
+) -> (f32, f32) {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let eval = |a: f32, b: f32, c: f32, d: f32| {
+        0.5 * (2.0 * b
+            + (c - a) * t
+            + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2
+            + (3.0 * b - a - 3.0 * c + d) * t3)
+    };
+    (eval(p0.0, p1.0, p2.0, p3.0), eval(p0.1, p1.1, p2.1, p3.1))
+}
+
+/// Remove the parts of a polyline that fall inside the circle at `center`.
+///
+/// Returns `None` when the circle does not touch the polyline, otherwise the
+/// remaining pieces, each paired with its per-point widths (empty when the
+/// input has no widths). Pieces shorter than two points are dropped.
+pub fn erase_from_polyline(
+    points: &[(f32, f32)],
+    widths: &[f32],
+    center: (f32, f32),
+    radius: f32,
+) -> Option<Vec<StrokePiece>> {
+    let inside =
+        |p: (f32, f32)| (p.0 - center.0).powi(2) + (p.1 - center.1).powi(2) <= radius * radius;
+    match points {
+        [] => return None,
+        [single] => return inside(*single).then(Vec::new),
+        _ => {}
+    }
+
+    let has_widths = widths.len() == points.len();
+    let width_at = |i: usize| if has_widths { widths[i] } else { 0.0 };
+
+    let mut pieces = Vec::new();
+    let mut current: Vec<(f32, f32)> = Vec::new();
+    let mut current_widths: Vec<f32> = Vec::new();
+    let mut touched = false;
+
+    let mut flush = |current: &mut Vec<(f32, f32)>, current_widths: &mut Vec<f32>| {
+        if current.len() >= 2 {
+            let piece_widths = if has_widths {
+                std::mem::take(current_widths)
+            } else {
+                Vec::new()
+            };
+            pieces.push((std::mem::take(current), piece_widths));
+        }
+        current.clear();
+        current_widths.clear();
+    };
+
+    for i in 0..points.len() - 1 {
+        let (a, b) = (points[i], points[i + 1]);
+        let (wa, wb) = (width_at(i), width_at(i + 1));
+        let lerp = |t: f32| {
+            (
+                (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t),
+                wa + (wb - wa) * t,
+            )
+        };
+
+        match segment_circle_overlap(a, b, center, radius) {
+            None => {
+                if current.is_empty() {
+                    current.push(a);
+                    current_widths.push(wa);
+                }
+                current.push(b);
+                current_widths.push(wb);
+            }
+            Some((t_in, t_out)) => {
+                touched = true;
+                // 圆外的前半段保留，圆内部分丢弃，圆外的后半段开始新的一段
+                if t_in > 0.0 {
+                    if current.is_empty() {
+                        current.push(a);
+                        current_widths.push(wa);
+                    }
+                    let (p, w) = lerp(t_in);
+                    current.push(p);
+                    current_widths.push(w);
+                }
+                flush(&mut current, &mut current_widths);
+                if t_out < 1.0 {
+                    let (p, w) = lerp(t_out);
+                    current.push(p);
+                    current_widths.push(w);
+                    current.push(b);
+                    current_widths.push(wb);
+                }
+            }
+        }
+    }
+    flush(&mut current, &mut current_widths);
+
+    touched.then_some(pieces)
+}
+
+/// Parameter range `[t_in, t_out]` (within 0..=1) of the segment `a`-`b`
+/// that lies inside the circle, or `None` if they don't overlap.
+fn segment_circle_overlap(
+    a: (f32, f32),
+    b: (f32, f32),
+    center: (f32, f32),
+    radius: f32,
+) -> Option<(f32, f32)> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (fx, fy) = (a.0 - center.0, a.1 - center.1);
+    let qa = dx * dx + dy * dy;
+    let qc = fx * fx + fy * fy - radius * radius;
+    if qa <= f32::EPSILON {
+        return (qc <= 0.0).then_some((0.0, 1.0));
+    }
+
+    let qb = 2.0 * (fx * dx + fy * dy);
+    let discriminant = qb * qb - 4.0 * qa * qc;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrt_d = discriminant.sqrt();
+    let t_in = ((-qb - sqrt_d) / (2.0 * qa)).max(0.0);
+    let t_out = ((-qb + sqrt_d) / (2.0 * qa)).min(1.0);
+    (t_in < t_out).then_some((t_in, t_out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify_keeps_endpoints_and_points_outside_tolerance() {
+        // 第2个点偏离 0.5 像素（容差内），第4个点偏离 5 像素（容差外）
+        let points = [
+            (0.0, 0.0),
+            (10.0, 0.5),
+            (20.0, 0.0),
+            (30.0, 5.0),
+            (40.0, 0.0),
+        ];
+        assert_eq!(simplify_indices(&points, 1.0), vec![0, 2, 3, 4]);
+
+        // 直线上的点全部丢弃，只剩两个端点
+        let line = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)];
+        assert_eq!(simplify_indices(&line, 0.1), vec![0, 3]);
+    }
+
+    #[test]
+    fn zero_strength_returns_input_unchanged() {
+        let points = vec![(0.0, 0.0), (5.0, 8.0), (10.0, 0.0), (15.0, 8.0)];
+        let widths = vec![1.0, 2.0, 3.0, 4.0];
+        assert_eq!(
+            smooth(&points, &widths, 0.0),
+            (points.clone(), widths.clone())
+        );
+    }
+
+    #[test]
+    fn smoothing_keeps_endpoints_and_widths_in_sync() {
+        let points: Vec<(f32, f32)> = (0..20)
+            .map(|i| (i as f32 * 4.0, if i % 2 == 0 { 0.0 } else { 3.0 }))
+            .collect();
+        let widths: Vec<f32> = (0..20).map(|i| 1.0 + i as f32 * 0.1).collect();
+
+        let (smoothed, smoothed_widths) = process(&points, &widths, 1.0, 1.0);
+        assert_eq!(smoothed.len(), smoothed_widths.len());
+        assert_eq!(smoothed.first(), points.first());
+        assert_eq!(smoothed.last(), points.last());
+        assert!(smoothed.len() > 2);
+
+        // 没有压感宽度的笔画保持为空
+        let (smoothed, smoothed_widths) = process(&points, &[], 1.0, 0.5);
+        assert!(!smoothed.is_empty());
+        assert!(smoothed_widths.is_empty());
+    }
+
+    #[test]
+    fn live_smoothing_matches_smoothing_the_whole_stroke() {
+        let points: Vec<(f32, f32)> = (0..60)
+            .map(|i| {
+                let t = i as f32 * 0.3;
+                (
+                    i as f32 * 5.0,
+                    t.sin() * 20.0 + if i % 3 == 0 { 2.0 } else { 0.0 },
+                )
+            })
+            .collect();
+        let widths: Vec<f32> = (0..60).map(|i| 2.0 + (i % 7) as f32 * 0.5).collect();
+
+        for (widths, strength) in [(&widths[..], 0.3), (&widths[..], 1.0), (&[][..], 1.0)] {
+            let mut smoother = LiveSmoother::default();
+            let (mut smoothed, mut smoothed_widths) = (Vec::new(), Vec::new());
+            for end in 1..=points.len() {
+                let widths = if widths.is_empty() {
+                    widths
+                } else {
+                    &widths[..end]
+                };
+                smoother.update(
+                    &points[..end],
+                    widths,
+                    strength,
+                    &mut smoothed,
+                    &mut smoothed_widths,
+                );
+                assert_eq!(
+                    (smoothed.clone(), smoothed_widths.clone()),
+                    smooth(&points[..end], widths, strength)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn erasing_the_middle_splits_with_widths_in_sync() {
+        let points = [
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (20.0, 0.0),
+            (30.0, 0.0),
+            (40.0, 0.0),
+        ];
+        let widths = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let pieces = erase_from_polyline(&points, &widths, (20.0, 0.0), 5.0).unwrap();
+
+        assert_eq!(pieces.len(), 2);
+        let (first, first_widths) = &pieces[0];
+        assert_eq!(first, &vec![(0.0, 0.0), (10.0, 0.0), (15.0, 0.0)]);
+        assert_eq!(first_widths, &vec![1.0, 2.0, 2.5]);
+        let (second, second_widths) = &pieces[1];
+        assert_eq!(second, &vec![(25.0, 0.0), (30.0, 0.0), (40.0, 0.0)]);
+        assert_eq!(second_widths, &vec![3.5, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn erasing_an_end_trims_it() {
+        let points = [(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)];
+        let pieces = erase_from_polyline(&points, &[], (20.0, 0.0), 4.0).unwrap();
+        assert_eq!(
+            pieces,
+            vec![(vec![(0.0, 0.0), (10.0, 0.0), (16.0, 0.0)], vec![])]
+        );
+    }
+
+    #[test]
+    fn missing_circle_leaves_stroke_untouched() {
+        let points = [(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)];
+        assert_eq!(erase_from_polyline(&points, &[], (10.0, 20.0), 5.0), None);
+    }
+
+    #[test]
+    fn segment_through_circle_overlaps() {
+        // 两个端点都在圆外，线段穿过圆
+        let (t_in, t_out) =
+            segment_circle_overlap((0.0, 0.0), (100.0, 0.0), (50.0, 0.0), 10.0).unwrap();
+        assert!((t_in - 0.4).abs() < 1e-5 && (t_out - 0.6).abs() < 1e-5);
+        assert_eq!(
+            segment_circle_overlap((0.0, 0.0), (100.0, 0.0), (50.0, 20.0), 10.0),
+            None
+        );
+    }
+}
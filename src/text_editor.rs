@@ -0,0 +1,643 @@
+//! Grapheme-aware editing model for the text tool.
+//!
+//! The cursor is stored as a byte offset into the text but is only ever moved
+//! to extended grapheme cluster boundaries, so multi-byte input (CJK, emoji,
+//! combining marks, ZWJ sequences) can never be split or cause a panic.
+//!
+//! While an IME is composing, the uncommitted preedit string is kept separately
+//! and only spliced into the displayed text, never into the edited text.
+//!
+//! A selection is the range between an anchor and the cursor. Plain movements
+//! collapse it; [`TextEditor::move_with`] extends it (Shift+movement).
+
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Text being edited plus a cursor that always sits on a grapheme boundary.
+#[derive(Debug, Clone, Default)]
+pub struct TextEditor {
+    text: String,
+    /// 光标位置（字节偏移，始终位于字素簇边界上）
+    cursor: usize,
+    /// 选区的另一端（字节偏移），None 表示没有选区
+    anchor: Option<usize>,
+    /// 上下移动时希望保持的列（以字素计），其他操作会清除它
+    preferred_column: Option<usize>,
+    /// IME 预编辑（尚未提交）的文本
+    preedit: String,
+    /// 预编辑文本中的光标/选中范围（字节偏移），None 表示隐藏光标
+    preedit_cursor: Option<(usize, usize)>,
+}
+
+/// The text shown while editing, with byte ranges into [`EditorDisplay::text`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorDisplay {
+    pub text: String,
+    /// Range of the cursor character, if the cursor is visible.
+    pub caret: Option<Range<usize>>,
+    /// Range of the IME preedit string, including the cursor character when
+    /// the IME places it inside the preedit.
+    pub preedit: Option<Range<usize>>,
+    /// Range of the segment the IME marks as selected inside the preedit.
+    pub preedit_highlight: Option<Range<usize>>,
+    /// Range of the selected text.
+    pub selection: Option<Range<usize>>,
+    /// 在光标处插入的内容（预编辑文本 + 光标字符）所占的范围
+    inserted: Range<usize>,
+}
+
+impl EditorDisplay {
+    /// Maps a byte offset in the displayed text back to the edited text.
+    /// Offsets inside the preedit or on the cursor character map to the cursor.
+    pub fn to_text_offset(&self, offset: usize) -> usize {
+        if offset <= self.inserted.start {
+            offset
+        } else if offset >= self.inserted.end {
+            offset - self.inserted.len()
+        } else {
+            self.inserted.start
+        }
+    }
+}
+
+impl TextEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Byte offset of the cursor in [`Self::text`].
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the whole text and places the cursor at the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.anchor = None;
+        self.preferred_column = None;
+    }
+
+    /// The selected byte range, if the selection is not empty.
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        (anchor != self.cursor).then(|| anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selection().map(|range| &self.text[range])
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = Some(0);
+        self.cursor = self.text.len();
+        self.preferred_column = None;
+    }
+
+    /// Deletes the selected text. Returns `false` when nothing is selected.
+    pub fn delete_selection(&mut self) -> bool {
+        let Some(range) = self.selection() else {
+            return false;
+        };
+        self.text.replace_range(range.clone(), "");
+        self.cursor = range.start;
+        self.anchor = None;
+        self.preferred_column = None;
+        true
+    }
+
+    /// Places the cursor at the grapheme boundary at or after `offset` (mouse
+    /// click), extending the selection from the old cursor when `extend` is set.
+    pub fn set_cursor(&mut self, offset: usize, extend: bool) {
+        let mut offset = offset.min(self.text.len());
+        while !self.text.is_char_boundary(offset) {
+            offset += 1;
+        }
+        let target = self.snap_forward(offset);
+        self.move_with(extend, |editor| editor.move_to(Some(target)));
+    }
+
+    /// Runs a cursor movement, extending the selection when `extend` is set
+    /// and collapsing it otherwise.
+    pub fn move_with(&mut self, extend: bool, movement: impl FnOnce(&mut Self) -> bool) -> bool {
+        let anchor = self.anchor.unwrap_or(self.cursor);
+        let moved = movement(self);
+        if extend {
+            self.anchor = Some(anchor);
+        }
+        moved
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+        self.clear_preedit();
+    }
+
+    /// Sets the IME composition string shown at the cursor. `cursor` is the
+    /// byte range reported by the IME; invalid ranges hide the cursor.
+    pub fn set_preedit(&mut self, text: &str, cursor: Option<(usize, usize)>) {
+        self.preedit = text.to_string();
+        self.preedit_cursor = cursor.filter(|&(start, end)| {
+            start <= end
+                && end <= text.len()
+                && text.is_char_boundary(start)
+                && text.is_char_boundary(end)
+        });
+    }
+
+    pub fn clear_preedit(&mut self) {
+        self.set_preedit("", None);
+    }
+
+    /// Inserts `s` at the cursor (replacing the selection) and moves the
+    /// cursor past it.
+    pub fn insert_str(&mut self, s: &str) {
+        self.delete_selection();
+        self.text.insert_str(self.cursor, s);
+        self.cursor += s.len();
+        // 插入的内容可能与前面的字符组成新的字素簇（如组合附加符号），重新对齐光标
+        self.cursor = self.snap_forward(self.cursor);
+        self.preferred_column = None;
+    }
+
+    /// Deletes the selection or the grapheme before the cursor. Returns
+    /// `false` if nothing was deleted.
+    pub fn backspace(&mut self) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        self.preferred_column = None;
+        match self.prev_boundary(self.cursor) {
+            Some(start) => {
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Deletes the selection or the grapheme after the cursor. Returns
+    /// `false` if nothing was deleted.
+    pub fn delete(&mut self) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+        self.preferred_column = None;
+        match self.next_boundary(self.cursor) {
+            Some(end) => {
+                self.text.replace_range(self.cursor..end, "");
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn move_left(&mut self) -> bool {
+        self.move_to(self.prev_boundary(self.cursor))
+    }
+
+    pub fn move_right(&mut self) -> bool {
+        self.move_to(self.next_boundary(self.cursor))
+    }
+
+    /// Moves to the start of the previous word (Ctrl+Left).
+    pub fn move_word_left(&mut self) -> bool {
+        let target = self
+            .text
+            .split_word_bound_indices()
+            .rev()
+            .find(|(start, word)| *start < self.cursor && Self::is_word(word))
+            .map(|(start, _)| start)
+            .unwrap_or(0);
+        self.move_to(Some(target))
+    }
+
+    /// Moves to the end of the next word (Ctrl+Right).
+    pub fn move_word_right(&mut self) -> bool {
+        let target = self
+            .text
+            .split_word_bound_indices()
+            .map(|(start, word)| (start + word.len(), word))
+            .find(|(end, word)| *end > self.cursor && Self::is_word(word))
+            .map(|(end, _)| end)
+            .unwrap_or(self.text.len());
+        self.move_to(Some(target))
+    }
+
+    /// Moves to the start of the current line (Home).
+    pub fn move_home(&mut self) -> bool {
+        let (start, _) = self.line_range(self.cursor);
+        self.move_to(Some(start))
+    }
+
+    /// Moves to the end of the current line (End).
+    pub fn move_end(&mut self) -> bool {
+        let (_, end) = self.line_range(self.cursor);
+        self.move_to(Some(end))
+    }
+
+    /// Moves to the start of the text (Ctrl+Home).
+    pub fn move_text_start(&mut self) -> bool {
+        self.move_to(Some(0))
+    }
+
+    /// Moves to the end of the text (Ctrl+End).
+    pub fn move_text_end(&mut self) -> bool {
+        self.move_to(Some(self.text.len()))
+    }
+
+    /// Moves to the same grapheme column on the previous line, or to the
+    /// start of the text when already on the first line.
+    pub fn move_up(&mut self) -> bool {
+        let (line_start, _) = self.line_range(self.cursor);
+        if line_start == 0 {
+            return self.move_to(Some(0));
+        }
+        let column = self.current_column();
+        let (prev_start, prev_end) = self.line_range(line_start - 1);
+        let target = self.offset_at_column(prev_start, prev_end, column);
+        self.move_vertically(target, column)
+    }
+
+    /// Moves to the same grapheme column on the next line, or to the end of
+    /// the text when already on the last line.
+    pub fn move_down(&mut self) -> bool {
+        let (_, line_end) = self.line_range(self.cursor);
+        if line_end == self.text.len() {
+            return self.move_to(Some(line_end));
+        }
+        let column = self.current_column();
+        let (next_start, next_end) = self.line_range(line_end + 1);
+        let target = self.offset_at_column(next_start, next_end, column);
+        self.move_vertically(target, column)
+    }
+
+    /// Returns the text with `cursor_char` inserted at the cursor position.
+    pub fn display_with_cursor(&self, cursor_char: char) -> String {
+        self.display(cursor_char).text
+    }
+
+    /// Builds the displayed text: the preedit string is spliced in at the
+    /// cursor and `cursor_char` is placed at the end of the IME cursor range.
+    pub fn display(&self, cursor_char: char) -> EditorDisplay {
+        let (before, after) = self.text.split_at(self.cursor);
+        let mut text =
+            String::with_capacity(self.text.len() + self.preedit.len() + cursor_char.len_utf8());
+        text.push_str(before);
+
+        // 没有预编辑文本时光标总是可见
+        let has_preedit = !self.preedit.is_empty();
+        let preedit_cursor = if has_preedit {
+            self.preedit_cursor
+        } else {
+            Some((0, 0))
+        };
+
+        let preedit_start = text.len();
+        let caret = preedit_cursor.map(|(_, end)| {
+            text.push_str(&self.preedit[..end]);
+            let caret_start = text.len();
+            text.push(cursor_char);
+            text.push_str(&self.preedit[end..]);
+            caret_start..caret_start + cursor_char.len_utf8()
+        });
+        if caret.is_none() {
+            text.push_str(&self.preedit);
+        }
+        let inserted = preedit_start..text.len();
+        text.push_str(after);
+
+        // 选区两端在光标处时不包含插入的预编辑文本和光标字符
+        let to_display = |offset: usize, at_cursor_is_before: bool| {
+            if offset < self.cursor || (offset == self.cursor && at_cursor_is_before) {
+                offset
+            } else {
+                offset + inserted.len()
+            }
+        };
+        let selection = self
+            .selection()
+            .map(|range| to_display(range.start, false)..to_display(range.end, true));
+
+        EditorDisplay {
+            text,
+            caret,
+            preedit: has_preedit.then(|| inserted.clone()),
+            preedit_highlight: preedit_cursor
+                .filter(|(start, end)| has_preedit && start < end)
+                .map(|(start, end)| preedit_start + start..preedit_start + end),
+            selection,
+            inserted,
+        }
+    }
+
+    fn move_to(&mut self, target: Option<usize>) -> bool {
+        self.anchor = None;
+        self.preferred_column = None;
+        match target {
+            Some(target) if target != self.cursor => {
+                self.cursor = target;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn move_vertically(&mut self, target: usize, column: usize) -> bool {
+        let moved = target != self.cursor;
+        self.anchor = None;
+        self.cursor = target;
+        self.preferred_column = Some(column);
+        moved
+    }
+
+    // 含字母或数字的片段才算"单词"，空白与标点会被跳过
+    fn is_word(segment: &str) -> bool {
+        segment.chars().any(char::is_alphanumeric)
+    }
+
+    fn prev_boundary(&self, offset: usize) -> Option<usize> {
+        self.text[..offset]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(start, _)| start)
+    }
+
+    fn next_boundary(&self, offset: usize) -> Option<usize> {
+        self.text[offset..]
+            .graphemes(true)
+            .next()
+            .map(|g| offset + g.len())
+    }
+
+    // 把任意字节偏移推到下一个（或当前）字素簇边界
+    fn snap_forward(&self, offset: usize) -> usize {
+        self.text
+            .grapheme_indices(true)
+            .map(|(start, g)| (start, start + g.len()))
+            .find(|(start, end)| *start < offset && offset < *end)
+            .map_or(offset, |(_, end)| end)
+    }
+
+    // 包含 offset 的行的字节范围（不含换行符）
+    fn line_range(&self, offset: usize) -> (usize, usize) {
+        let start = self.text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let end = self.text[offset..]
+            .find('\n')
+            .map_or(self.text.len(), |i| offset + i);
+        (start, end)
+    }
+
+    fn current_column(&self) -> usize {
+        self.preferred_column.unwrap_or_else(|| {
+            let (start, _) = self.line_range(self.cursor);
+            self.text[start..self.cursor].graphemes(true).count()
+        })
+    }
+
+    fn offset_at_column(&self, line_start: usize, line_end: usize, column: usize) -> usize {
+        self.text[line_start..line_end]
+            .grapheme_indices(true)
+            .nth(column)
+            .map_or(line_end, |(i, _)| line_start + i)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(text: &str) -> TextEditor {
+        let mut editor = TextEditor::default();
+        editor.set_text(text);
+        editor
+    }
+
+    #[test]
+    fn insert_multibyte_and_move_by_grapheme() {
+        let mut e = TextEditor::default();
+        e.insert_str("中文");
+        e.insert_str("👍🏽");
+        assert_eq!(e.text(), "中文👍🏽");
+        assert_eq!(e.cursor(), e.text().len());
+
+        // 肤色修饰的表情是一个字素簇，左移一次应跳过整个簇
+        assert!(e.move_left());
+        assert_eq!(e.cursor(), "中文".len());
+        assert!(e.move_left());
+        assert_eq!(e.cursor(), "中".len());
+        e.insert_str("a");
+        assert_eq!(e.text(), "中a文👍🏽");
+    }
+
+    #[test]
+    fn backspace_and_delete_remove_whole_graphemes() {
+        let family = "👨\u{200d}👩\u{200d}👧";
+        let mut e = editor(&format!("x{family}é"));
+        assert!(e.backspace());
+        assert_eq!(e.text(), format!("x{family}"));
+        assert!(e.backspace());
+        assert_eq!(e.text(), "x");
+
+        let mut e = editor("e\u{301}z");
+        e.move_text_start();
+        assert!(e.delete());
+        assert_eq!(e.text(), "z");
+        e.move_text_end();
+        assert!(!e.delete());
+        e.move_text_start();
+        assert!(!e.backspace());
+    }
+
+    #[test]
+    fn combining_mark_typed_after_base_keeps_cursor_on_boundary() {
+        let mut e = editor("e");
+        e.insert_str("\u{301}");
+        assert_eq!(e.cursor(), e.text().len());
+        assert!(e.move_left());
+        assert_eq!(e.cursor(), 0);
+    }
+
+    #[test]
+    fn cursor_stops_at_text_edges() {
+        let mut e = editor("ab");
+        assert!(!e.move_right());
+        e.move_text_start();
+        assert!(!e.move_left());
+        let mut empty = TextEditor::default();
+        assert!(!empty.backspace());
+        assert!(!empty.move_left());
+        assert!(!empty.move_right());
+    }
+
+    #[test]
+    fn word_navigation_skips_spaces_and_punctuation() {
+        let mut e = editor("hello, wide  world");
+        assert!(e.move_word_left());
+        assert_eq!(e.cursor(), "hello, wide  ".len());
+        assert!(e.move_word_left());
+        assert_eq!(e.cursor(), "hello, ".len());
+        assert!(e.move_word_left());
+        assert_eq!(e.cursor(), 0);
+        assert!(!e.move_word_left());
+
+        assert!(e.move_word_right());
+        assert_eq!(e.cursor(), "hello".len());
+        assert!(e.move_word_right());
+        assert_eq!(e.cursor(), "hello, wide".len());
+        assert!(e.move_word_right());
+        assert_eq!(e.cursor(), e.text().len());
+        assert!(!e.move_word_right());
+    }
+
+    #[test]
+    fn home_end_are_line_relative() {
+        let mut e = editor("first\nsecond line");
+        assert!(e.move_home());
+        assert_eq!(e.cursor(), "first\n".len());
+        assert!(e.move_end());
+        assert_eq!(e.cursor(), e.text().len());
+        e.move_text_start();
+        assert!(e.move_end());
+        assert_eq!(e.cursor(), "first".len());
+    }
+
+    #[test]
+    fn vertical_movement_keeps_grapheme_column() {
+        // 第一行是中文（每字 3 字节），列必须按字素计算而不是字节
+        let mut e = editor("中文字\nab\nxyz1");
+        assert!(e.move_up());
+        assert_eq!(e.cursor(), "中文字\n".len() + "ab".len());
+        assert!(e.move_up());
+        // 记住了最初的第 4 列，但第一行只有 3 个字，停在行尾
+        assert_eq!(e.cursor(), "中文字".len());
+        assert!(e.move_down());
+        assert_eq!(e.cursor(), "中文字\nab".len());
+        assert!(e.move_down());
+        assert_eq!(e.cursor(), e.text().len());
+
+        // 首行上移到文本开头，末行下移到文本结尾
+        e.move_text_start();
+        e.move_right();
+        assert!(e.move_up());
+        assert_eq!(e.cursor(), 0);
+        e.move_text_end();
+        e.move_left();
+        assert!(e.move_down());
+        assert_eq!(e.cursor(), e.text().len());
+    }
+
+    #[test]
+    fn preedit_is_spliced_at_cursor_without_editing_text() {
+        let mut e = editor("ab");
+        e.move_left();
+        e.set_preedit("にほん", Some((0, "にほ".len())));
+        let display = e.display('|');
+        assert_eq!(display.text, "aにほ|んb");
+        assert_eq!(display.caret, Some(7..8));
+        assert_eq!(display.preedit, Some(1..1 + "にほん|".len()));
+        assert_eq!(display.preedit_highlight, Some(1..1 + "にほ".len()));
+        assert_eq!(e.text(), "ab");
+
+        // 无效的范围（不在字符边界上）隐藏光标
+        e.set_preedit("にほん", Some((1, 2)));
+        let display = e.display('|');
+        assert_eq!(display.text, "aにほんb");
+        assert_eq!(display.caret, None);
+        assert_eq!(display.preedit_highlight, None);
+
+        e.clear_preedit();
+        e.insert_str("日本");
+        assert_eq!(e.display('|').text, "a日本|b");
+        assert_eq!(e.display('|').preedit, None);
+    }
+
+    #[test]
+    fn display_inserts_cursor_at_byte_offset() {
+        let mut e = editor("日本");
+        e.move_left();
+        assert_eq!(e.display_with_cursor('|'), "日|本");
+    }
+
+    #[test]
+    fn shift_movement_extends_and_plain_movement_collapses() {
+        let mut e = editor("你好 world");
+        assert!(e.move_with(true, TextEditor::move_word_left));
+        assert_eq!(e.selected_text(), Some("world"));
+        assert!(e.move_with(true, TextEditor::move_left));
+        assert_eq!(e.selected_text(), Some(" world"));
+        assert!(e.move_with(true, TextEditor::move_left));
+        assert_eq!(e.selected_text(), Some("好 world"));
+
+        // 不按 Shift 的移动取消选区
+        e.move_with(false, TextEditor::move_right);
+        assert_eq!(e.selection(), None);
+        assert_eq!(e.cursor(), "你好".len());
+    }
+
+    #[test]
+    fn editing_replaces_selection() {
+        let mut e = editor("hello world");
+        e.select_all();
+        assert_eq!(e.selected_text(), Some("hello world"));
+        e.insert_str("👋");
+        assert_eq!(e.text(), "👋");
+        assert_eq!(e.selection(), None);
+
+        let mut e = editor("abcdef");
+        e.set_cursor(1, false);
+        e.set_cursor(4, true);
+        assert_eq!(e.selected_text(), Some("bcd"));
+        assert!(e.backspace());
+        assert_eq!(e.text(), "aef");
+        assert_eq!(e.cursor(), 1);
+
+        let mut e = editor("abcdef");
+        e.set_cursor(5, false);
+        e.set_cursor(2, true);
+        assert!(e.delete());
+        assert_eq!(e.text(), "abf");
+        assert!(!e.delete_selection());
+    }
+
+    #[test]
+    fn set_cursor_snaps_to_grapheme_boundary() {
+        let mut e = editor("a中👍🏽");
+        e.set_cursor(2, false);
+        assert_eq!(e.cursor(), "a中".len());
+        e.set_cursor("a中".len() + 4, false);
+        assert_eq!(e.cursor(), e.text().len());
+        e.set_cursor(100, false);
+        assert_eq!(e.cursor(), e.text().len());
+    }
+
+    #[test]
+    fn display_selection_and_offsets_skip_inserted_cursor() {
+        let mut e = editor("abcd");
+        e.set_cursor(1, false);
+        e.set_cursor(3, true);
+        let display = e.display('|');
+        assert_eq!(display.text, "abc|d");
+        assert_eq!(display.selection, Some(1..3));
+
+        e.set_cursor(3, false);
+        e.set_cursor(1, true);
+        let display = e.display('|');
+        assert_eq!(display.text, "a|bcd");
+        assert_eq!(display.selection, Some(2..4));
+
+        // 显示文本中的偏移映射回编辑文本
+        assert_eq!(display.to_text_offset(0), 0);
+        assert_eq!(display.to_text_offset(1), 1);
+        assert_eq!(display.to_text_offset(2), 1);
+        assert_eq!(display.to_text_offset(5), 4);
+
+        e.set_preedit("かな", None);
+        let display = e.display('|');
+        assert_eq!(display.text, "aかなbcd");
+        assert_eq!(display.to_text_offset(1 + "か".len()), 1);
+        assert_eq!(display.to_text_offset(1 + "かな".len() + 1), 2);
+    }
+}
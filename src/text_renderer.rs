@@ -1,11 +1,32 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
 use glyphon::{
-    Attrs, Buffer, Cache, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache,
-    TextArea, TextAtlas, TextBounds, TextRenderer as GlyphonTextRenderer, Viewport,
+    Buffer, Cache, FontSystem, Metrics, Resolution, Shaping, SwashCache, TextArea, TextAtlas,
+    TextRenderer as GlyphonTextRenderer, Viewport, Wrap,
 };
 
+use crate::config::FontConfig;
+use crate::fonts::create_font_system;
+use crate::text_style::TextStyle;
+
 /// 光标字符常量
 pub const CURSOR_CHAR: char = '|';
 
+/// 文本测量缓存的最大条目数，超过后清空重建
+const MAX_METRICS_CACHE_ENTRIES: usize = 1024;
+
+/// Horizontal extent of a byte range on one laid-out line, relative to the
+/// text origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextSpan {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub baseline: f32,
+    pub bottom: f32,
+}
+
 /// Text renderer wrapper for glyphon
 pub struct TextRenderer {
     pub font_system: FontSystem,
@@ -14,6 +35,15 @@ pub struct TextRenderer {
     pub atlas: TextAtlas,
     pub text_renderer: GlyphonTextRenderer,
     pub viewport: Viewport,
+    // 🚀 离屏渲染（旋转文本）使用独立的渲染器和视口，与屏幕文本共享字形图集
+    offscreen_renderer: GlyphonTextRenderer,
+    offscreen_viewport: Viewport,
+    // 🚀 界面文字（属性栏标签）使用独立的渲染器，不会覆盖同一帧中已准备好的标注文本
+    ui_renderer: GlyphonTextRenderer,
+    // 🚀 选区上的标签（尺寸标签）与工具栏面板的标签同时显示，也使用独立的渲染器
+    overlay_renderer: GlyphonTextRenderer,
+    // 🚀 文本测量缓存：(文本, 字号, 样式) -> 排版后的宽高
+    metrics_cache: HashMap<(String, u32, TextStyle), (f32, f32)>,
 }
 
 impl TextRenderer {
@@ -24,36 +54,10 @@ impl TextRenderer {
         width: u32,
         height: u32,
         format: wgpu::TextureFormat,
+        fonts: &FontConfig,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        // 🚀 内存优化：创建空的字体系统，不自动加载系统字体
-        let mut font_system = FontSystem::new_with_locale_and_db(
-            "en-US".to_string(),
-            glyphon::fontdb::Database::new(), // 使用空的字体数据库
-        );
-
-        // 🚀 加载支持中文的字体
-        let dejavu_font_data = include_bytes!("../fonts/NotoSerifCJKsc-VF.ttf");
-        println!(
-            "🚀 加载 DejaVu Sans 字体，大小: {} 字节",
-            dejavu_font_data.len()
-        );
-        font_system
-            .db_mut()
-            .load_font_data(dejavu_font_data.to_vec());
-
-        // 🚀 加载表情符号字体
-        let emoji_font_data = include_bytes!("../fonts/SegoeUIEmoji.ttf");
-        println!(
-            "🚀 加载 Segoe UI Emoji 字体，大小: {} 字节",
-            emoji_font_data.len()
-        );
-        font_system
-            .db_mut()
-            .load_font_data(emoji_font_data.to_vec());
-
-        // 检查字体是否加载成功
-        let font_count = font_system.db().len();
-        println!("🚀 字体数据库中的字体数量: {}", font_count);
+        // 🚀 按配置加载内置字体、系统字体和额外字体目录
+        let font_system = create_font_system(fonts);
 
         // Create cache and atlas
         let cache = Cache::new(device);
@@ -67,6 +71,19 @@ impl TextRenderer {
         // Create viewport
         let viewport = Viewport::new(device, &cache);
 
+        // Offscreen renderer for text drawn as textured quads
+        let offscreen_renderer =
+            GlyphonTextRenderer::new(&mut atlas, device, wgpu::MultisampleState::default(), None);
+        let offscreen_viewport = Viewport::new(device, &cache);
+
+        // UI label renderer, drawn with the screen viewport
+        let ui_renderer =
+            GlyphonTextRenderer::new(&mut atlas, device, wgpu::MultisampleState::default(), None);
+
+        // Capture box overlay label renderer, drawn with the screen viewport
+        let overlay_renderer =
+            GlyphonTextRenderer::new(&mut atlas, device, wgpu::MultisampleState::default(), None);
+
         println!("🚀 文本渲染器初始化完成");
 
         Ok(Self {
@@ -76,6 +93,11 @@ impl TextRenderer {
             atlas,
             text_renderer,
             viewport,
+            offscreen_renderer,
+            offscreen_viewport,
+            ui_renderer,
+            overlay_renderer,
+            metrics_cache: HashMap::new(),
         })
     }
 
@@ -103,51 +125,277 @@ impl TextRenderer {
     }
 
     /// Render the prepared text
-    pub fn render<'rpass>(
-        &'rpass self,
-        render_pass: &mut wgpu::RenderPass<'rpass>,
-    ) -> Result<(), glyphon::RenderError> {
+    pub fn render(&self, render_pass: &mut wgpu::RenderPass) -> Result<(), glyphon::RenderError> {
         self.text_renderer
             .render(&self.atlas, &self.viewport, render_pass)
     }
 
-    /// Create a text buffer with the given text
-    pub fn create_buffer(&mut self, text: &str, font_size: f32, width: f32, height: f32) -> Buffer {
-        // 🚀 使用相对行高，更符合 glyphon 最佳实践
-        let metrics = Metrics::relative(font_size, 1.2); // 1.2倍行高
-        let mut buffer = Buffer::new(&mut self.font_system, metrics);
+    /// Prepare UI labels, independent of the annotation text prepared with
+    /// [`Self::prepare`] in the same frame.
+    pub fn prepare_ui<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_areas: impl IntoIterator<Item = TextArea<'a>>,
+    ) -> Result<(), glyphon::PrepareError> {
+        self.ui_renderer.prepare(
+            device,
+            queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            &self.viewport,
+            text_areas,
+            &mut self.swash_cache,
+        )
+    }
+
+    /// Render the prepared UI labels
+    pub fn render_ui(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+    ) -> Result<(), glyphon::RenderError> {
+        self.ui_renderer
+            .render(&self.atlas, &self.viewport, render_pass)
+    }
+
+    /// Prepare labels drawn on the capture box, independent of the toolbar
+    /// panel labels prepared with [`Self::prepare_ui`] in the same frame.
+    pub fn prepare_overlay<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_areas: impl IntoIterator<Item = TextArea<'a>>,
+    ) -> Result<(), glyphon::PrepareError> {
+        self.overlay_renderer.prepare(
+            device,
+            queue,
+            &mut self.font_system,
+            &mut self.atlas,
+            &self.viewport,
+            text_areas,
+            &mut self.swash_cache,
+        )
+    }
 
-        // 🚀 修复：为多行文本设置合适的缓冲区大小
-        let buffer_width = width.max(200.0); // 最小宽度200像素
-        let buffer_height = if text.contains('\n') {
-            // 多行文本需要更多高度
-            height.max(font_size * 1.2 * text.matches('\n').count() as f32 + font_size * 2.0)
-        } else {
-            height.max(font_size * 2.0) // 单行文本的最小高度
-        };
+    /// Render the prepared capture box labels
+    pub fn render_overlay(
+        &self,
+        render_pass: &mut wgpu::RenderPass,
+    ) -> Result<(), glyphon::RenderError> {
+        self.overlay_renderer
+            .render(&self.atlas, &self.viewport, render_pass)
+    }
 
-        buffer.set_size(
+    /// Render text areas into an offscreen `target` of the given size.
+    ///
+    /// `load` decides whether the target is cleared first (rotated text is
+    /// rasterized onto a transparent texture, which yields premultiplied
+    /// alpha) or drawn over existing content (export).
+    pub fn render_to_texture<'a>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        text_areas: impl IntoIterator<Item = TextArea<'a>>,
+        target: &wgpu::TextureView,
+        (width, height): (u32, u32),
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.offscreen_viewport
+            .update(queue, Resolution { width, height });
+        self.offscreen_renderer.prepare(
+            device,
+            queue,
             &mut self.font_system,
-            Some(buffer_width),
-            Some(buffer_height),
+            &mut self.atlas,
+            &self.offscreen_viewport,
+            text_areas,
+            &mut self.swash_cache,
+        )?;
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Offscreen Text Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Offscreen Text Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                    depth_slice: None,
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.offscreen_renderer.render(
+                &self.atlas,
+                &self.offscreen_viewport,
+                &mut render_pass,
+            )?;
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        Ok(())
+    }
+
+    /// Measure the laid-out size of `text`: the width of the widest line and
+    /// the total height of all lines. Results are cached by text, size and
+    /// style.
+    pub fn measure(&mut self, text: &str, font_size: f32, style: &TextStyle) -> (f32, f32) {
+        let key = (text.to_string(), font_size.to_bits(), style.clone());
+        if let Some(size) = self.metrics_cache.get(&key) {
+            return *size;
+        }
+
+        let buffer = self.shape(text, font_size, style);
+
+        let (width, height) =
+            buffer
+                .layout_runs()
+                .fold((0.0f32, 0.0f32), |(width, height), run| {
+                    (
+                        width.max(run.line_w),
+                        height.max(run.line_top + run.line_height),
+                    )
+                });
+        // 空文本也占一行高度，方便显示光标和点击
+        let size = (width, height.max(font_size * style.line_spacing));
+
+        if self.metrics_cache.len() >= MAX_METRICS_CACHE_ENTRIES {
+            self.metrics_cache.clear();
+        }
+        self.metrics_cache.insert(key, size);
+        size
+    }
+
+    /// Locate the byte `range` of `text` in its layout: one span per line the
+    /// range touches. An empty range yields a zero-width span (a caret).
+    pub fn spans(
+        &mut self,
+        text: &str,
+        font_size: f32,
+        style: &TextStyle,
+        range: Range<usize>,
+    ) -> Vec<TextSpan> {
+        let buffer = self.shape(text, font_size, style);
+        let line_starts = Self::line_starts(&buffer);
+
+        let mut spans = Vec::new();
+        for run in buffer.layout_runs() {
+            let line_start = line_starts[run.line_i];
+            let line_range = line_start..line_start + run.text.len();
+            if range.start > line_range.end || range.end < line_range.start {
+                continue;
+            }
+
+            let (mut left, mut right) = (f32::MAX, f32::MIN);
+            for glyph in run.glyphs {
+                let glyph_range = line_start + glyph.start..line_start + glyph.end;
+                if glyph_range.start < range.end && range.start < glyph_range.end {
+                    left = left.min(glyph.x);
+                    right = right.max(glyph.x + glyph.w);
+                }
+            }
+            if left > right {
+                // 范围内没有字形（光标位置或空行）：取范围起点所在的位置
+                let caret_x = run
+                    .glyphs
+                    .iter()
+                    .find(|glyph| line_start + glyph.start >= range.start)
+                    .map_or(run.line_w, |glyph| glyph.x);
+                left = caret_x;
+                right = caret_x;
+            }
+            spans.push(TextSpan {
+                left,
+                right,
+                top: run.line_top,
+                baseline: run.line_y,
+                bottom: run.line_top + run.line_height,
+            });
+        }
+        spans
+    }
+
+    /// Byte offset in `text` of the caret position closest to `point`
+    /// (relative to the text origin), using the layout's hit test.
+    pub fn hit(
+        &mut self,
+        text: &str,
+        font_size: f32,
+        style: &TextStyle,
+        point: (f32, f32),
+    ) -> Option<usize> {
+        let buffer = self.shape(text, font_size, style);
+        let cursor = buffer.hit(point.0, point.1)?;
+        Some(Self::line_starts(&buffer)[cursor.line] + cursor.index)
+    }
+
+    // 每行在整段文本中的起始字节偏移
+    fn line_starts(buffer: &Buffer) -> Vec<usize> {
+        let mut line_starts = Vec::with_capacity(buffer.lines.len());
+        let mut offset = 0;
+        for line in &buffer.lines {
+            line_starts.push(offset);
+            offset += line.text().len() + line.ending().as_str().len();
+        }
+        line_starts
+    }
+
+    /// Font families available in the font database, sorted and deduplicated.
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self
+            .font_system
+            .db()
+            .faces()
+            .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+            .collect();
+        families.sort();
+        families.dedup();
+        families
+    }
+
+    /// Create a text buffer laid out with `style`. The buffer is exactly as
+    /// wide as its widest line and never wraps, matching [`Self::measure`].
+    pub fn create_buffer(&mut self, text: &str, font_size: f32, style: &TextStyle) -> Buffer {
+        let buffer = self.shape(text, font_size, style);
+        println!(
+            "🚀 创建文本缓冲区: 文本='{:?}', 行数={}",
+            text,
+            text.matches('\n').count() + 1
         );
+        buffer
+    }
 
-        // 🚀 使用支持中文的字体属性 - 使用 DejaVu Sans 作为主要字体
+    // 不限制宽度、不自动换行地排版文本；居中/右对齐需要以最宽的行为基准再排一次
+    fn shape(&mut self, text: &str, font_size: f32, style: &TextStyle) -> Buffer {
+        let mut buffer = Buffer::new(
+            &mut self.font_system,
+            Metrics::relative(font_size, style.line_spacing),
+        );
+        buffer.set_wrap(&mut self.font_system, Wrap::None);
+        buffer.set_size(&mut self.font_system, None, None);
         buffer.set_text(
             &mut self.font_system,
             text,
-            &Attrs::new().family(Family::Name("DejaVu Sans")),
+            &style.attrs(),
             Shaping::Advanced,
         );
-        buffer.shape_until_scroll(&mut self.font_system, false);
 
-        println!(
-            "🚀 创建文本缓冲区: 文本='{:?}', 大小={}x{}, 行数={}",
-            text,
-            buffer_width,
-            buffer_height,
-            text.matches('\n').count() + 1
-        );
+        if let Some(align) = style.glyphon_align() {
+            for line in &mut buffer.lines {
+                line.set_align(Some(align));
+            }
+            buffer.shape_until_scroll(&mut self.font_system, false);
+            let width = buffer
+                .layout_runs()
+                .fold(0.0f32, |width, run| width.max(run.line_w));
+            buffer.set_size(&mut self.font_system, Some(width), None);
+        }
+        buffer.shape_until_scroll(&mut self.font_system, false);
         buffer
     }
 }
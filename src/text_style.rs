@@ -0,0 +1,219 @@
+//! Per-element text style: font family, weight, slant, decorations,
+//! background box, outline, alignment and line spacing.
+
+use std::hash::{Hash, Hasher};
+
+use glyphon::cosmic_text::Align;
+use glyphon::{Attrs, Family, Style, Weight};
+
+/// 默认字体族（未指定字体时使用）
+pub const DEFAULT_FONT_FAMILY: &str = "DejaVu Sans";
+/// 默认行距（相对字号）
+pub const DEFAULT_LINE_SPACING: f32 = 1.2;
+/// 属性栏中可循环选择的行距
+pub const LINE_SPACING_STEPS: [f32; 4] = [1.0, 1.2, 1.5, 2.0];
+/// 背景框的内边距（相对字号）
+pub const BACKGROUND_PADDING: f32 = 0.25;
+/// 背景框的圆角半径（相对字号）
+pub const BACKGROUND_RADIUS: f32 = 0.2;
+/// 背景框的不透明度
+pub const BACKGROUND_ALPHA: f32 = 0.8;
+/// 描边宽度（相对字号），限制在文字裁剪边界的留白之内
+const OUTLINE_WIDTH: f32 = 0.08;
+const MAX_OUTLINE_WIDTH: f32 = 3.0;
+
+/// Horizontal alignment of the lines inside a text element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Style of one text element, stored in the element itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextStyle {
+    /// Font family name from the loaded font database; `None` uses
+    /// [`DEFAULT_FONT_FAMILY`].
+    pub family: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub align: TextAlign,
+    /// Fill a rounded rectangle behind the text in the contrast color.
+    pub background: bool,
+    /// Stroke the glyphs in the contrast color.
+    pub outline: bool,
+    /// Line height relative to the font size.
+    pub line_spacing: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            family: None,
+            bold: false,
+            italic: false,
+            underline: false,
+            strikethrough: false,
+            align: TextAlign::Left,
+            background: false,
+            outline: false,
+            line_spacing: DEFAULT_LINE_SPACING,
+        }
+    }
+}
+
+// 行距不会是 NaN，可以安全地作为缓存键
+impl Eq for TextStyle {}
+
+impl Hash for TextStyle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.family.hash(state);
+        self.bold.hash(state);
+        self.italic.hash(state);
+        self.underline.hash(state);
+        self.strikethrough.hash(state);
+        self.align.hash(state);
+        self.background.hash(state);
+        self.outline.hash(state);
+        self.line_spacing.to_bits().hash(state);
+    }
+}
+
+impl TextStyle {
+    pub fn family_name(&self) -> &str {
+        self.family.as_deref().unwrap_or(DEFAULT_FONT_FAMILY)
+    }
+
+    /// Shaping attributes for glyphon.
+    pub fn attrs(&self) -> Attrs<'_> {
+        Attrs::new()
+            .family(Family::Name(self.family_name()))
+            .weight(if self.bold {
+                Weight::BOLD
+            } else {
+                Weight::NORMAL
+            })
+            .style(if self.italic {
+                Style::Italic
+            } else {
+                Style::Normal
+            })
+    }
+
+    /// Line alignment for glyphon; `None` keeps the natural direction.
+    pub fn glyphon_align(&self) -> Option<Align> {
+        match self.align {
+            TextAlign::Left => None,
+            TextAlign::Center => Some(Align::Center),
+            TextAlign::Right => Some(Align::Right),
+        }
+    }
+
+    /// Offsets at which the glyphs are drawn again in the contrast color to
+    /// form the outline; empty when the outline is off.
+    pub fn outline_offsets(&self, font_size: f32) -> Vec<(f32, f32)> {
+        if !self.outline {
+            return Vec::new();
+        }
+        let width = (font_size * OUTLINE_WIDTH).clamp(1.0, MAX_OUTLINE_WIDTH);
+        // 八个方向各绘制一遍，拼出一圈描边
+        (0..8)
+            .map(|step| {
+                let angle = step as f32 * std::f32::consts::FRAC_PI_4;
+                (angle.cos() * width, angle.sin() * width)
+            })
+            .collect()
+    }
+
+    /// Advances to the next line spacing step, wrapping around.
+    pub fn cycle_line_spacing(&mut self) {
+        self.line_spacing = LINE_SPACING_STEPS
+            .iter()
+            .copied()
+            .find(|step| *step > self.line_spacing + f32::EPSILON)
+            .unwrap_or(LINE_SPACING_STEPS[0]);
+    }
+
+    /// Advances to the next family in `families` (the font database), going
+    /// back to the default family after the last one.
+    pub fn cycle_family(&mut self, families: &[String]) {
+        let next = match &self.family {
+            None => families.first(),
+            Some(current) => families
+                .iter()
+                .position(|family| family == current)
+                .and_then(|index| families.get(index + 1)),
+        };
+        self.family = next.cloned();
+    }
+}
+
+/// Black or white, whichever stands out more against `color`; used for the
+/// text background and outline.
+pub fn contrast_color(color: [f32; 3]) -> [f32; 3] {
+    let luminance = 0.2126 * color[0] + 0.7152 * color[1] + 0.0722 * color[2];
+    if luminance > 0.5 {
+        [0.0, 0.0, 0.0]
+    } else {
+        [1.0, 1.0, 1.0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn family_cycle_wraps_back_to_default() {
+        let families = vec!["A".to_string(), "B".to_string()];
+        let mut style = TextStyle::default();
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            style.cycle_family(&families);
+            seen.push(style.family.clone());
+        }
+        assert_eq!(seen, [Some("A".to_string()), Some("B".to_string()), None]);
+
+        // 当前字体不在字体列表中时回到默认字体
+        style.family = Some("Missing".to_string());
+        style.cycle_family(&families);
+        assert_eq!(style.family, None);
+    }
+
+    #[test]
+    fn line_spacing_cycles_through_steps() {
+        let mut style = TextStyle {
+            line_spacing: 1.0,
+            ..TextStyle::default()
+        };
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            style.cycle_line_spacing();
+            seen.push(style.line_spacing);
+        }
+        assert_eq!(seen, [1.2, 1.5, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn contrast_is_black_on_light_and_white_on_dark() {
+        assert_eq!(contrast_color([1.0, 1.0, 0.0]), [0.0, 0.0, 0.0]);
+        assert_eq!(contrast_color([1.0, 0.0, 0.0]), [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn outline_width_is_clamped() {
+        let mut style = TextStyle::default();
+        assert!(style.outline_offsets(24.0).is_empty());
+
+        style.outline = true;
+        let offsets = style.outline_offsets(500.0);
+        assert_eq!(offsets.len(), 8);
+        for (x, y) in offsets {
+            assert!((x.hypot(y) - MAX_OUTLINE_WIDTH).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Top-level window rectangles reported by the window system, used as
+//! capture region candidates next to the ones detected in the screenshot.
+//!
+//! Only X11 exposes the geometry of other applications' windows; on Wayland,
+//! Windows and macOS the list is empty and detection relies on the image.
+
+use crate::detect::Rect;
+
+/// A window as (x, y, width, height) in desktop coordinates.
+pub type WindowRect = (i32, i32, u32, u32);
+
+/// Rectangles of the visible top-level windows on the captured screen, in
+/// screenshot pixels. `origin` is the screen's position on the desktop and
+/// `exclude` the id of the overlay window itself.
+pub fn window_regions(origin: (i32, i32), size: (u32, u32), exclude: Option<u32>) -> Vec<Rect> {
+    match platform::stacked_windows(exclude) {
+        Ok(windows) => visible_regions(&windows, origin, size),
+        Err(e) => {
+            eprintln!("无法获取窗口位置: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Clip `windows` (bottom to top) to the screen at `origin` with `size` and
+/// drop the ones completely hidden behind a single window above them.
+pub fn visible_regions(windows: &[WindowRect], origin: (i32, i32), size: (u32, u32)) -> Vec<Rect> {
+    let clipped: Vec<Option<Rect>> = windows
+        .iter()
+        .map(|&(x, y, width, height)| {
+            let left = (x - origin.0).max(0) as u32;
+            let top = (y - origin.1).max(0) as u32;
+            let right = (x - origin.0 + width as i32).clamp(0, size.0 as i32) as u32;
+            let bottom = (y - origin.1 + height as i32).clamp(0, size.1 as i32) as u32;
+            (left < right && top < bottom).then_some((left, top, right, bottom))
+        })
+        .collect();
+
+    let covers = |outer: Rect, inner: Rect| {
+        outer.0 <= inner.0 && outer.1 <= inner.1 && outer.2 >= inner.2 && outer.3 >= inner.3
+    };
+    clipped
+        .iter()
+        .enumerate()
+        .filter_map(|(index, rect)| {
+            let rect = (*rect)?;
+            let hidden = clipped[index + 1..]
+                .iter()
+                .flatten()
+                .any(|&above| covers(above, rect));
+            (!hidden).then_some(rect)
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::WindowRect;
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, MapState, Window};
+
+    type Error = Box<dyn std::error::Error>;
+
+    /// Client windows from `_NET_CLIENT_LIST_STACKING`, bottom to top,
+    /// including the frame the window manager draws around them.
+    pub fn stacked_windows(exclude: Option<u32>) -> Result<Vec<WindowRect>, Error> {
+        let (conn, screen_num) = x11rb::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        let client_list = atom(&conn, b"_NET_CLIENT_LIST_STACKING")?;
+        let frame_extents = atom(&conn, b"_NET_FRAME_EXTENTS")?;
+
+        let clients: Vec<Window> = conn
+            .get_property(false, root, client_list, AtomEnum::WINDOW, 0, u32::MAX)?
+            .reply()?
+            .value32()
+            .map(|windows| windows.collect())
+            .unwrap_or_default();
+
+        let mut rects = Vec::new();
+        for window in clients {
+            if Some(window) == exclude {
+                continue;
+            }
+            // 窗口可能在查询过程中被关闭，跳过即可
+            let Ok(attributes) = conn.get_window_attributes(window)?.reply() else {
+                continue;
+            };
+            if attributes.map_state != MapState::VIEWABLE {
+                continue;
+            }
+            let (Ok(geometry), Ok(position)) = (
+                conn.get_geometry(window)?.reply(),
+                conn.translate_coordinates(window, root, 0, 0)?.reply(),
+            ) else {
+                continue;
+            };
+            // 左、右、上、下边框
+            let [left, right, top, bottom] = conn
+                .get_property(false, window, frame_extents, AtomEnum::CARDINAL, 0, 4)?
+                .reply()
+                .ok()
+                .and_then(|reply| reply.value32().map(|values| values.collect::<Vec<_>>()))
+                .and_then(|values| <[u32; 4]>::try_from(values).ok())
+                .unwrap_or_default();
+            rects.push((
+                position.dst_x as i32 - left as i32,
+                position.dst_y as i32 - top as i32,
+                geometry.width as u32 + left + right,
+                geometry.height as u32 + top + bottom,
+            ));
+        }
+        Ok(rects)
+    }
+
+    fn atom(conn: &impl Connection, name: &[u8]) -> Result<u32, Error> {
+        Ok(conn.intern_atom(false, name)?.reply()?.atom)
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod platform {
+    use super::WindowRect;
+
+    pub fn stacked_windows(_exclude: Option<u32>) -> Result<Vec<WindowRect>, String> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_are_clipped_to_the_captured_screen() {
+        // 第二块屏幕位于 (1920, 0)
+        let windows = [
+            (1800, -20, 400, 300),
+            (2000, 100, 200, 100),
+            (100, 100, 50, 50),
+        ];
+        assert_eq!(
+            visible_regions(&windows, (1920, 0), (1920, 1080)),
+            vec![(0, 0, 280, 280), (80, 100, 280, 200)]
+        );
+    }
+
+    #[test]
+    fn windows_hidden_behind_a_higher_one_are_dropped() {
+        // 从下到上：被遮住的小窗口、大窗口、部分重叠的窗口
+        let windows = [(100, 100, 50, 50), (50, 50, 400, 300), (400, 300, 200, 200)];
+        assert_eq!(
+            visible_regions(&windows, (0, 0), (800, 600)),
+            vec![(50, 50, 450, 350), (400, 300, 600, 500)]
+        );
+    }
+}